@@ -1,5 +1,3 @@
-use risc::instructions::Instruction;
-
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -15,13 +13,15 @@ struct Opt {
 #[cfg(not(tarpaulin_include))]
 fn main() -> std::io::Result<()> {
     let opt = Opt::from_args();
+    // The object is named after the file, and other objects refer to its labels as `@Name.LABEL`
+    let module = opt.input.file_stem().and_then(|stem| stem.to_str()).expect("Filename is malformed.").to_string();
     let filename = opt.input.into_os_string().into_string().expect("Filename is malformed.");
     let content = std::fs::read_to_string(&filename).unwrap_or_else(|_| panic!("Unable to open file {:?}", &filename));
 
-    match assembler::assemble(&content) {
-        Ok(instructions) => {
-            let encoded = Instruction::serialize_all(instructions);
-            std::fs::write("out.o", &encoded[..]).expect("Unable to write output to file");
+    match assembler::assemble_object(&module, &content) {
+        Ok(object) => {
+            let encoded = object.serialize();
+            std::fs::write(format!("{}.o", module), &encoded[..]).expect("Unable to write output to file");
         }
         Err(err) => {
             println!("Assembly error: {:?}", err);
@@ -1,5 +1,9 @@
 
-use risc::instructions::Instruction;
+use ast::scope::Symbol;
+use compiler::diagnostics::Format;
+use compiler::ParseError;
+
+use std::rc::Rc;
 
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -11,6 +15,34 @@ struct Opt {
     /// Assembly language file
     #[structopt(name = "FILE", parse(from_os_str))]
     input: PathBuf,
+
+    /// Do not check array indices at runtime
+    #[structopt(long)]
+    no_bounds_checks: bool,
+
+    /// Directory where the symbol files of imported modules are looked up, after the current one
+    #[structopt(short = "I", long, number_of_values = 1, parse(from_os_str))]
+    import_path: Vec<PathBuf>,
+
+    /// How compilation errors are displayed: plain, ansi (with colors) or json (one error per line)
+    #[structopt(long, default_value = "plain")]
+    error_format: Format,
+}
+
+// Symbol files are named after their module, like `Shapes.sym`
+#[cfg(not(tarpaulin_include))]
+fn read_symbol_file(import_path: &[PathBuf], module: &str) -> Result<Vec<Rc<Symbol>>, ParseError> {
+    let filename = format!("{}.sym", module);
+    let content = std::iter::once(PathBuf::from("."))
+        .chain(import_path.iter().cloned())
+        .find_map(|directory| std::fs::read_to_string(directory.join(&filename)).ok())
+        .ok_or_else(|| ParseError::UndefinedModule(String::from(module)))?;
+
+    let (name, symbols) = ast::symbol_file::read(&content)?;
+    if name != module {
+        return Err(ParseError::UndefinedModule(String::from(module)));
+    }
+    Ok(symbols)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -23,13 +55,20 @@ fn main() {
 
     let content = std::fs::read_to_string(&filename).unwrap_or_else(|_| panic!("Unable to open file {:?}", filename));
 
-    match compiler::compile(&content) {
-        Ok(instructions) => {
-            let encoded = Instruction::serialize_all(instructions);
-            std::fs::write("out.o", &encoded[..]).expect("Unable to write output to file");
+    let options = compiler::CompileOptions {
+        bounds_checks: !opt.no_bounds_checks,
+    };
+
+    let import_path = opt.import_path;
+    match compiler::compile_with_recovery(&content, options, &mut |module| read_symbol_file(&import_path, module)) {
+        Ok(program) => {
+            std::fs::write(format!("{}.sym", program.module), &program.symbol_file).expect("Unable to write symbol file");
+            // Objects are named after their module, like symbol files
+            let encoded = program.object().serialize();
+            std::fs::write(format!("{}.o", program.module), &encoded[..]).expect("Unable to write output to file");
         }
-        Err(err) => {
-            println!("Compilation error: {:?}", err);
+        Err(errors) => {
+            eprint!("{}", compiler::diagnostics::render_all(&errors, &filename, &content, opt.error_format));
             std::process::exit(-1);
         }
     }
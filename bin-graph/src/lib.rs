@@ -1,140 +1,332 @@
 #![allow(dead_code)]
 
-use crate::ast::Ast;
 use ::ast::{
-    ast::is_empty,
-    tree::{ExpressionOp, NodeInfo, TermOp, Tree, VarType},
+    syntax::{Decl, Designator, Expr, Module, Selector, SetElement, Stmt},
+    tree::{ExpressionOp, SimpleExpressionOp, TermOp, VarType},
 };
-use ast::ast;
-use log::debug;
 
-pub fn to_dot(ast: &Ast) -> String {
-    let mut s = String::from("digraph G {\n");
-
-    let mut grow = vec![]; // Yes, this is stupid
-    push_strings(&mut s, ast, &mut grow);
+pub fn to_dot(module: &Module) -> String {
+    let mut graph = Graph::new();
+    graph.module(module);
+    graph.finish()
+}
 
-    s.push_str("}\n");
+// Nodes are numbered in the order they are visited, parents before their children
+struct Graph {
+    dot: String,
+    nodes: usize,
+}
 
-    debug!("{:?}", grow);
+impl Graph {
+    fn new() -> Graph {
+        Graph {
+            dot: String::from("digraph G {\n"),
+            nodes: 0,
+        }
+    }
 
-    s.to_string()
-}
+    fn finish(mut self) -> String {
+        self.dot.push_str("}\n");
+        self.dot
+    }
 
-fn push_strings(s: &mut String, ast: &Ast, grow: &mut Vec<u32>) {
-    match ast.as_ref() {
-        Tree::Nil => {}
-        Tree::Node(node) => {
-            let node_id = format!("{}", grow.len());
-            s.push_str(format!("node{node_id}[label=\"").as_str());
-            s.push_str(node_label(&node.info).as_str());
-            s.push_str("\"];\n");
-
-            if !is_empty(&node.child) {
-                let l = grow.len() as u32;
-                grow.push(l);
-                push_strings(s, &node.child, grow);
-                s.push_str(format!("node{}->node{};\n", node_id, l + 1).as_str());
-            }
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        self.dot.push_str(format!("node{id}[label=\"{label}\"];\n").as_str());
+        id
+    }
 
-            if !is_empty(&node.sibling) {
-                let l = grow.len() as u32;
-                grow.push(grow.len() as u32);
+    // The edge to a child is added once the whole child is in the graph
+    fn edge(&mut self, parent: usize, child: usize) {
+        self.dot.push_str(format!("node{parent}->node{child};\n").as_str());
+    }
 
-                push_strings(s, &node.sibling, grow);
-                s.push_str(format!("node{}->node{};\n", node_id, l + 1).as_str());
-            }
-        }
+    fn leaf(&mut self, parent: usize, label: &str) {
+        let child = self.node(label);
+        self.edge(parent, child);
     }
 
-    s.push_str("")
-}
+    fn module(&mut self, module: &Module) -> usize {
+        let id = self.node("Module");
+        self.leaf(id, &module.name.name);
+        let declarations = self.declarations(&module.declarations);
+        self.edge(id, declarations);
+        let body = self.statements(&module.body);
+        self.edge(id, body);
+        id
+    }
 
-fn node_label(node_info: &NodeInfo) -> String {
-    match node_info {
-        NodeInfo::Constant(number) => {
-            format!("{number}")
-        }
-        NodeInfo::Term(TermOp::Times) => {
-            "*".to_string()
-        }
-        NodeInfo::Term(TermOp::Div) => {
-            "/".to_string()
-        }
-        NodeInfo::SimpleExpression(::ast::tree::SimpleExpressionOp::Plus) => {
-            "+".to_string()
-        }
-        NodeInfo::SimpleExpression(::ast::tree::SimpleExpressionOp::Minus) => {
-            "-".to_string()
+    fn declarations(&mut self, declarations: &[Decl]) -> usize {
+        let id = self.node("Declarations");
+        for declaration in declarations.iter() {
+            let child = self.declaration(declaration);
+            self.edge(id, child);
         }
+        id
+    }
 
-        NodeInfo::Module => {
-            "Module".to_string()
-        }
-        NodeInfo::Declaration => {
-            "Declaration".to_string()
-        }
-        NodeInfo::Declarations => {
-            "Declarations".to_string()
-        }
-        NodeInfo::Var => {
-            "Var".to_string()
-        }
-        NodeInfo::IfStatement => {
-            "If".to_string()
+    fn declaration(&mut self, declaration: &Decl) -> usize {
+        match declaration {
+            Decl::Var { symbol, var_type, .. } => {
+                let id = self.node("Var");
+                self.leaf(id, &symbol.name);
+                self.leaf(id, &type_label(var_type));
+                id
+            }
+            Decl::Procedure { symbol, declarations, body, .. } => {
+                let id = self.node(format!("Procedure {}", symbol.name).as_str());
+                let child = self.declarations(declarations);
+                self.edge(id, child);
+                let child = self.statements(body);
+                self.edge(id, child);
+                id
+            }
         }
+    }
 
-        NodeInfo::Then => {
-            "Then".to_string()
+    fn statements(&mut self, statements: &[Stmt]) -> usize {
+        let id = self.node("StatSeq");
+        for statement in statements.iter() {
+            let child = self.statement(statement);
+            self.edge(id, child);
         }
+        id
+    }
 
-        NodeInfo::Else => {
-            "Else".to_string()
-        }
-        NodeInfo::WhileStatement => {
-            "While".to_string()
-        }
-        NodeInfo::Do => {
-            "Do".to_string()
-        }
-        NodeInfo::StatementSequence => {
-            "StatSeq".to_string()
-        }
-        NodeInfo::Type(VarType::Integer) => {
-            "Integer".to_string()
-        }
-        NodeInfo::Type(VarType::Array(n)) => {
-            format!("Array[{n}]")
-        }
-        NodeInfo::Assignement => {
-            ":=".to_string()
-        }
+    // Statements of a part of a compound statement, like the THEN part of an IF
+    fn part(&mut self, parent: usize, label: &str, statements: &[Stmt]) {
+        let id = self.node(label);
+        let child = self.statements(statements);
+        self.edge(id, child);
+        self.edge(parent, id);
+    }
 
-        NodeInfo::Expression(ExpressionOp::Eql) => {
-            "=".to_string()
-        }
-        NodeInfo::Expression(ExpressionOp::Neq) => {
-            "!=".to_string()
-        }
-        NodeInfo::Expression(ExpressionOp::Lss) => {
-            "<".to_string()
+    fn statement(&mut self, statement: &Stmt) -> usize {
+        match statement {
+            Stmt::Assignment { target, value, .. } => {
+                let id = self.node(":=");
+                self.designator_child(id, target);
+                self.expression_child(id, value);
+                id
+            }
+            Stmt::ProcedureCall { procedure, parameters, .. } => {
+                let id = self.node(format!("Call {}", procedure.name).as_str());
+                self.parameters(id, parameters);
+                id
+            }
+            Stmt::StandardProcedureCall { procedure, parameters, .. } => {
+                let id = self.node(procedure.name());
+                self.parameters(id, parameters);
+                id
+            }
+            Stmt::If { branches, else_branch, .. } => {
+                let id = self.node("If");
+                for (index, branch) in branches.iter().enumerate() {
+                    if index == 0 {
+                        self.expression_child(id, &branch.condition);
+                        self.part(id, "Then", &branch.body);
+                    } else {
+                        let elsif = self.node("Elsif");
+                        self.expression_child(elsif, &branch.condition);
+                        self.part(elsif, "Then", &branch.body);
+                        self.edge(id, elsif);
+                    }
+                }
+                if let Some(else_branch) = else_branch {
+                    self.part(id, "Else", else_branch);
+                }
+                id
+            }
+            Stmt::While { condition, body, .. } => {
+                let id = self.node("While");
+                self.expression_child(id, condition);
+                self.part(id, "Do", body);
+                id
+            }
+            Stmt::Repeat { body, condition, .. } => {
+                let id = self.node("Repeat");
+                let child = self.statements(body);
+                self.edge(id, child);
+                self.expression_child(id, condition);
+                id
+            }
+            Stmt::For {
+                control,
+                begin,
+                limit,
+                step,
+                body,
+                ..
+            } => {
+                let id = self.node(format!("For (by {step})").as_str());
+                self.designator_child(id, control);
+                self.expression_child(id, begin);
+                let to = self.node("To");
+                self.expression_child(to, limit);
+                self.edge(id, to);
+                self.part(id, "Do", body);
+                id
+            }
+            Stmt::Case { selector, cases, else_branch, .. } => {
+                let id = self.node("Case");
+                self.expression_child(id, selector);
+                for case in cases.iter() {
+                    self.part(id, &labels_label(&case.labels), &case.body);
+                }
+                if let Some(else_branch) = else_branch {
+                    self.part(id, "Else", else_branch);
+                }
+                id
+            }
         }
-        NodeInfo::Expression(ExpressionOp::Leq) => {
-            "<=".to_string()
+    }
+
+    fn parameters(&mut self, parent: usize, parameters: &[Expr]) {
+        for parameter in parameters.iter() {
+            self.expression_child(parent, parameter);
         }
-        NodeInfo::Expression(ExpressionOp::Gtr) => {
-            ">".to_string()
+    }
+
+    fn expression_child(&mut self, parent: usize, expression: &Expr) {
+        let child = self.expression(expression);
+        self.edge(parent, child);
+    }
+
+    fn expression(&mut self, expression: &Expr) -> usize {
+        match expression {
+            Expr::Constant { value, .. } => self.node(format!("{value}").as_str()),
+            Expr::Boolean { value, .. } => self.node(format!("{}", value).to_uppercase().as_str()),
+            Expr::Char { value, .. } => self.node(format!("{:02X}X", value).as_str()),
+            // Quotes have to be escaped inside dot labels
+            Expr::String { value, .. } => self.node(format!("\\\"{value}\\\"").as_str()),
+            Expr::Set { value, .. } => self.node(&set_label(*value)),
+            Expr::SetConstructor { elements, .. } => {
+                let id = self.node("{}");
+                for element in elements.iter() {
+                    match element {
+                        SetElement::Single(value) => self.expression_child(id, value),
+                        SetElement::Range { low, high, .. } => {
+                            let range = self.node("..");
+                            self.expression_child(range, low);
+                            self.expression_child(range, high);
+                            self.edge(id, range);
+                        }
+                    }
+                }
+                id
+            }
+            Expr::Designator(designator) => self.designator(designator),
+            Expr::Relation { operator, left, right, .. } => self.binary(relation_label(*operator), left, right),
+            Expr::Addition { operator, left, right, .. } => self.binary(addition_label(*operator), left, right),
+            Expr::Multiplication { operator, left, right, .. } => self.binary(multiplication_label(*operator), left, right),
+            Expr::Not { operand, .. } => {
+                let id = self.node("~");
+                self.expression_child(id, operand);
+                id
+            }
+            Expr::Negate { operand, .. } => {
+                let id = self.node("Neg");
+                self.expression_child(id, operand);
+                id
+            }
+            Expr::StandardFunctionCall { procedure, parameters, .. } => {
+                let id = self.node(procedure.name());
+                self.parameters(id, parameters);
+                id
+            }
         }
-        NodeInfo::Expression(ExpressionOp::Geq) => {
-            ">=".to_string()
+    }
+
+    fn binary(&mut self, label: &str, left: &Expr, right: &Expr) -> usize {
+        let id = self.node(label);
+        self.expression_child(id, left);
+        self.expression_child(id, right);
+        id
+    }
+
+    fn designator_child(&mut self, parent: usize, designator: &Designator) {
+        let child = self.designator(designator);
+        self.edge(parent, child);
+    }
+
+    // Selectors are children of the variable, in the order they apply
+    fn designator(&mut self, designator: &Designator) -> usize {
+        let id = self.node(&designator.symbol.name);
+        for selector in designator.selectors.iter() {
+            match selector {
+                Selector::Field { name, .. } => self.leaf(id, format!(".{name}").as_str()),
+                Selector::Index { index, .. } => {
+                    let child = self.node("[]");
+                    self.expression_child(child, index);
+                    self.edge(id, child);
+                }
+            }
         }
+        id
+    }
+}
+
+fn multiplication_label(operator: TermOp) -> &'static str {
+    match operator {
+        TermOp::Times => "*",
+        TermOp::Div => "DIV",
+        TermOp::Mod => "MOD",
+        TermOp::And => "&",
+        TermOp::Intersection => "*",
+        TermOp::SymmetricDifference => "/",
+    }
+}
+
+fn addition_label(operator: SimpleExpressionOp) -> &'static str {
+    match operator {
+        SimpleExpressionOp::Plus => "+",
+        SimpleExpressionOp::Minus => "-",
+        SimpleExpressionOp::Or => "OR",
+        SimpleExpressionOp::Union => "+",
+        SimpleExpressionOp::Difference => "-",
+    }
+}
+
+fn relation_label(operator: ExpressionOp) -> &'static str {
+    match operator {
+        ExpressionOp::Eql => "=",
+        ExpressionOp::Neq => "!=",
+        ExpressionOp::Lss => "<",
+        ExpressionOp::Leq => "<=",
+        ExpressionOp::Gtr => ">",
+        ExpressionOp::Geq => ">=",
+        ExpressionOp::In => "IN",
+    }
+}
 
-        NodeInfo::Ident(ident) => {
-            format!("{}", ident.name)
+fn type_label(var_type: &VarType) -> String {
+    match var_type {
+        VarType::Integer => "Integer".to_string(),
+        VarType::Boolean => "Boolean".to_string(),
+        VarType::Char => "Char".to_string(),
+        VarType::Set => "Set".to_string(),
+        VarType::Array(n, _) => format!("Array[{n}]"),
+        VarType::Record(fields) => {
+            let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+            format!("Record({})", names.join(", "))
         }
     }
 }
 
+fn labels_label(labels: &[(i32, i32)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(low, high)| if low == high { format!("{low}") } else { format!("{low}..{high}") })
+        .collect();
+    labels.join(", ")
+}
+
+fn set_label(value: u32) -> String {
+    let elements: Vec<String> = (0..32).filter(|bit| value & (1 << bit) != 0).map(|bit| format!("{bit}")).collect();
+    format!("{{{}}}", elements.join(", "))
+}
+
 #[cfg(test)]
 mod tests {
     use std::rc::Rc;
@@ -142,27 +334,71 @@ mod tests {
     use super::*;
 
     use ::ast::{
-        scope::Symbol,
-        tree::{ExpressionOp, NodeInfo, SimpleExpressionOp, TermOp, VarType},
+        scope::{Symbol, SymbolKind},
+        syntax::Expr,
+        token::Span,
+        tree::{ExpressionOp, Field, SimpleExpressionOp, TermOp, VarType},
     };
     use test_log::test;
 
+    fn constant(value: i32) -> Expr {
+        Expr::Constant { value, span: Span::default() }
+    }
+
+    fn symbol(name: &str) -> Rc<Symbol> {
+        Rc::new(Symbol {
+            name: name.to_string(),
+            adr: 0,
+            size: 0,
+            level: 0,
+            kind: SymbolKind::Variable,
+            declared_type: Some(VarType::Integer),
+            export: None,
+            module: None,
+        })
+    }
+
     #[test]
-    fn is_empty_for_null() {
-        let ast = ast::empty();
-        assert_eq!("digraph G {\n}\n", to_dot(&ast))
+    fn is_small_for_empty_module() {
+        let module = Module {
+            name: symbol("Empty"),
+            declarations: vec![],
+            body: vec![],
+            span: Span::default(),
+        };
+        assert_eq!(
+            "digraph G {
+node0[label=\"Module\"];
+node1[label=\"Empty\"];
+node0->node1;
+node2[label=\"Declarations\"];
+node0->node2;
+node3[label=\"StatSeq\"];
+node0->node3;
+}
+",
+            to_dot(&module)
+        )
     }
 
     #[test]
     fn is_simple_graph() {
-        // 2 * (42 * 10)
-        let x = ast::leaf(NodeInfo::Constant(42));
-        let y = ast::leaf(NodeInfo::Constant(10));
-        let xy = ast::node(NodeInfo::SimpleExpression(SimpleExpressionOp::Plus), x, y);
-        let z = ast::leaf(NodeInfo::Constant(2));
-        let zxy = ast::node(NodeInfo::Term(TermOp::Times), z, xy);
-
-        println!("{}", to_dot(&zxy));
+        // 2 * (42 + 10)
+        let xy = Expr::Addition {
+            operator: SimpleExpressionOp::Plus,
+            left: Box::new(constant(42)),
+            right: Box::new(constant(10)),
+            span: Span::default(),
+        };
+        let zxy = Expr::Multiplication {
+            operator: TermOp::Times,
+            left: Box::new(constant(2)),
+            right: Box::new(xy),
+            span: Span::default(),
+        };
+
+        let mut graph = Graph::new();
+        graph.expression(&zxy);
 
         assert_eq!(
             "digraph G {
@@ -177,52 +413,67 @@ node2->node4;
 node0->node2;
 }
 ",
-            to_dot(&zxy)
+            graph.finish()
         );
     }
 
     #[test]
-    fn can_format_node_infos() {
-        assert_eq!("Module", node_label(&NodeInfo::Module));
-
-        assert_eq!("Declarations", node_label(&NodeInfo::Declarations));
-        assert_eq!("Declaration", node_label(&NodeInfo::Declaration));
-        assert_eq!("Var", node_label(&NodeInfo::Var));
-
-        assert_eq!("*", node_label(&NodeInfo::Term(TermOp::Times)));
-        assert_eq!("/", node_label(&NodeInfo::Term(TermOp::Div)));
-
-        assert_eq!("+", node_label(&NodeInfo::SimpleExpression(SimpleExpressionOp::Plus)));
-        assert_eq!("-", node_label(&NodeInfo::SimpleExpression(SimpleExpressionOp::Minus)));
-        assert_eq!("If", node_label(&NodeInfo::IfStatement));
-        assert_eq!("Then", node_label(&NodeInfo::Then));
-        assert_eq!("Else", node_label(&NodeInfo::Else));
-        assert_eq!("While", node_label(&NodeInfo::WhileStatement));
-        assert_eq!("Do", node_label(&NodeInfo::Do));
-
-        assert_eq!("Integer", node_label(&NodeInfo::Type(VarType::Integer)));
-        assert_eq!("Array[10]", node_label(&NodeInfo::Type(VarType::Array(10))));
-
-        assert_eq!("StatSeq", node_label(&NodeInfo::StatementSequence));
-
-        assert_eq!(":=", node_label(&NodeInfo::Assignement));
-
-        assert_eq!("=", node_label(&NodeInfo::Expression(ExpressionOp::Eql)));
-        assert_eq!("!=", node_label(&NodeInfo::Expression(ExpressionOp::Neq)));
-        assert_eq!("<", node_label(&NodeInfo::Expression(ExpressionOp::Lss)));
-        assert_eq!("<=", node_label(&NodeInfo::Expression(ExpressionOp::Leq)));
-        assert_eq!(">", node_label(&NodeInfo::Expression(ExpressionOp::Gtr)));
-        assert_eq!(">=", node_label(&NodeInfo::Expression(ExpressionOp::Geq)));
-
-        assert_eq!("42", node_label(&NodeInfo::Constant(42)));
+    fn can_format_labels() {
+        assert_eq!("*", multiplication_label(TermOp::Times));
+        assert_eq!("DIV", multiplication_label(TermOp::Div));
+        assert_eq!("MOD", multiplication_label(TermOp::Mod));
+        assert_eq!("&", multiplication_label(TermOp::And));
+
+        assert_eq!("+", addition_label(SimpleExpressionOp::Plus));
+        assert_eq!("-", addition_label(SimpleExpressionOp::Minus));
+        assert_eq!("OR", addition_label(SimpleExpressionOp::Or));
+
+        assert_eq!("1, 3..5", labels_label(&[(1, 1), (3, 5)]));
+
+        assert_eq!("Integer", type_label(&VarType::Integer));
+        assert_eq!("Array[10]", type_label(&VarType::Array(10, Rc::new(VarType::Integer))));
+        let point = vec![
+            Field {
+                name: String::from("x"),
+                offset: 0,
+                field_type: VarType::Integer,
+            },
+            Field {
+                name: String::from("y"),
+                offset: 1,
+                field_type: VarType::Integer,
+            },
+        ];
+        assert_eq!("Record(x, y)", type_label(&VarType::Record(Rc::new(point))));
+
+        assert_eq!("=", relation_label(ExpressionOp::Eql));
+        assert_eq!("!=", relation_label(ExpressionOp::Neq));
+        assert_eq!("<", relation_label(ExpressionOp::Lss));
+        assert_eq!("<=", relation_label(ExpressionOp::Leq));
+        assert_eq!(">", relation_label(ExpressionOp::Gtr));
+        assert_eq!(">=", relation_label(ExpressionOp::Geq));
+        assert_eq!("IN", relation_label(ExpressionOp::In));
+        assert_eq!("{1, 3, 4}", set_label(0b11010));
+    }
 
-        assert_eq!(
-            "x",
-            node_label(&NodeInfo::Ident(Rc::new(Symbol {
-                name: "x".to_string(),
-                adr: 0,
-                size: 0
-            })))
-        );
+    #[test]
+    fn can_format_constants() {
+        let label = |expression: Expr| {
+            let mut graph = Graph::new();
+            graph.expression(&expression);
+            graph.finish()
+        };
+        assert!(label(constant(-42)).contains("[label=\"-42\"]"));
+        assert!(label(Expr::Boolean {
+            value: true,
+            span: Span::default()
+        })
+        .contains("[label=\"TRUE\"]"));
+        assert!(label(Expr::Char { value: 65, span: Span::default() }).contains("[label=\"41X\"]"));
+        assert!(label(Expr::String {
+            value: String::from("abc"),
+            span: Span::default()
+        })
+        .contains("[label=\"\\\"abc\\\"\"]"));
     }
 }
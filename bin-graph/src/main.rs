@@ -1,5 +1,6 @@
 
 
+use compiler::diagnostics::Format;
 use log::debug;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -11,6 +12,10 @@ struct Opt {
     /// Assembly language file
     #[structopt(name = "FILE", parse(from_os_str))]
     input: PathBuf,
+
+    /// How parsing errors are displayed: plain, ansi (with colors) or json (one error per line)
+    #[structopt(long, default_value = "plain")]
+    error_format: Format,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -24,14 +29,14 @@ fn main() {
 
     let content = std::fs::read_to_string(&filename).unwrap_or_else(|_| panic!("Unable to open file {:?}", filename));
 
-    match compiler::build_ast(&content) {
-        Ok(ast) => {
-            debug!("Built ast {:?}", ast);
+    match compiler::build_module(&content) {
+        Ok(module) => {
+            debug!("Built module {:?}", module);
 
-            println!("{:}", to_dot(&ast));
+            println!("{:}", to_dot(&module));
         }
         Err(err) => {
-            println!("Parsing error: {:?}", err);
+            eprint!("{}", compiler::diagnostics::render(&err, &filename, &content, opt.error_format));
             std::process::exit(-1);
         }
     }
@@ -0,0 +1,44 @@
+use risc::object::Object;
+
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Link object files (compiled from assembly or oberon-0) into an image to load in the risc computer
+#[derive(StructOpt, Debug)]
+#[structopt(name = "cli-risc", version = "0.0.1")]
+struct Opt {
+    /// Object files, the first one is executed
+    #[structopt(name = "FILE", required = true, parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+
+    /// Image file
+    #[structopt(short, long, default_value = "out.bin", parse(from_os_str))]
+    output: PathBuf,
+}
+
+#[cfg(not(tarpaulin_include))]
+fn main() {
+    let opt = Opt::from_args();
+
+    let mut objects = vec![];
+    for input in opt.inputs.iter() {
+        let content = std::fs::read(input).unwrap_or_else(|_| panic!("Unable to open file {:?}", input));
+        match Object::deserialize(&content) {
+            Ok(object) => objects.push(object),
+            Err(err) => {
+                println!("Invalid object file {:?}: {:?}", input, err);
+                std::process::exit(-1);
+            }
+        }
+    }
+
+    match linker::link(&objects) {
+        Ok(image) => {
+            std::fs::write(&opt.output, &image.serialize()[..]).expect("Unable to write output to file");
+        }
+        Err(err) => {
+            println!("Link error: {:?}", err);
+            std::process::exit(-1);
+        }
+    }
+}
@@ -22,8 +22,8 @@ struct Opt {
     // #[structopt(short = "m", name = "max-cyles", default_value = "9999")]
     // execution_max_cycles: u32,
 
-    /// Stack base address when simulating process
-    #[structopt(short = "s", name = "stack-base", long, default_value = "1000")]
+    /// Stack base address when simulating process, the stack grows down from the end of the memory by default
+    #[structopt(short = "s", name = "stack-base", long, default_value = "4096")]
     execution_stack_base: usize,
 
     /// Memory position to dump data from
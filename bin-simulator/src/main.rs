@@ -1,4 +1,7 @@
+use compiler::diagnostics::Format;
+use risc::instructions::{Instruction, MAX_TRAP_LINE};
 use simulator::Execution;
+use simulator::ExecutionError;
 use simulator::Simulator;
 
 use std::path::PathBuf;
@@ -18,12 +21,20 @@ struct Opt {
     #[structopt(short, long)]
     compile: bool,
 
+    /// Treat input as an image produced by the linker
+    #[structopt(short, long, conflicts_with = "compile")]
+    image: bool,
+
+    /// How compilation errors are displayed: plain, ansi (with colors) or json (one error per line)
+    #[structopt(long, default_value = "plain")]
+    error_format: Format,
+
     /// Maximum number of cycles to run before failing execution
     #[structopt(short = "m", name = "max-cyles", default_value = "99999")]
     execution_max_cycles: u32,
 
-    /// Stack base address when simulating process
-    #[structopt(short = "s", name = "stack-base", long, default_value = "1000")]
+    /// Stack base address when simulating process, the stack grows down from the end of the memory by default
+    #[structopt(short = "s", name = "stack-base", long, default_value = "4096")]
     execution_stack_base: usize,
 
     /// Memory position to dump instruction from
@@ -61,17 +72,58 @@ fn dump_mem(s: &Simulator, from: usize, count: usize) {
     }
 }
 
+// Line of the Oberon source a trap comes from, with its text when the program was compiled from it
+#[cfg(not(tarpaulin_include))]
+fn source_line(source: Option<&str>, line: u32) -> String {
+    if line == MAX_TRAP_LINE {
+        return format!("line {} or later", line);
+    }
+    let text = line.checked_sub(1).and_then(|index| source?.lines().nth(index as usize));
+    match text {
+        Some(text) => format!("line {}: {}", line, text.trim()),
+        None => format!("line {}", line),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn describe(error: &ExecutionError, max_cycles: u32, source: Option<&str>) -> String {
+    match *error {
+        ExecutionError::MaxCycleReached => format!("execution stopped after {} instructions", max_cycles),
+        ExecutionError::ArrayIndexOutOfBounds { line, index } => {
+            format!("index {} is out of the bounds of the array, at {}", index, source_line(source, line))
+        }
+        ExecutionError::DivisionByZero { dividend } => format!("division of {} by zero", dividend),
+        ExecutionError::AssertionFailed { line } => format!("assertion failed, at {}", source_line(source, line)),
+        ExecutionError::Halted { line, code } => format!("halted with code {}, at {}", code, source_line(source, line)),
+        ExecutionError::InvalidAddress { address } => format!("no memory or device at address {}", address),
+        ExecutionError::CaseNotMatched { line, selector } => {
+            format!("no label of the CASE matches {}, at {}", selector, source_line(source, line))
+        }
+    }
+}
+
 #[cfg(not(tarpaulin_include))]
 fn main() {
     let opt = Opt::from_args();
 
     let filename = opt.input.into_os_string().into_string().expect("Filename is malformed.");
 
-    let content = std::fs::read_to_string(filename).expect("Unable to read from input file.");
-
-    let mut simulator = if opt.compile {
-        simulator::Simulator::from_oberon(&content).unwrap()
+    // Traps point to lines of the Oberon source, if there is one
+    let mut source = None;
+    let mut simulator = if opt.image {
+        let content = std::fs::read(filename).expect("Unable to read from input file.");
+        simulator::Simulator::from_image(&Instruction::deserialize_image(&content))
+    } else if opt.compile {
+        let content = std::fs::read_to_string(&filename).expect("Unable to read from input file.");
+        let error_format = opt.error_format;
+        let simulator = simulator::Simulator::from_oberon(&content).unwrap_or_else(|err| {
+            eprint!("{}", compiler::diagnostics::render(&err, &filename, &content, error_format));
+            std::process::exit(-1);
+        });
+        source = Some(content);
+        simulator
     } else {
+        let content = std::fs::read_to_string(filename).expect("Unable to read from input file.");
         simulator::Simulator::from_assembler(&content).unwrap()
     };
 
@@ -82,16 +134,22 @@ fn main() {
     println!("--- Instructions ---");
     dump_mem(&simulator, opt.instruction_dump_from, opt.instruction_dump_count);
 
+    // Integers read by the program come from stdin, which is only read when needed
+    simulator.set_input(
+        std::io::stdin()
+            .lines()
+            .map_while(Result::ok)
+            .flat_map(|line| line.split_whitespace().filter_map(|word| word.parse::<i32>().ok()).collect::<Vec<_>>()),
+    );
+
     // Execute
     println!(">>>>>>");
     println!("Executing program...");
 
-    let success = simulator
-        .execute(Execution {
-            max_cycles: opt.execution_max_cycles,
-            stack_base: opt.execution_stack_base,
-        })
-        .is_ok();
+    let result = simulator.execute(Execution {
+        max_cycles: opt.execution_max_cycles,
+        stack_base: opt.execution_stack_base,
+    });
 
     println!("<<<<<<");
     println!("After execution:");
@@ -101,11 +159,14 @@ fn main() {
     dump_mem(&simulator, opt.instruction_dump_from, opt.instruction_dump_count);
     println!("... Memory ---");
     dump_mem(&simulator, opt.memory_dump_from, opt.memory_dump_count);
-
-    if success {
-        println!("Program run successfully.");
-    } else {
-        println!("Warning: execution stopped after {:?} instructions", opt.execution_max_cycles);
-        std::process::exit(1);
+    println!("--- Output ---");
+    print!("{}", simulator.output());
+
+    match result {
+        Ok(()) => println!("Program run successfully."),
+        Err(error) => {
+            eprintln!("Error: {}", describe(&error, opt.execution_max_cycles, source.as_deref()));
+            std::process::exit(1);
+        }
     }
 }
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 use std::rc::Rc;
 
+use crate::token::Span;
 use crate::tree::NodeInfo;
 use crate::tree::Tree;
 use crate::tree::TreeNode;
@@ -72,15 +73,31 @@ pub fn child(ast: &Ast) -> Option<&Ast> {
 }
 
 pub fn leaf(node_info: NodeInfo) -> Ast {
+    leaf_at(node_info, Span::default())
+}
+
+pub fn node(node_info: NodeInfo, child: Ast, sibling: Ast) -> Ast {
+    node_at(node_info, child, sibling, Span::default())
+}
+
+pub fn leaf_at(node_info: NodeInfo, span: Span) -> Ast {
+    node_at(node_info, empty(), empty(), span)
+}
+
+pub fn node_at(node_info: NodeInfo, child: Ast, sibling: Ast, span: Span) -> Ast {
     Rc::new(Tree::Node(TreeNode {
         info: node_info,
-        child: empty(),
-        sibling: empty(),
+        child,
+        sibling,
+        span,
     }))
 }
 
-pub fn node(node_info: NodeInfo, child: Ast, sibling: Ast) -> Ast {
-    Rc::new(Tree::Node(TreeNode { info: node_info, child, sibling }))
+pub fn span(ast: &Ast) -> Option<Span> {
+    match ast.as_ref() {
+        Tree::Node(TreeNode { span, .. }) => Some(*span),
+        Tree::Nil => None,
+    }
 }
 
 pub fn is_empty(ast: &Ast) -> bool {
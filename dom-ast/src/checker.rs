@@ -0,0 +1,317 @@
+// Semantic analysis, once the whole module is parsed.
+//
+// The parser resolves symbols, fields and constants, but it does not look at the types of expressions.
+// This pass computes the type of every expression, and checks that operators, statements and calls
+// are given operands of the expected type.
+// Errors are located at the expression or designator they are found in.
+use std::rc::Rc;
+
+use crate::ast;
+use crate::ast::Ast;
+use crate::parser::ParseError;
+use crate::scope::{StandardProcedure, Symbol, SymbolKind};
+use crate::token::ScanContext;
+use crate::tree::*;
+
+pub fn check(module: &Ast) -> Result<(), ParseError> {
+    Checker.check_tree(module)
+}
+
+struct Checker;
+
+impl Checker {
+    // Declarations are walked until the statements of the module and of its procedures are found
+    fn check_tree(&mut self, tree: &Ast) -> Result<(), ParseError> {
+        match tree.as_ref() {
+            Tree::Nil => Ok(()),
+            Tree::Node(node) => match &node.info {
+                NodeInfo::StatementSequence(_) => self.check_statements(tree),
+                _ => {
+                    self.check_tree(&node.child)?;
+                    self.check_tree(&node.sibling)
+                }
+            },
+        }
+    }
+
+    // Statements are chained as siblings of each other
+    fn check_statements(&mut self, tree: &Ast) -> Result<(), ParseError> {
+        let mut tree = tree;
+        while let Tree::Node(node) = tree.as_ref() {
+            self.check_statement(&node.child)?;
+            tree = &node.sibling;
+        }
+        Ok(())
+    }
+
+    fn check_statement(&mut self, statement: &Ast) -> Result<(), ParseError> {
+        let node = match statement.as_ref() {
+            Tree::Nil => return Ok(()),
+            Tree::Node(node) => node,
+        };
+        match &node.info {
+            // The statements of a FOR loop are followed by the increment of the control variable
+            NodeInfo::StatementSequence(_) => self.check_statements(statement),
+            NodeInfo::Assignement => self.check_assignment(&node.child, &node.sibling),
+            NodeInfo::ProcedureCall(procedure) => self.check_procedure_call(procedure, &node.child),
+            NodeInfo::StandardProcedureCall(procedure, _) => self.check_standard_parameters(*procedure, &node.child),
+            NodeInfo::IfStatement | NodeInfo::WhileStatement => {
+                self.expect_type(&node.child, &VarType::Boolean)?;
+                self.check_branches(&node.sibling)
+            }
+            NodeInfo::RepeatStatement => {
+                self.check_statements(&node.child)?;
+                self.expect_type(&node.sibling, &VarType::Boolean)
+            }
+            // The control variable is an INTEGER, so the initialization checks the first bound
+            NodeInfo::ForStatement(_) => {
+                self.check_assignment_at(&node.child)?;
+                self.check_branches(&node.sibling)
+            }
+            NodeInfo::CaseStatement => {
+                let selector_type = match self.expression_type(&node.child)? {
+                    selector_type @ (VarType::Integer | VarType::Char) => selector_type,
+                    found => return Err(mismatch(VarType::Integer, found, &node.child)),
+                };
+                // Labels are located at the start of their branch
+                let mut branch = &node.sibling;
+                while let Tree::Node(branch_node) = branch.as_ref() {
+                    match &branch_node.info {
+                        NodeInfo::CaseLabels(_, label_type) if *label_type != selector_type => {
+                            return Err(mismatch(selector_type, label_type.clone(), branch));
+                        }
+                        _ => branch = &branch_node.sibling,
+                    }
+                }
+                self.check_branches(&node.sibling)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Branches of IF, WHILE, FOR and CASE statements, with the conditions of ELSIF branches and the limit of FOR loops
+    fn check_branches(&mut self, tree: &Ast) -> Result<(), ParseError> {
+        let node = match tree.as_ref() {
+            Tree::Nil => return Ok(()),
+            Tree::Node(node) => node,
+        };
+        match &node.info {
+            NodeInfo::Elsif => self.expect_type(&node.child, &VarType::Boolean)?,
+            NodeInfo::To => self.expect_type(&node.child, &VarType::Integer)?,
+            _ => self.check_statements(&node.child)?,
+        }
+        self.check_branches(&node.sibling)
+    }
+
+    fn check_assignment_at(&mut self, assignment: &Ast) -> Result<(), ParseError> {
+        match assignment.as_ref() {
+            Tree::Node(node) => self.check_assignment(&node.child, &node.sibling),
+            Tree::Nil => Ok(()),
+        }
+    }
+
+    fn check_assignment(&mut self, subject: &Ast, object: &Ast) -> Result<(), ParseError> {
+        let subject_type = self.expression_type(subject)?;
+
+        // Strings are checked by the parser, since they are copied from the data area
+        if let Tree::Node(TreeNode { info: NodeInfo::String(_), .. }) = object.as_ref() {
+            return Ok(());
+        }
+
+        // NOTE(pht) assignments store a single word, so arrays and records are only changed element by element
+        if subject_type.is_structured() {
+            return Err(ParseError::NotAssignable(designator_name(subject), start(subject)));
+        }
+        self.expect_type(object, &subject_type)
+    }
+
+    fn check_procedure_call(&mut self, procedure: &Symbol, actual_parameters: &Ast) -> Result<(), ParseError> {
+        let formal_parameters = match &procedure.kind {
+            SymbolKind::Procedure(parameters) => parameters,
+            _ => return Ok(()),
+        };
+
+        let mut actual_parameters = actual_parameters;
+        for formal_parameter in formal_parameters.iter() {
+            let actual_parameter = match actual_parameters.as_ref() {
+                Tree::Node(node) => node,
+                Tree::Nil => break,
+            };
+            self.expect_type(&actual_parameter.child, &declared_type(formal_parameter))?;
+            actual_parameters = &actual_parameter.sibling;
+        }
+        Ok(())
+    }
+
+    fn check_standard_parameters(&mut self, procedure: StandardProcedure, actual_parameters: &Ast) -> Result<(), ParseError> {
+        let expected = match procedure {
+            // The parser only accepts strings and arrays of characters, followed by their capacity
+            StandardProcedure::OutString => return Ok(()),
+            StandardProcedure::Assert => VarType::Boolean,
+            StandardProcedure::OutChar => VarType::Char,
+            _ => VarType::Integer,
+        };
+
+        let mut actual_parameters = actual_parameters;
+        while let Tree::Node(actual_parameter) = actual_parameters.as_ref() {
+            self.expect_type(&actual_parameter.child, &expected)?;
+            actual_parameters = &actual_parameter.sibling;
+        }
+        Ok(())
+    }
+
+    fn expect_type(&mut self, expression: &Ast, expected: &VarType) -> Result<(), ParseError> {
+        let found = self.expression_type(expression)?;
+        if &found == expected {
+            Ok(())
+        } else {
+            Err(mismatch(expected.clone(), found, expression))
+        }
+    }
+
+    fn expression_type(&mut self, expression: &Ast) -> Result<VarType, ParseError> {
+        let node = match expression.as_ref() {
+            Tree::Node(node) => node,
+            // NOTE(pht) should not happen, the parser never gives an empty expression
+            Tree::Nil => return Ok(VarType::Integer),
+        };
+
+        match &node.info {
+            NodeInfo::Constant(_) => Ok(VarType::Integer),
+            NodeInfo::Boolean(_) => Ok(VarType::Boolean),
+            NodeInfo::Char(_) => Ok(VarType::Char),
+            NodeInfo::Set(_) => Ok(VarType::Set),
+            // A string of length 1 can be used as a character, other ones are arrays with a terminating 0X
+            NodeInfo::String(value) if value.len() == 1 => Ok(VarType::Char),
+            NodeInfo::String(value) => Ok(VarType::Array(value.len() as u32 + 1, Rc::new(VarType::Char))),
+            NodeInfo::Ident(symbol) => self.selected_type(symbol, &node.child, node.span.start),
+            NodeInfo::Term(operator) => {
+                let operand_type = match operator {
+                    TermOp::Times | TermOp::Div | TermOp::Mod => VarType::Integer,
+                    TermOp::And => VarType::Boolean,
+                    TermOp::Intersection | TermOp::SymmetricDifference => VarType::Set,
+                };
+                self.expect_operands(&node.child, &node.sibling, operand_type)
+            }
+            NodeInfo::SimpleExpression(operator) => {
+                let operand_type = match operator {
+                    SimpleExpressionOp::Plus | SimpleExpressionOp::Minus => VarType::Integer,
+                    SimpleExpressionOp::Or => VarType::Boolean,
+                    SimpleExpressionOp::Union | SimpleExpressionOp::Difference => VarType::Set,
+                };
+                self.expect_operands(&node.child, &node.sibling, operand_type)
+            }
+            NodeInfo::Expression(ExpressionOp::In) => {
+                self.expect_type(&node.child, &VarType::Integer)?;
+                self.expect_type(&node.sibling, &VarType::Set)?;
+                Ok(VarType::Boolean)
+            }
+            NodeInfo::Expression(operator) => {
+                // Values are compared word by word, so only basic types can be compared
+                let operand_type = match (operator, self.expression_type(&node.child)?) {
+                    (ExpressionOp::Eql | ExpressionOp::Neq, VarType::Boolean) => VarType::Boolean,
+                    (ExpressionOp::Eql | ExpressionOp::Neq, VarType::Set) => VarType::Set,
+                    (_, VarType::Char) => VarType::Char,
+                    (_, VarType::Integer) => VarType::Integer,
+                    (_, found) => return Err(mismatch(VarType::Integer, found, &node.child)),
+                };
+                self.expect_type(&node.sibling, &operand_type)?;
+                Ok(VarType::Boolean)
+            }
+            NodeInfo::Not => {
+                self.expect_type(&node.child, &VarType::Boolean)?;
+                Ok(VarType::Boolean)
+            }
+            NodeInfo::Negate => {
+                self.expect_type(&node.child, &VarType::Integer)?;
+                Ok(VarType::Integer)
+            }
+            NodeInfo::SetConstructor => {
+                let mut elements = &node.child;
+                while let Tree::Node(element) = elements.as_ref() {
+                    match element.child.as_ref() {
+                        Tree::Node(TreeNode {
+                            info: NodeInfo::Range,
+                            child: low,
+                            sibling: high,
+                            ..
+                        }) => {
+                            self.expect_type(low, &VarType::Integer)?;
+                            self.expect_type(high, &VarType::Integer)?;
+                        }
+                        _ => self.expect_type(&element.child, &VarType::Integer)?,
+                    }
+                    elements = &element.sibling;
+                }
+                Ok(VarType::Set)
+            }
+            // Only functions can be called in expressions, and the parser checked the number of parameters
+            NodeInfo::StandardProcedureCall(procedure, _) => {
+                self.check_standard_parameters(*procedure, &node.child)?;
+                match procedure {
+                    StandardProcedure::Odd => Ok(VarType::Boolean),
+                    _ => Ok(VarType::Integer),
+                }
+            }
+            _ => Ok(VarType::Integer),
+        }
+    }
+
+    // Both operands of an operator have the same type, which is also the type of the result
+    fn expect_operands(&mut self, left: &Ast, right: &Ast, operand_type: VarType) -> Result<VarType, ParseError> {
+        self.expect_type(left, &operand_type)?;
+        self.expect_type(right, &operand_type)?;
+        Ok(operand_type)
+    }
+
+    // Type of a variable once its selectors are applied, with the type of the indices checked along the way
+    fn selected_type(&mut self, symbol: &Symbol, selector: &Ast, context: ScanContext) -> Result<VarType, ParseError> {
+        let mut var_type = declared_type(symbol);
+        let mut selector = selector;
+        while let Tree::Node(node) = selector.as_ref() {
+            var_type = match (&node.info, &var_type) {
+                (NodeInfo::Index(_, _, _), VarType::Array(_, element_type)) => {
+                    let element_type = element_type.as_ref().clone();
+                    self.expect_type(&node.child, &VarType::Integer)?;
+                    element_type
+                }
+                (NodeInfo::Index(_, _, _), _) => return Err(ParseError::NotAnArray(symbol.name.clone(), context)),
+                (NodeInfo::Field(name, _), VarType::Record(fields)) => match fields.iter().find(|field| &field.name == name) {
+                    Some(field) => field.field_type.clone(),
+                    None => return Err(ParseError::UndefinedField(name.clone())),
+                },
+                (NodeInfo::Field(name, _), _) => return Err(ParseError::NotARecord(name.clone())),
+                _ => var_type,
+            };
+            selector = &node.sibling;
+        }
+        Ok(var_type)
+    }
+}
+
+// The expression of the wrong type is the one located
+fn mismatch(expected: VarType, found: VarType, expression: &Ast) -> ParseError {
+    ParseError::TypeMismatch {
+        expected,
+        found,
+        context: start(expression),
+    }
+}
+
+fn start(tree: &Ast) -> ScanContext {
+    ast::span(tree).unwrap_or_default().start
+}
+
+// NOTE(pht) symbols declared without a type (in tests) are considered integers, like in the parser
+fn declared_type(symbol: &Symbol) -> VarType {
+    symbol.declared_type.clone().unwrap_or(VarType::Integer)
+}
+
+fn designator_name(designator: &Ast) -> String {
+    match designator.as_ref() {
+        Tree::Node(TreeNode {
+            info: NodeInfo::Ident(symbol), ..
+        }) => symbol.name.clone(),
+        _ => String::new(),
+    }
+}
@@ -7,11 +7,17 @@ pub mod scanner;
 mod scanner_tests;
 
 pub mod scope;
+pub mod symbol_file;
 
 pub mod tree;
 
 pub mod parser;
 mod parser_tests;
 
+pub mod checker;
+
 pub mod ast;
 mod ast_tests;
+
+pub mod syntax;
+mod syntax_tests;
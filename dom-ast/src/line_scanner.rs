@@ -11,7 +11,10 @@ pub struct LineScanner<'a> {
     line_number: u32,
     column_number: u32,
     chars: Peekable<CharIndices<'a>>,
+    length: usize,
     pub current: Option<Rc<Scan>>,
+    // Position right after the last token scanned
+    end: ScanContext,
 }
 
 impl LineScanner<'_> {
@@ -20,7 +23,9 @@ impl LineScanner<'_> {
             line_number,
             column_number: 0,
             chars: line.char_indices().peekable(),
+            length: line.len(),
             current: None,
+            end: ScanContext { line: line_number, column: 0 },
         }
     }
 
@@ -28,6 +33,10 @@ impl LineScanner<'_> {
         self.current.as_ref().cloned()
     }
 
+    pub fn end(&self) -> ScanContext {
+        self.end
+    }
+
     fn context(&self, column_number: u32) -> ScanContext {
         ScanContext {
             line: self.line_number,
@@ -47,6 +56,9 @@ impl LineScanner<'_> {
         });
 
         self.current = Some(scan.clone());
+        // Characters of the token have been consumed
+        let end = self.chars.peek().map_or(self.length, |&(column, _)| column);
+        self.end = self.context(end as u32);
 
         Some(Ok(scan))
     }
@@ -95,30 +107,101 @@ impl LineScanner<'_> {
                 break;
             }
         }
-        match &ident.to_ascii_lowercase()[..] {
-            "var" => self.token_at(column, Token::Var),
-            "module" => self.token_at(column, Token::Module),
-            "begin" => self.token_at(column, Token::Begin),
-            "end" => self.token_at(column, Token::End),
-            "if" => self.token_at(column, Token::If),
-            "then" => self.token_at(column, Token::Then),
-            "else" => self.token_at(column, Token::Else),
-            "elsif" => self.token_at(column, Token::Elsif),
-            "while" => self.token_at(column, Token::While),
-            "do" => self.token_at(column, Token::Do),
-            "array" => self.token_at(column, Token::Array),
-            "of" => self.token_at(column, Token::Of),
+        // Keywords are upper-case, so `in` or `to` are identifiers like any other
+        match &ident[..] {
+            "CONST" => self.token_at(column, Token::Const),
+            "TYPE" => self.token_at(column, Token::Type),
+            "VAR" => self.token_at(column, Token::Var),
+            "MODULE" => self.token_at(column, Token::Module),
+            "IMPORT" => self.token_at(column, Token::Import),
+            "BEGIN" => self.token_at(column, Token::Begin),
+            "END" => self.token_at(column, Token::End),
+            "RECORD" => self.token_at(column, Token::Record),
+            "IF" => self.token_at(column, Token::If),
+            "THEN" => self.token_at(column, Token::Then),
+            "ELSE" => self.token_at(column, Token::Else),
+            "ELSIF" => self.token_at(column, Token::Elsif),
+            "WHILE" => self.token_at(column, Token::While),
+            "DO" => self.token_at(column, Token::Do),
+            "ARRAY" => self.token_at(column, Token::Array),
+            "OF" => self.token_at(column, Token::Of),
+            "PROCEDURE" => self.token_at(column, Token::Procedure),
+            "CASE" => self.token_at(column, Token::Case),
+            "OR" => self.token_at(column, Token::Or),
+            "IN" => self.token_at(column, Token::In),
+            "DIV" => self.token_at(column, Token::Div),
+            "MOD" => self.token_at(column, Token::Mod),
+            "REPEAT" => self.token_at(column, Token::Repeat),
+            "UNTIL" => self.token_at(column, Token::Until),
+            "FOR" => self.token_at(column, Token::For),
+            "TO" => self.token_at(column, Token::To),
+            "BY" => self.token_at(column, Token::By),
+            "TRUE" => self.token_at(column, Token::True),
+            "FALSE" => self.token_at(column, Token::False),
             _ => self.token_at(column, Token::Ident(ident)),
         }
     }
 
+    // Character literals are written in hexadecimal, followed by `X`. They can only be
+    // told apart from integers once the suffix is found, so they are looked up ahead.
+    fn scan_number(&mut self, column: usize) -> Option<ScanResult> {
+        let mut lookahead = self.chars.clone();
+        let mut digits = String::from("");
+        while let Some(&(_column, next_char)) = lookahead.peek() {
+            if next_char.is_ascii_digit() || ('A'..='F').contains(&next_char) {
+                digits.push(next_char);
+                lookahead.next();
+            } else {
+                break;
+            }
+        }
+
+        match lookahead.peek() {
+            Some(&(_column, 'X')) => {
+                for _ in 0..=digits.len() {
+                    self.forward();
+                }
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(code) => self.token_at(column, Token::Char(code)),
+                    Err(_) => self.error_at(column, ScanErrorType::InvalidCharacterCode),
+                }
+            }
+            _ => self.scan_integer(column),
+        }
+    }
+
+    fn scan_string(&mut self, column: usize) -> Option<ScanResult> {
+        self.forward();
+        let mut content = String::from("");
+        loop {
+            match self.chars.peek() {
+                Some(&(_column, '"')) => {
+                    self.forward();
+                    return self.token_at(column, Token::String(content));
+                }
+                Some(&(_, '\n')) | None => {
+                    return self.error_at(column, ScanErrorType::UnterminatedString);
+                }
+                Some(&(char_column, c)) if !c.is_ascii() => {
+                    self.forward();
+                    return self.error_at(char_column, ScanErrorType::InvalidChar(c));
+                }
+                Some(&(_column, c)) => {
+                    content.push(c);
+                    self.forward();
+                }
+            }
+        }
+    }
+
     fn scan_integer(&mut self, column: usize) -> Option<ScanResult> {
-        let mut n: u32 = 0;
+        let mut n: Option<u32> = Some(0);
         loop {
             let p = self.chars.peek();
             if let Some(&(_column, next_char)) = p {
                 if let Some(d) = next_char.to_digit(10) {
-                    n = n * 10 + d;
+                    // Keep consuming digits after an overflow, so that the number is skipped as a whole
+                    n = n.and_then(|n| n.checked_mul(10)).and_then(|n| n.checked_add(d)).filter(|n| *n <= i32::MAX as u32);
                     self.forward();
                     continue;
                     // TODO(pht) replace with single 'break' here ?
@@ -129,7 +212,10 @@ impl LineScanner<'_> {
                 break;
             }
         }
-        self.token_at(column, Token::Int(n))
+        match n {
+            Some(n) => self.token_at(column, Token::Int(n)),
+            None => self.error_at(column, ScanErrorType::IntegerOverflow),
+        }
     }
 
     fn scan_sigil(&mut self, column: usize, first_char: char) -> Option<ScanResult> {
@@ -160,6 +246,14 @@ impl LineScanner<'_> {
                     self.token_at(column, Token::Lss)
                 }
             }
+            '.' => {
+                if let Some(&(_column, '.')) = p {
+                    self.forward();
+                    self.token_at(column, Token::Upto)
+                } else {
+                    self.token_at(column, Token::Period)
+                }
+            }
             _ => {
                 panic!(
                     "Programmer error: function `LineScanner::scan_sigil` called with character `{:?}` that does not start a sigil.",
@@ -204,7 +298,8 @@ impl Iterator for LineScanner<'_> {
                 self.error_at(column, ScanErrorType::InvalidChar(c))
             }
 
-            Some(&(column, c)) if c.is_numeric() => self.scan_integer(column),
+            Some(&(column, c)) if c.is_numeric() => self.scan_number(column),
+            Some(&(column, '"')) => self.scan_string(column),
             Some(&(column, ':')) => self.scan_sigil(column, ':'),
             Some(&(column, '>')) => self.scan_sigil(column, '>'),
             Some(&(column, '<')) => self.scan_sigil(column, '<'),
@@ -224,12 +319,17 @@ impl Iterator for LineScanner<'_> {
             Some(&(column, '+')) => self.scan_single(column, Token::Plus),
             Some(&(column, '-')) => self.scan_single(column, Token::Minus),
             Some(&(column, '*')) => self.scan_single(column, Token::Times),
-            Some(&(column, '/')) => self.scan_single(column, Token::Div),
-            Some(&(column, '.')) => self.scan_single(column, Token::Period),
+            Some(&(column, '/')) => self.scan_single(column, Token::Rdiv),
+            Some(&(column, '.')) => self.scan_sigil(column, '.'),
+            Some(&(column, '|')) => self.scan_single(column, Token::Bar),
             Some(&(column, '=')) => self.scan_single(column, Token::Eql),
             Some(&(column, '#')) => self.scan_single(column, Token::Neq),
             Some(&(column, '[')) => self.scan_single(column, Token::Lbrak),
             Some(&(column, ']')) => self.scan_single(column, Token::Rbrak),
+            Some(&(column, '{')) => self.scan_single(column, Token::Lbrace),
+            Some(&(column, '}')) => self.scan_single(column, Token::Rbrace),
+            Some(&(column, '&')) => self.scan_single(column, Token::And),
+            Some(&(column, '~')) => self.scan_single(column, Token::Not),
             Some(&(column, _first_char)) => self.scan_word(column),
             None => {
                 self.current = None;
@@ -116,6 +116,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scans_imports_and_export_marks() {
+        let mut scanner = LineScanner::new(0, "IMPORT B := A; VAR x*, y-");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Import),
+                (0, 7, Token::Ident(String::from("B"))),
+                (0, 9, Token::Becomes),
+                (0, 12, Token::Ident(String::from("A"))),
+                (0, 13, Token::Semicolon),
+                (0, 15, Token::Var),
+                (0, 19, Token::Ident(String::from("x"))),
+                (0, 20, Token::Times),
+                (0, 21, Token::Comma),
+                (0, 23, Token::Ident(String::from("y"))),
+                (0, 24, Token::Minus),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scans_div_and_mod_keywords() {
+        let mut scanner = LineScanner::new(0, "x DIV 2 MOD y");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Ident(String::from("x"))),
+                (0, 2, Token::Div),
+                (0, 6, Token::Int(2)),
+                (0, 8, Token::Mod),
+                (0, 12, Token::Ident(String::from("y"))),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scans_lowercase_keywords_as_identifiers() {
+        let mut scanner = LineScanner::new(0, "in to by div mod or In");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Ident(String::from("in"))),
+                (0, 3, Token::Ident(String::from("to"))),
+                (0, 6, Token::Ident(String::from("by"))),
+                (0, 9, Token::Ident(String::from("div"))),
+                (0, 13, Token::Ident(String::from("mod"))),
+                (0, 17, Token::Ident(String::from("or"))),
+                (0, 20, Token::Ident(String::from("In"))),
+            ],
+        );
+    }
+
     #[test]
     fn test_scans_arithmetic() {
         let mut scanner = LineScanner::new(0, "(x/42)+(y*12)-3");
@@ -124,7 +177,7 @@ mod tests {
             vec![
                 (0, 0, Token::Lparen),
                 (0, 1, Token::Ident(String::from("x"))),
-                (0, 2, Token::Div),
+                (0, 2, Token::Rdiv),
                 (0, 3, Token::Int(42)),
                 (0, 5, Token::Rparen),
                 (0, 6, Token::Plus),
@@ -178,6 +231,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scans_procedure_declaration() {
+        let mut scanner = LineScanner::new(0, "PROCEDURE P(VAR x: INTEGER);");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Procedure),
+                (0, 10, Token::Ident(String::from("P"))),
+                (0, 11, Token::Lparen),
+                (0, 12, Token::Var),
+                (0, 16, Token::Ident(String::from("x"))),
+                (0, 17, Token::Colon),
+                (0, 19, Token::Ident(String::from("INTEGER"))),
+                (0, 26, Token::Rparen),
+                (0, 27, Token::Semicolon),
+            ],
+        );
+    }
+
     #[test]
     fn test_scans_logical_expressions() {
         let mut scanner = LineScanner::new(0, "IF x # y THEN foo ELSIF x >= 0 THEN bar ELSE baz END");
@@ -219,12 +291,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scans_boolean_operators() {
+        let mut scanner = LineScanner::new(0, "~a & b OR TRUE # FALSE");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Not),
+                (0, 1, Token::Ident(String::from("a"))),
+                (0, 3, Token::And),
+                (0, 5, Token::Ident(String::from("b"))),
+                (0, 7, Token::Or),
+                (0, 10, Token::True),
+                (0, 15, Token::Neq),
+                (0, 17, Token::False),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scans_loops() {
+        let mut scanner = LineScanner::new(0, "REPEAT UNTIL FOR TO BY");
+        assert_scans_all(
+            &mut scanner,
+            vec![(0, 0, Token::Repeat), (0, 7, Token::Until), (0, 13, Token::For), (0, 17, Token::To), (0, 20, Token::By)],
+        );
+    }
+
+    #[test]
+    fn test_scans_case_labels() {
+        let mut scanner = LineScanner::new(0, "CASE x OF 1..3: | 4");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Case),
+                (0, 5, Token::Ident(String::from("x"))),
+                (0, 7, Token::Of),
+                (0, 10, Token::Int(1)),
+                (0, 11, Token::Upto),
+                (0, 13, Token::Int(3)),
+                (0, 14, Token::Colon),
+                (0, 16, Token::Bar),
+                (0, 18, Token::Int(4)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scans_declaration_sections() {
+        let mut scanner = LineScanner::new(0, "CONST TYPE VAR RECORD");
+        assert_scans_all(&mut scanner, vec![(0, 0, Token::Const), (0, 6, Token::Type), (0, 11, Token::Var), (0, 15, Token::Record)]);
+    }
+
     #[test]
     fn test_ignores_comments() {
         let mut scanner = LineScanner::new(0, "IF (* blah *) (");
         assert_scans_all(&mut scanner, vec![(0, 0, Token::If), (0, 14, Token::Lparen)]);
     }
 
+    #[test]
+    fn test_returns_error_on_integer_overflow() {
+        let mut scanner = LineScanner::new(0, "2147483647 2147483648 99999999999 1");
+        assert_scans(&mut scanner, 0, 0, Token::Int(2147483647));
+        assert_scans_error(&mut scanner, 0, 11, ScanErrorType::IntegerOverflow);
+        assert_scans_error(&mut scanner, 0, 22, ScanErrorType::IntegerOverflow);
+        assert_scans(&mut scanner, 0, 34, Token::Int(1));
+        assert_done(&mut scanner);
+    }
+
+    #[test]
+    fn test_scans_characters_and_strings() {
+        let mut scanner = LineScanner::new(0, "c := 41X; 0AX; s := \"Hello, World\" 4 \"\"");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Ident(String::from("c"))),
+                (0, 2, Token::Becomes),
+                (0, 5, Token::Char(0x41)),
+                (0, 8, Token::Semicolon),
+                (0, 10, Token::Char(0x0A)),
+                (0, 13, Token::Semicolon),
+                (0, 15, Token::Ident(String::from("s"))),
+                (0, 17, Token::Becomes),
+                (0, 20, Token::String(String::from("Hello, World"))),
+                (0, 35, Token::Int(4)),
+                (0, 37, Token::String(String::from(""))),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scans_sets() {
+        let mut scanner = LineScanner::new(0, "x IN {1, 3..5}");
+        assert_scans_all(
+            &mut scanner,
+            vec![
+                (0, 0, Token::Ident(String::from("x"))),
+                (0, 2, Token::In),
+                (0, 5, Token::Lbrace),
+                (0, 6, Token::Int(1)),
+                (0, 7, Token::Comma),
+                (0, 9, Token::Int(3)),
+                (0, 10, Token::Upto),
+                (0, 12, Token::Int(5)),
+                (0, 13, Token::Rbrace),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_returns_error_on_invalid_characters_and_strings() {
+        let mut scanner = LineScanner::new(0, "100X \"abc");
+        assert_scans_error(&mut scanner, 0, 0, ScanErrorType::InvalidCharacterCode);
+        assert_scans_error(&mut scanner, 0, 5, ScanErrorType::UnterminatedString);
+        assert_done(&mut scanner);
+    }
+
     #[test]
     fn test_returns_error_on_unfinished_comments() {
         let mut scanner = LineScanner::new(0, "(*");
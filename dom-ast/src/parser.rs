@@ -1,6 +1,7 @@
 use log::debug;
 use crate::ast;
 use crate::ast::Ast;
+use crate::checker;
 use crate::scanner::*;
 use crate::scope::*;
 use crate::token::*;
@@ -13,18 +14,89 @@ pub enum ParseError {
     ScanError(crate::token::ScanError),
     UndefinedSymbol(String),
     PrematureEof,
-    UnexpectedToken(Rc<Scan>),
+    // Expected is empty when the token is of the right kind, but can not be used where it is
+    UnexpectedToken { expected: Vec<Token>, found: Rc<Scan> },
     SymbolAlreadyDeclared(String, ScanContext),
     UnexpectedBlockEnding { expected: String, found: String },
-    Todo,
+    // Variables of enclosing procedures can not be reached, only locals and module variables
+    InaccessibleSymbol(String),
+    ParameterCountMismatch { procedure: String, expected: usize, found: usize },
+    // Parameters are passed in registers, see MAX_PARAMETERS
+    TooManyParameters(String, ScanContext),
+    NotAType(String),
+    DuplicateCaseLabel(i32),
+    NotAConstant(String),
+    NotARecord(String),
+    IndexOutOfBounds(i32),
+    UndefinedField(String),
+    // Strings can only be assigned to arrays of characters with room left for the terminating 0X,
+    // or to a character when they have a single one
+    StringNotAssignable(String, ScanContext),
+    // Sets can only hold elements from 0 to 31
+    SetElementOutOfRange(i32),
+    // Division by zero, overflow, or operands of the wrong type
+    InvalidConstantExpression,
+    // Imported module without a symbol file
+    UndefinedModule(String),
+    // Line of a symbol file that was not written by the compiler
+    InvalidSymbolFile(String),
+    // Variables exported with `-` can only be changed by their own module
+    ReadOnlyVariable(String),
+    // Errors of the checker, located at the expression or designator they are found in
+    TypeMismatch { expected: VarType, found: VarType, context: ScanContext },
+    NotAnArray(String, ScanContext),
+    // Arrays and records can not be assigned as a whole
+    NotAssignable(String, ScanContext),
 }
 
 pub type ParseResult = Result<Rc<Tree>, ParseError>;
 
-type IdentList = Vec<(String, ScanContext)>;
+// Errors the parser recovered from, with the location of the token they were found at
+pub type ParseErrors = Vec<(ParseError, Option<ScanContext>)>;
+
+type IdentList = Vec<(String, ScanContext, Option<Export>)>;
+
+// Gives the symbols exported by an imported module, usually read from its symbol file
+pub type Importer<'a> = dyn FnMut(&str) -> Result<Vec<Rc<Symbol>>, ParseError> + 'a;
 
 pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    parse_module_with_imports(scanner, scope, &mut |module| Err(ParseError::UndefinedModule(String::from(module))))
+}
+
+pub fn parse_module_with_imports(scanner: &mut Scanner, scope: &Scope, import: &mut Importer) -> ParseResult {
+    let (module, mut errors) = parse_module_with_recovery(scanner, scope, import);
+    match module {
+        Some(module) if errors.is_empty() => Ok(module),
+        // There is always an error when the module is missing
+        _ => Err(errors.remove(0).0),
+    }
+}
+
+// Syntax errors do not stop the parser: they are all reported, and the statements and declarations
+// they are found in are left out of the tree. The module is only checked when there are none.
+pub fn parse_module_with_recovery(scanner: &mut Scanner, scope: &Scope, import: &mut Importer) -> (Option<Ast>, ParseErrors) {
+    scope.collect_errors();
+    let module = match parse_module_tree(scanner, scope, import) {
+        Ok(module) => Some(module),
+        Err(error) => {
+            // The scope collects the errors, so the one that stopped the parser is reported like the others
+            let _ = scope.report_error(error, current_token_or_none(scanner).map(|scan| scan.context));
+            None
+        }
+    };
+
+    let mut errors = scope.take_errors();
+    if let (Some(module), true) = (&module, errors.is_empty()) {
+        if let Err(error) = checker::check(module) {
+            errors.push((error, None));
+        }
+    }
+    (module, errors)
+}
+
+fn parse_module_tree(scanner: &mut Scanner, scope: &Scope, import: &mut Importer) -> ParseResult {
     let current = current_token(scanner)?;
+    let start = current.context;
 
     let current = match current.as_ref() {
         Scan { token: Token::Module, .. } => {
@@ -32,19 +104,24 @@ pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             current_token(scanner)?
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[Token::Module]));
         }
     };
 
     let module_ident;
+    let module_span;
     let current = match current.as_ref() {
-        Scan { token: Token::Ident(ident), .. } => {
+        Scan {
+            token: Token::Ident(ident),
+            context,
+        } => {
             scan_next(scanner)?;
             module_ident = ident;
+            module_span = span_from(scanner, *context);
             current_token(scanner)?
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[IDENT]));
         }
     };
 
@@ -55,19 +132,23 @@ pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
         Scan { token: Token::Semicolon, .. } => {
             // NOTE(pht) I have no idea what the _size_ of a module should be, or where it should be in memory.
             // Probably it will be the size of the functions, maybe ? In which case you can only add the entry later ?
-            let module_size = 1;
-
-            add_symbol(scope, module_ident, module_size, current.context)?;
+            // For the moment it takes one slot, like an INTEGER.
+            // The module body keeps its return address in it.
+            add_symbol(scope, module_ident, VarType::Integer, current.context)?;
             symbol = lookup(scope, module_ident)?;
 
-            child = ast::leaf(NodeInfo::Ident(symbol));
+            child = ast::leaf_at(NodeInfo::Ident(symbol), module_span);
             scan_next(scanner)?;
+            if let Some(Scan { token: Token::Import, .. }) = current_token_or_none(scanner).as_deref() {
+                scan_next(scanner)?;
+                parse_import_list(scanner, scope, import)?;
+            }
             sibling = parse_declarations(scanner, scope, &mut parse_begin_end)?;
 
             current_token(scanner)?
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[Token::Semicolon]));
         }
     };
 
@@ -77,7 +158,7 @@ pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             current_token(scanner)?
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[Token::End]));
         }
     };
 
@@ -87,16 +168,17 @@ pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             ..
         } => {
             if ending_ident != module_ident {
-                return Err(ParseError::UnexpectedBlockEnding {
+                let error = ParseError::UnexpectedBlockEnding {
                     expected: String::from(module_ident),
                     found: String::from(ending_ident),
-                });
+                };
+                report_error(scanner, scope, error)?;
             }
             scan_next(scanner)?;
             current_token(scanner)?
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[IDENT]));
         }
     };
 
@@ -106,13 +188,54 @@ pub fn parse_module(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             current_token_or_none(scanner)
         }
         _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            return Err(unexpected(current, &[Token::Period]));
         }
     };
 
     match current {
-        None => Ok(ast::node(NodeInfo::Module, child, sibling)),
-        Some(scan) => Err(ParseError::UnexpectedToken(scan)),
+        None => Ok(ast::node_at(NodeInfo::Module, child, sibling, span_from(scanner, start))),
+        Some(scan) => Err(unexpected(scan, &[])),
+    }
+}
+
+// `IMPORT A, B := C;` makes the exports of C available as `B.x`
+fn parse_import_list(scanner: &mut Scanner, scope: &Scope, import: &mut Importer) -> Result<(), ParseError> {
+    loop {
+        let current = current_token(scanner)?;
+        let (alias, alias_context) = match current.as_ref() {
+            Scan {
+                token: Token::Ident(ident),
+                context,
+            } => (String::from(ident), *context),
+            _ => return Err(unexpected(current, &[IDENT])),
+        };
+        scan_next(scanner)?;
+
+        let mut module = alias.clone();
+        if current_token(scanner)?.token == Token::Becomes {
+            scan_next(scanner)?;
+            let current = current_token(scanner)?;
+            match current.as_ref() {
+                Scan { token: Token::Ident(ident), .. } => module = String::from(ident),
+                _ => return Err(unexpected(current, &[IDENT])),
+            }
+            scan_next(scanner)?;
+        }
+
+        if scope.lookup_local(&alias).is_some() {
+            return Err(ParseError::SymbolAlreadyDeclared(alias, alias_context));
+        }
+        scope.add_module(&alias, import(&module)?);
+
+        let current = current_token(scanner)?;
+        match current.token {
+            Token::Comma => scan_next(scanner)?,
+            Token::Semicolon => {
+                scan_next(scanner)?;
+                return Ok(());
+            }
+            _ => return Err(unexpected(current, &[Token::Comma, Token::Semicolon])),
+        }
     }
 }
 
@@ -127,10 +250,17 @@ pub fn parse_begin_end(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
 }
 
 pub fn parse_declarations(scanner: &mut Scanner, scope: &Scope, and_then: &mut dyn FnMut(&mut Scanner, &Scope) -> ParseResult) -> ParseResult {
-    // It should actually just be "parse_const_declarations".
-    // Which should, in the end, try to parse the "var_declarations"
-    // ...
-    let mut declarations = ast::node(NodeInfo::Declarations, ast::empty(), ast::empty());
+    let start = current_start(scanner);
+    // Constants and types only live in the scope, they do not appear in the tree
+    if let Some(Scan { token: Token::Const, .. }) = current_token_or_none(scanner).as_deref() {
+        scan_next(scanner)?;
+        parse_const_declarations(scanner, scope)?;
+    }
+
+    if let Some(Scan { token: Token::Type, .. }) = current_token_or_none(scanner).as_deref() {
+        scan_next(scanner)?;
+        parse_type_declarations(scanner, scope)?;
+    }
 
     let current = current_token_or_none(scanner);
     if let Some(scan) = current.as_ref() {
@@ -138,121 +268,372 @@ pub fn parse_declarations(scanner: &mut Scanner, scope: &Scope, and_then: &mut d
             scan_next(scanner)?;
             // NOTE(pht) and_then is passed to parse_declaration, but in the end
             // it should be passed to the last function that parses declaration
-            declarations = parse_var_declarations(scanner, scope, and_then)?;
+            return parse_var_declarations(scanner, scope, and_then);
         }
     }
-    Ok(declarations)
+
+    let declarations = parse_procedure_declarations(scanner, scope)?;
+    let span = span_from(scanner, start);
+    Ok(ast::node_at(NodeInfo::Declarations, declarations, and_then(scanner, scope)?, span))
 }
 
 pub fn parse_var_declarations(scanner: &mut Scanner, scope: &Scope, and_then: &mut dyn FnMut(&mut Scanner, &Scope) -> ParseResult) -> ParseResult {
+    let start = current_start(scanner);
     let declarations = recur_parse_declaration(scanner, scope)?;
 
-    Ok(ast::node(NodeInfo::Declarations, declarations, and_then(scanner, scope)?))
+    let span = span_from(scanner, start);
+    Ok(ast::node_at(NodeInfo::Declarations, declarations, and_then(scanner, scope)?, span))
 }
 
 fn recur_parse_declaration(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    let current = current_token_or_none(scanner);
-    if current.is_none() {
-        return Ok(ast::empty());
+    match current_token_or_none(scanner).as_deref() {
+        None => return Ok(ast::empty()),
+        Some(Scan { token: Token::Ident(_), .. }) => {}
+        // Procedures declarations are chained after the variables declarations
+        Some(_) => return parse_procedure_declarations(scanner, scope),
     }
 
-    let idents = parse_ident_list(scanner)?;
-    debug!("List of idents to declare after first loop {:?}", idents);
-
-    if idents.is_empty() {
-        return Ok(ast::empty());
+    let declaration = parse_var_declaration(scanner, scope);
+    match recover_declaration(scanner, scope, declaration)? {
+        Some((idents, var_type, type_span, span)) => var_declarations(&mut idents.iter(), scope, (var_type, type_span), span, recur_parse_declaration(scanner, scope)?),
+        // Variables whose declaration can not be parsed are left out of the tree, and the section
+        // goes on when it was started again, usually after a missing semicolon
+        None => {
+            if current_token_or_none(scanner).map_or(false, |scan| scan.token == Token::Var) {
+                scan_next(scanner)?;
+            }
+            recur_parse_declaration(scanner, scope)
+        }
     }
+}
 
-    let mut current = current_token(scanner)?;
+// Variables with their type, the span of the type and the span of the declaration
+fn parse_var_declaration(scanner: &mut Scanner, scope: &Scope) -> Result<(IdentList, VarType, Span, Span), ParseError> {
+    let start = current_start(scanner);
+    let idents = parse_ident_list(scanner, scope)?;
+    debug!("List of idents to declare after first loop {:?}", idents);
+
+    let current = current_token(scanner)?;
     debug!("After var declarations, current ? {:?}", current);
 
     if let Scan { token: Token::Colon, .. } = current.as_ref() {
         scan_next(scanner)?;
     } else {
-        return Err(ParseError::UnexpectedToken(current));
+        return Err(unexpected(current, &[Token::Colon]));
     }
 
-    current = current_token(scanner)?;
+    let type_start = current_start(scanner);
+    let var_type = parse_type(scanner, scope)?;
+    let type_span = span_from(scanner, type_start);
 
-    if let Scan {
-        token: Token::Array,
-        context: _array_ident_context,
-    } = current.as_ref()
-    {
-        debug!("Parsing array type declaration");
-        scan_next(scanner)?;
-        debug!("Scanned next token");
-        current = current_token(scanner)?;
-        debug!("Found current token {:?}", current);
-
-        if let Scan {
-            token: Token::Int(array_capacity),
-            ..
-        } = current.as_ref()
-        {
-            scan_next(scanner)?;
+    for (ident, ident_context, export) in idents.iter() {
+        add_symbol(scope, ident, var_type.clone(), *ident_context)?;
+        if let Some(export) = export {
+            scope.export(ident, *export);
+        }
+    }
 
-            let mut current = current_token(scanner)?;
-            debug!("Found current token {:?}", current);
+    let span = span_from(scanner, start);
+    expect_semicolon(scanner)?;
+    Ok((idents, var_type, type_span, span))
+}
 
-            if let Scan { token: Token::Of, .. } = current.as_ref() {
-                scan_next(scanner)?;
-                current = current_token(scanner)?;
+// Either the name of a type, an array type or a record type
+fn parse_type(scanner: &mut Scanner, scope: &Scope) -> Result<VarType, ParseError> {
+    let current = current_token(scanner)?;
+    match current.as_ref() {
+        Scan {
+            token: Token::Ident(type_ident), ..
+        } => {
+            let var_type = lookup_type_or_report(scanner, scope, type_ident)?;
+            scan_next(scanner)?;
+            Ok(var_type)
+        }
+        Scan { token: Token::Array, .. } => {
+            debug!("Parsing array type declaration");
+            scan_next(scanner)?;
+            parse_array_type(scanner, scope)
+        }
+        Scan { token: Token::Record, .. } => {
+            debug!("Parsing record type declaration");
+            scan_next(scanner)?;
+            parse_field_lists(scanner, scope)
+        }
+        _ => Err(unexpected(current, &[IDENT, Token::Array, Token::Record])),
+    }
+}
 
-                if let Scan {
-                    token: Token::Ident(type_ident), ..
-                } = current.as_ref()
-                {
-                    // NOTE(pht) will have to be relaxed to allow nested arrays or
-                    // arrays of records
-                    if type_ident != "INTEGER" {
-                        return Err(ParseError::UndefinedSymbol(String::from(type_ident)));
-                    }
+// `ARRAY N, M OF T` is a shorthand for `ARRAY N OF ARRAY M OF T`
+fn parse_array_type(scanner: &mut Scanner, scope: &Scope) -> Result<VarType, ParseError> {
+    let current = current_token(scanner)?;
+    let array_capacity = match parse_constant_expression(scanner, scope)? {
+        (capacity, VarType::Integer) if capacity > 0 => capacity as u32,
+        _ => return Err(unexpected(current, &[])),
+    };
 
-                    scan_next(scanner)?;
-                    current = current_token(scanner)?;
-                    if let Scan { token: Token::Semicolon, .. } = current.as_ref() {
-                        scan_next(scanner)?;
+    let current = current_token(scanner)?;
+    let element_type = match current.as_ref() {
+        Scan { token: Token::Comma, .. } => {
+            scan_next(scanner)?;
+            parse_array_type(scanner, scope)?
+        }
+        Scan { token: Token::Of, .. } => {
+            scan_next(scanner)?;
+            parse_type(scanner, scope)?
+        }
+        _ => return Err(unexpected(current, &[Token::Comma, Token::Of])),
+    };
+    Ok(VarType::Array(array_capacity, Rc::new(element_type)))
+}
 
-                        for (ident, ident_context) in idents.iter() {
-                            // TODO(pht) add some capacity info into the identified, otherwise we won't be able to
-                            // remember the size ?
-                            add_symbol(scope, ident, *array_capacity, *ident_context)?;
-                        }
+// Fields are laid out in order of declaration, each one right after the previous one
+// NOTE(pht) export marks are accepted on fields, but all the fields of an exported type are visible
+fn parse_field_lists(scanner: &mut Scanner, scope: &Scope) -> Result<VarType, ParseError> {
+    let mut fields: Vec<Field> = vec![];
+    let mut offset = 0;
+    loop {
+        let idents = parse_ident_list(scanner, scope)?;
+        if !idents.is_empty() {
+            let current = current_token(scanner)?;
+            if let Scan { token: Token::Colon, .. } = current.as_ref() {
+                scan_next(scanner)?;
+            } else {
+                return Err(unexpected(current, &[Token::Colon]));
+            }
 
-                        return var_declarations(&mut idents.iter(), scope, VarType::Array(*array_capacity), recur_parse_declaration(scanner, scope)?);
-                    }
+            let field_type = parse_type(scanner, scope)?;
+            for (ident, ident_context, _) in idents {
+                if fields.iter().any(|field| field.name == ident) {
+                    return Err(ParseError::SymbolAlreadyDeclared(ident, ident_context));
                 }
+                fields.push(Field {
+                    name: ident,
+                    offset,
+                    field_type: field_type.clone(),
+                });
+                offset += field_type.size();
             }
         }
-    }
 
-    if let Scan {
-        token: Token::Ident(type_ident),
-        context: _type_ident_context,
-    } = current.as_ref()
-    {
-        if type_ident != "INTEGER" {
-            return Err(ParseError::UndefinedSymbol(String::from(type_ident)));
+        let current = current_token(scanner)?;
+        match current.as_ref() {
+            Scan { token: Token::Semicolon, .. } => {
+                scan_next(scanner)?;
+            }
+            Scan { token: Token::End, .. } => {
+                scan_next(scanner)?;
+                return Ok(VarType::Record(Rc::new(fields)));
+            }
+            _ => return Err(unexpected(current, &[Token::Semicolon, Token::End])),
         }
+    }
+}
+
+fn parse_const_declarations(scanner: &mut Scanner, scope: &Scope) -> Result<(), ParseError> {
+    while let Some(Scan { token: Token::Ident(_), .. }) = current_token_or_none(scanner).as_deref() {
+        let declaration = parse_const_declaration(scanner, scope);
+        recover_declaration(scanner, scope, declaration)?;
+    }
+    Ok(())
+}
+
+fn parse_const_declaration(scanner: &mut Scanner, scope: &Scope) -> Result<(), ParseError> {
+    let (ident, ident_context, export) = parse_declared_ident(scanner, scope)?;
+    let (value, var_type) = parse_constant_expression(scanner, scope)?;
+
+    if scope.lookup_local(&ident).is_some() {
+        return Err(ParseError::SymbolAlreadyDeclared(ident, ident_context));
+    }
+    scope.add_constant(&ident, var_type, value);
+    if let Some(export) = export {
+        scope.export(&ident, export);
+    }
+    expect_semicolon(scanner)
+}
 
+fn parse_type_declarations(scanner: &mut Scanner, scope: &Scope) -> Result<(), ParseError> {
+    while let Some(Scan { token: Token::Ident(_), .. }) = current_token_or_none(scanner).as_deref() {
+        let declaration = parse_type_declaration(scanner, scope);
+        recover_declaration(scanner, scope, declaration)?;
+    }
+    Ok(())
+}
+
+fn parse_type_declaration(scanner: &mut Scanner, scope: &Scope) -> Result<(), ParseError> {
+    let (ident, ident_context, export) = parse_declared_ident(scanner, scope)?;
+    let var_type = parse_type(scanner, scope)?;
+
+    if scope.lookup_local(&ident).is_some() {
+        return Err(ParseError::SymbolAlreadyDeclared(ident, ident_context));
+    }
+    scope.add_type(&ident, var_type);
+    if let Some(export) = export {
+        scope.export(&ident, export);
+    }
+    expect_semicolon(scanner)
+}
+
+// Parses the `ident* =` part of constant and type declarations
+fn parse_declared_ident(scanner: &mut Scanner, scope: &Scope) -> Result<(String, ScanContext, Option<Export>), ParseError> {
+    let current = current_token(scanner)?;
+    let (ident, ident_context) = match current.as_ref() {
+        Scan {
+            token: Token::Ident(ident),
+            context,
+        } => (String::from(ident), *context),
+        _ => return Err(unexpected(current, &[IDENT])),
+    };
+    scan_next(scanner)?;
+    let export = parse_export_mark(scanner, scope, false)?;
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Eql, .. } = current.as_ref() {
         scan_next(scanner)?;
-        current = current_token(scanner)?;
-        if let Scan { token: Token::Semicolon, .. } = current.as_ref() {
-            scan_next(scanner)?;
+        Ok((ident, ident_context, export))
+    } else {
+        Err(unexpected(current, &[Token::Eql]))
+    }
+}
 
-            for (ident, ident_context) in idents.iter() {
-                add_symbol(scope, ident, 1, *ident_context)?;
-            }
+// Only module level declarations can be exported, and only variables and fields can be read-only
+fn parse_export_mark(scanner: &mut Scanner, scope: &Scope, read_only_allowed: bool) -> Result<Option<Export>, ParseError> {
+    let current = current_token(scanner)?;
+    let export = match current.token {
+        Token::Times => Export::ReadWrite,
+        Token::Minus if read_only_allowed => Export::ReadOnly,
+        _ => return Ok(None),
+    };
+    if scope.level() != 0 {
+        return Err(unexpected(current, &[]));
+    }
+    scan_next(scanner)?;
+    Ok(Some(export))
+}
+
+fn expect_semicolon(scanner: &mut Scanner) -> Result<(), ParseError> {
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Semicolon, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        Ok(())
+    } else {
+        Err(unexpected(current, &[Token::Semicolon]))
+    }
+}
+
+// Constant identifiers are replaced by their value when parsing factors,
+// so evaluating a constant expression only has to deal with literals.
+pub fn parse_constant_expression(scanner: &mut Scanner, scope: &Scope) -> Result<(i32, VarType), ParseError> {
+    let expression = parse_expression(scanner, scope)?;
+    evaluate_constant(&expression)
+}
 
-            return var_declarations(&mut idents.iter(), scope, VarType::Integer, recur_parse_declaration(scanner, scope)?);
+fn evaluate_constant(tree: &Ast) -> Result<(i32, VarType), ParseError> {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return Err(ParseError::InvalidConstantExpression),
+    };
+
+    match &node.info {
+        NodeInfo::Constant(value) => Ok((*value, VarType::Integer)),
+        NodeInfo::Boolean(value) => Ok((*value as i32, VarType::Boolean)),
+        NodeInfo::Char(value) => Ok((*value as i32, VarType::Char)),
+        NodeInfo::String(value) if value.len() == 1 => Ok((value.as_bytes()[0] as i32, VarType::Char)),
+        NodeInfo::Set(value) => Ok((*value as i32, VarType::Set)),
+        NodeInfo::SetConstructor => {
+            let mut value = 0;
+            let mut element = &node.child;
+            while let Tree::Node(element_node) = element.as_ref() {
+                let (low, high) = match element_node.child.as_ref() {
+                    Tree::Node(TreeNode {
+                        info: NodeInfo::Range,
+                        child,
+                        sibling,
+                        ..
+                    }) => (evaluate_set_element(child)?, evaluate_set_element(sibling)?),
+                    _ => {
+                        let single = evaluate_set_element(&element_node.child)?;
+                        (single, single)
+                    }
+                };
+                for bit in low..=high {
+                    value |= 1 << bit;
+                }
+                element = &element_node.sibling;
+            }
+            Ok((value, VarType::Set))
+        }
+        NodeInfo::Ident(symbol) => Err(ParseError::NotAConstant(String::from(&symbol.name))),
+        NodeInfo::Not => match evaluate_constant(&node.child)? {
+            (value, VarType::Boolean) => Ok(((value == 0) as i32, VarType::Boolean)),
+            _ => Err(ParseError::InvalidConstantExpression),
+        },
+        NodeInfo::Negate => match evaluate_constant(&node.child)? {
+            (value, VarType::Integer) => value.checked_neg().map(|value| (value, VarType::Integer)).ok_or(ParseError::InvalidConstantExpression),
+            _ => Err(ParseError::InvalidConstantExpression),
+        },
+        NodeInfo::Term(operator) => {
+            let left = evaluate_constant(&node.child)?;
+            let right = evaluate_constant(&node.sibling)?;
+            let value = match (operator, left, right) {
+                (TermOp::Times, (left, VarType::Integer), (right, VarType::Integer)) => left.checked_mul(right),
+                (TermOp::Div, (left, VarType::Integer), (right, VarType::Integer)) => left.checked_div_euclid(right),
+                (TermOp::Mod, (left, VarType::Integer), (right, VarType::Integer)) => left.checked_rem_euclid(right),
+                (TermOp::And, (left, VarType::Boolean), (right, VarType::Boolean)) => return Ok(((left != 0 && right != 0) as i32, VarType::Boolean)),
+                (TermOp::Intersection, (left, VarType::Set), (right, VarType::Set)) => return Ok((left & right, VarType::Set)),
+                (TermOp::SymmetricDifference, (left, VarType::Set), (right, VarType::Set)) => return Ok((left ^ right, VarType::Set)),
+                _ => None,
+            };
+            value.map(|value| (value, VarType::Integer)).ok_or(ParseError::InvalidConstantExpression)
+        }
+        NodeInfo::SimpleExpression(operator) => {
+            let left = evaluate_constant(&node.child)?;
+            let right = evaluate_constant(&node.sibling)?;
+            let value = match (operator, left, right) {
+                (SimpleExpressionOp::Plus, (left, VarType::Integer), (right, VarType::Integer)) => left.checked_add(right),
+                (SimpleExpressionOp::Minus, (left, VarType::Integer), (right, VarType::Integer)) => left.checked_sub(right),
+                (SimpleExpressionOp::Or, (left, VarType::Boolean), (right, VarType::Boolean)) => return Ok(((left != 0 || right != 0) as i32, VarType::Boolean)),
+                (SimpleExpressionOp::Union, (left, VarType::Set), (right, VarType::Set)) => return Ok((left | right, VarType::Set)),
+                (SimpleExpressionOp::Difference, (left, VarType::Set), (right, VarType::Set)) => return Ok((left & !right, VarType::Set)),
+                _ => None,
+            };
+            value.map(|value| (value, VarType::Integer)).ok_or(ParseError::InvalidConstantExpression)
         }
+        NodeInfo::Expression(ExpressionOp::In) => {
+            let element = evaluate_set_element(&node.child)?;
+            match evaluate_constant(&node.sibling)? {
+                (set, VarType::Set) => Ok(((set >> element) & 1, VarType::Boolean)),
+                _ => Err(ParseError::InvalidConstantExpression),
+            }
+        }
+        NodeInfo::Expression(operator) => {
+            let (left, left_type) = evaluate_constant(&node.child)?;
+            let (right, right_type) = evaluate_constant(&node.sibling)?;
+            if left_type != right_type {
+                return Err(ParseError::InvalidConstantExpression);
+            }
+            let value = match operator {
+                ExpressionOp::Eql => left == right,
+                ExpressionOp::Neq => left != right,
+                ExpressionOp::Lss => left < right,
+                ExpressionOp::Leq => left <= right,
+                ExpressionOp::Gtr => left > right,
+                ExpressionOp::Geq => left >= right,
+                ExpressionOp::In => unreachable!("Programmer error: membership is evaluated separately"),
+            };
+            Ok((value as i32, VarType::Boolean))
+        }
+        _ => Err(ParseError::InvalidConstantExpression),
     }
+}
 
-    Err(ParseError::UnexpectedToken(current))
+fn evaluate_set_element(tree: &Ast) -> Result<i32, ParseError> {
+    match evaluate_constant(tree)? {
+        (value, VarType::Integer) if (0..=31).contains(&value) => Ok(value),
+        _ => Err(ParseError::InvalidConstantExpression),
+    }
 }
 
-fn parse_ident_list(scanner: &mut Scanner) -> Result<IdentList, ParseError> {
+fn parse_ident_list(scanner: &mut Scanner, scope: &Scope) -> Result<IdentList, ParseError> {
     let mut idents: IdentList = vec![];
 
     let mut current;
@@ -266,8 +647,9 @@ fn parse_ident_list(scanner: &mut Scanner) -> Result<IdentList, ParseError> {
         {
             // NOTE(pht) Ideally, I would like not to have to clone the identifier, but since the token can fall
             // out of scope, I don't see a way to do that.
-            idents.push((String::from(ident), *ident_context));
             scan_next(scanner)?;
+            let export = parse_export_mark(scanner, scope, true)?;
+            idents.push((String::from(ident), *ident_context, export));
             current = current_token(scanner)?;
             if let Scan { token: Token::Comma, .. } = current.as_ref() {
                 scan_next(scanner)?;
@@ -281,16 +663,200 @@ fn parse_ident_list(scanner: &mut Scanner) -> Result<IdentList, ParseError> {
     Ok(idents)
 }
 
-pub fn var_declarations(idents: &mut dyn Iterator<Item = &(String, ScanContext)>, scope: &Scope, node_type: crate::tree::VarType, final_sibling: Ast) -> ParseResult {
+// Each variable is located at its identifier, and its declaration spans the whole `a, b: T`
+pub fn var_declarations(
+    idents: &mut dyn Iterator<Item = &(String, ScanContext, Option<Export>)>,
+    scope: &Scope,
+    (node_type, type_span): (crate::tree::VarType, Span),
+    span: Span,
+    final_sibling: Ast,
+) -> ParseResult {
     match idents.next() {
         None => Ok(final_sibling),
-        Some((ident, _ident_context)) => {
+        Some((ident, ident_context, _export)) => {
             let symbol = lookup(scope, ident)?;
-            let child = ast::leaf(NodeInfo::Ident(symbol));
-            let sibling = ast::leaf(NodeInfo::Type(node_type));
-            let var = ast::node(NodeInfo::Var, child, sibling);
+            let child = ast::leaf_at(NodeInfo::Ident(symbol), ident_span(span.file, *ident_context, ident));
+            let sibling = ast::leaf_at(NodeInfo::Type(node_type.clone()), type_span);
+            let var = ast::node_at(NodeInfo::Var, child, sibling, span);
+
+            let next = var_declarations(idents, scope, (node_type, type_span), span, final_sibling)?;
+            Ok(ast::node_at(NodeInfo::Declaration, var, next, span))
+        }
+    }
+}
+
+fn parse_procedure_declarations(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let current = current_token_or_none(scanner);
+    if let Some(scan) = current.as_ref() {
+        if let Scan { token: Token::Procedure, .. } = scan.as_ref() {
+            scan_next(scanner)?;
+            return parse_procedure_declaration(scanner, scope);
+        }
+    }
+    Ok(ast::empty())
+}
+
+pub fn parse_procedure_declaration(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    debug!("parse_procedure_declaration {:?}", current_token(scanner));
+    // The PROCEDURE keyword has been consumed already
+    let start = scanner.previous_start();
+    let current = current_token(scanner)?;
+
+    let (procedure_ident, procedure_context) = match current.as_ref() {
+        Scan {
+            token: Token::Ident(ident),
+            context,
+        } => {
+            scan_next(scanner)?;
+            (String::from(ident), *context)
+        }
+        _ => {
+            return Err(unexpected(current, &[IDENT]));
+        }
+    };
+
+    if scope.lookup_local(&procedure_ident).is_some() {
+        return Err(ParseError::SymbolAlreadyDeclared(procedure_ident, procedure_context));
+    }
+    let export = parse_export_mark(scanner, scope, false)?;
+
+    let procedure_scope = Scope::new_procedure(scope);
+    let parameters = parse_formal_parameters(scanner, &procedure_scope)?;
+    if parameters.len() > MAX_PARAMETERS {
+        return Err(ParseError::TooManyParameters(procedure_ident, procedure_context));
+    }
+
+    // The procedure is added before its body is parsed, to allow recursive calls
+    scope.add_procedure(&procedure_ident, parameters);
+    if let Some(export) = export {
+        scope.export(&procedure_ident, export);
+    }
+    let symbol = lookup(scope, &procedure_ident)?;
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Semicolon, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::Semicolon]));
+    }
+
+    let declarations = parse_declarations(scanner, &procedure_scope, &mut parse_begin_end)?;
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::End, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::End]));
+    }
+
+    let current = current_token(scanner)?;
+    match current.as_ref() {
+        Scan {
+            token: Token::Ident(ending_ident),
+            ..
+        } => {
+            if *ending_ident != procedure_ident {
+                let error = ParseError::UnexpectedBlockEnding {
+                    expected: procedure_ident,
+                    found: String::from(ending_ident),
+                };
+                report_error(scanner, scope, error)?;
+            }
+            scan_next(scanner)?;
+        }
+        _ => {
+            return Err(unexpected(current, &[IDENT]));
+        }
+    }
+
+    let span = span_from(scanner, start);
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Semicolon, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::Semicolon]));
+    }
+
+    let frame_size = procedure_scope.size();
+    let next_declarations = parse_procedure_declarations(scanner, scope)?;
+
+    Ok(ast::node_at(NodeInfo::ProcedureDeclaration(symbol, frame_size), declarations, next_declarations, span))
+}
+
+fn parse_formal_parameters(scanner: &mut Scanner, scope: &Scope) -> Result<Vec<Rc<Symbol>>, ParseError> {
+    let mut parameters = vec![];
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Lparen, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Ok(parameters);
+    }
+
+    let mut current = current_token(scanner)?;
+    if let Scan { token: Token::Rparen, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(parameters);
+    }
+
+    loop {
+        let by_reference = if let Scan { token: Token::Var, .. } = current.as_ref() {
+            scan_next(scanner)?;
+            true
+        } else {
+            false
+        };
 
-            Ok(ast::node(NodeInfo::Declaration, var, var_declarations(idents, scope, node_type, final_sibling)?))
+        let idents = parse_ident_list(scanner, scope)?;
+        current = current_token(scanner)?;
+        if idents.is_empty() {
+            return Err(unexpected(current, &[IDENT]));
+        }
+
+        if let Scan { token: Token::Colon, .. } = current.as_ref() {
+            scan_next(scanner)?;
+        } else {
+            return Err(unexpected(current, &[Token::Colon]));
+        }
+
+        current = current_token(scanner)?;
+        let var_type = if let Scan {
+            token: Token::Ident(type_ident), ..
+        } = current.as_ref()
+        {
+            // NOTE(pht) parameters are passed in a single register for the moment,
+            // so structured values can only be passed by reference
+            let var_type = lookup_type_or_report(scanner, scope, type_ident)?;
+            if var_type.is_structured() && !by_reference {
+                return Err(unexpected(current, &[]));
+            }
+            scan_next(scanner)?;
+            var_type
+        } else {
+            return Err(unexpected(current, &[IDENT]));
+        };
+
+        for (ident, ident_context, _) in idents.iter() {
+            if scope.lookup_local(ident).is_some() {
+                return Err(ParseError::SymbolAlreadyDeclared(String::from(ident), *ident_context));
+            }
+            scope.add_parameter(ident, var_type.clone(), by_reference);
+            parameters.push(lookup(scope, ident)?);
+        }
+
+        current = current_token(scanner)?;
+        match current.as_ref() {
+            Scan { token: Token::Semicolon, .. } => {
+                scan_next(scanner)?;
+                current = current_token(scanner)?;
+            }
+            Scan { token: Token::Rparen, .. } => {
+                scan_next(scanner)?;
+                return Ok(parameters);
+            }
+            _ => {
+                return Err(unexpected(current, &[Token::Semicolon, Token::Rparen]));
+            }
         }
     }
 }
@@ -298,134 +864,672 @@ pub fn var_declarations(idents: &mut dyn Iterator<Item = &(String, ScanContext)>
 pub fn parse_statement_sequence(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
     debug!("parse_statement_sequence {:?}", current_token(scanner));
 
-    let first_statement = parse_statement(scanner, scope)?;
+    let context = current_token(scanner)?.context;
+    // Statements that can not be parsed are left out of the tree
+    let statement = parse_statement(scanner, scope);
+    let first_statement = recover(scanner, scope, statement, &STATEMENT_END)?.unwrap_or_else(ast::empty);
+    let span = span_from(scanner, context);
     let current = current_token_or_none(scanner);
     debug!("parse_statement current ? {:?}", current);
 
     let next_statement = match current {
-        Some(scan) => {
-            if let Scan { token: Token::Semicolon, .. } = scan.as_ref() {
+        Some(scan) => match scan.token {
+            Token::Semicolon => {
                 scan_next(scanner)?;
                 parse_statement_sequence(scanner, scope)?
+            }
+            // A missing semicolon is reported, and the next statement is parsed as if it was there
+            Token::Ident(_) | Token::If | Token::While | Token::Repeat | Token::For | Token::Case => {
+                report_error(scanner, scope, unexpected(scan, &[Token::Semicolon]))?;
+                parse_statement_sequence(scanner, scope)?
+            }
+            _ => ast::empty(),
+        },
+        _ => ast::empty(),
+    };
+
+    Ok(ast::node_at(NodeInfo::StatementSequence(context), first_statement, next_statement, span))
+}
+
+pub fn parse_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    debug!("parse_statement {:?}", current_token(scanner));
+    let mut current = current_token(scanner)?;
+
+    if let Scan { token: Token::Ident(ident), .. } = current.as_ref() {
+        let symbol = lookup_qualified(scanner, scope, ident)?;
+        match symbol.kind {
+            SymbolKind::Procedure(_) => {
+                scan_next(scanner)?;
+                return parse_procedure_call(scanner, scope, symbol, current.context);
+            }
+            SymbolKind::StandardProcedure(procedure) if !procedure.is_function() => {
+                scan_next(scanner)?;
+                return parse_standard_procedure_call(scanner, scope, procedure, current.context);
+            }
+            SymbolKind::Constant(_) | SymbolKind::Type | SymbolKind::StandardProcedure(_) => {
+                return Err(unexpected(current, &[]));
+            }
+            _ => {}
+        }
+
+        let (subject, subject_type) = parse_designator(scanner, scope, symbol.clone())?;
+
+        current = current_token(scanner)?;
+        if current.as_ref().token == Token::Becomes {
+            expect_writable(&symbol)?;
+            scan_next(scanner)?;
+            return parse_assignment(subject, &subject_type, scanner, scope);
+        }
+        return Err(unexpected(current, &[Token::Becomes]));
+    }
+
+    if let Scan { token: Token::If, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_if_statement(scanner, scope);
+    }
+
+    if let Scan { token: Token::While, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_while_statement(scanner, scope);
+    }
+
+    if let Scan { token: Token::Repeat, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_repeat_statement(scanner, scope);
+    }
+
+    if let Scan { token: Token::For, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_for_statement(scanner, scope);
+    }
+
+    if let Scan { token: Token::Case, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_case_statement(scanner, scope);
+    }
+
+    Err(unexpected(current, &[IDENT, Token::If, Token::While, Token::Repeat, Token::For, Token::Case]))
+}
+
+// Calls start at the name of the procedure, which has been consumed
+fn parse_procedure_call(scanner: &mut Scanner, scope: &Scope, procedure: Rc<Symbol>, start: ScanContext) -> ParseResult {
+    debug!("parse_procedure_call {:?}", current_token_or_none(scanner));
+    let parameters = match &procedure.kind {
+        SymbolKind::Procedure(parameters) => parameters,
+        _ => panic!(
+            "Programmer error: function `parse_procedure_call` called with symbol {:?} that is not a procedure.",
+            procedure
+        ),
+    };
+
+    let mut actual_parameters = vec![];
+
+    let current = current_token_or_none(scanner);
+    if let Some(Scan { token: Token::Lparen, .. }) = current.as_deref() {
+        scan_next(scanner)?;
+
+        let mut current = current_token(scanner)?;
+        if let Scan { token: Token::Rparen, .. } = current.as_ref() {
+            scan_next(scanner)?;
+        } else {
+            loop {
+                let by_reference = parameters
+                    .get(actual_parameters.len())
+                    .map_or(false, |parameter| matches!(parameter.kind, SymbolKind::Parameter { by_reference: true }));
+
+                // VAR parameters need a variable to take the address of, not an expression
+                let parameter_start = current.context;
+                let actual_parameter = if by_reference {
+                    match current.as_ref() {
+                        Scan { token: Token::Ident(ident), .. } => {
+                            let symbol = lookup_qualified(scanner, scope, ident)?;
+                            expect_writable(&symbol)?;
+                            parse_ident_with_selector(scanner, scope, symbol)?
+                        }
+                        _ => return Err(unexpected(current, &[IDENT])),
+                    }
+                } else {
+                    parse_expression(scanner, scope)?
+                };
+                actual_parameters.push((actual_parameter, span_from(scanner, parameter_start)));
+
+                current = current_token(scanner)?;
+                match current.as_ref() {
+                    Scan { token: Token::Comma, .. } => {
+                        scan_next(scanner)?;
+                        current = current_token(scanner)?;
+                    }
+                    Scan { token: Token::Rparen, .. } => {
+                        scan_next(scanner)?;
+                        break;
+                    }
+                    _ => {
+                        return Err(unexpected(current, &[Token::Comma, Token::Rparen]));
+                    }
+                }
+            }
+        }
+    }
+
+    if actual_parameters.len() != parameters.len() {
+        return Err(ParseError::ParameterCountMismatch {
+            procedure: procedure.name.clone(),
+            expected: parameters.len(),
+            found: actual_parameters.len(),
+        });
+    }
+
+    let mut parameters_tree = ast::empty();
+    for (actual_parameter, span) in actual_parameters.into_iter().rev() {
+        parameters_tree = ast::node_at(NodeInfo::ActualParameter, actual_parameter, parameters_tree, span);
+    }
+
+    Ok(ast::node_at(NodeInfo::ProcedureCall(procedure), parameters_tree, ast::empty(), span_from(scanner, start)))
+}
+
+// Parameters of predeclared procedures are expressions, except for the variables changed by INC, DEC and In.Int
+// NOTE(pht) the parentheses can only be omitted when there is no parameter, as in Out.Ln
+fn parse_standard_procedure_call(scanner: &mut Scanner, scope: &Scope, procedure: StandardProcedure, start: ScanContext) -> ParseResult {
+    let line = start.line;
+    let (min, max) = procedure.parameter_count();
+    let mut actual_parameters = vec![];
+    let mut capacity = None;
+
+    let current = current_token_or_none(scanner);
+    match current.as_deref() {
+        Some(Scan { token: Token::Lparen, .. }) => scan_next(scanner)?,
+        _ if max == 0 => {
+            let span = span_from(scanner, start);
+            return Ok(ast::node_at(NodeInfo::StandardProcedureCall(procedure, line), ast::empty(), ast::empty(), span));
+        }
+        _ => return Err(unexpected(current_token(scanner)?, &[Token::Lparen])),
+    }
+
+    if current_token(scanner)?.token == Token::Rparen {
+        scan_next(scanner)?;
+    } else {
+        loop {
+            let variable_expected = actual_parameters.is_empty() && matches!(procedure, StandardProcedure::Inc | StandardProcedure::Dec | StandardProcedure::InInt);
+            let current = current_token(scanner)?;
+            let actual_parameter = match current.as_ref() {
+                Scan { token: Token::Ident(ident), .. } if variable_expected => {
+                    let symbol = lookup_qualified(scanner, scope, ident)?;
+                    if !matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter { .. }) {
+                        return Err(unexpected(current, &[]));
+                    }
+                    expect_writable(&symbol)?;
+                    parse_ident_with_selector(scanner, scope, symbol)?
+                }
+                _ if variable_expected => return Err(unexpected(current, &[IDENT])),
+                // The code of HALT is part of the trap instruction
+                _ if procedure == StandardProcedure::Halt => {
+                    let (code, _) = parse_constant_expression(scanner, scope)?;
+                    ast::leaf_at(NodeInfo::Constant(code), span_from(scanner, current.context))
+                }
+                _ if procedure == StandardProcedure::OutString => {
+                    let (string, string_capacity) = parse_printed_string(scanner, scope)?;
+                    capacity = Some(string_capacity);
+                    string
+                }
+                _ => parse_expression(scanner, scope)?,
+            };
+            actual_parameters.push((actual_parameter, span_from(scanner, current.context)));
+
+            let current = current_token(scanner)?;
+            match current.token {
+                Token::Comma => scan_next(scanner)?,
+                Token::Rparen => {
+                    scan_next(scanner)?;
+                    break;
+                }
+                _ => return Err(unexpected(current, &[Token::Comma, Token::Rparen])),
+            }
+        }
+    }
+
+    if actual_parameters.len() < min || actual_parameters.len() > max {
+        return Err(ParseError::ParameterCountMismatch {
+            procedure: String::from(procedure.name()),
+            expected: actual_parameters.len().clamp(min, max),
+            found: actual_parameters.len(),
+        });
+    }
+
+    // NOTE(pht) Out.String prints until the terminating 0X, but never past the end of the array,
+    // so the capacity of the array is passed along.
+    // The capacity is located with the string it comes from
+    if let Some(capacity) = capacity {
+        let span = actual_parameters[0].1;
+        actual_parameters.push((ast::leaf_at(NodeInfo::Constant(capacity), span), span));
+    }
+
+    let mut parameters_tree = ast::empty();
+    for (actual_parameter, span) in actual_parameters.into_iter().rev() {
+        parameters_tree = ast::node_at(NodeInfo::ActualParameter, actual_parameter, parameters_tree, span);
+    }
+
+    Ok(ast::node_at(
+        NodeInfo::StandardProcedureCall(procedure, line),
+        parameters_tree,
+        ast::empty(),
+        span_from(scanner, start),
+    ))
+}
+
+// A string, or an array of characters, along with its capacity
+fn parse_printed_string(scanner: &mut Scanner, scope: &Scope) -> Result<(Ast, i32), ParseError> {
+    let current = current_token(scanner)?;
+    match current.as_ref() {
+        Scan {
+            token: Token::String(value),
+            context,
+        } => {
+            scan_next(scanner)?;
+            Ok((ast::leaf_at(NodeInfo::String(value.clone()), span_from(scanner, *context)), value.len() as i32 + 1))
+        }
+        Scan { token: Token::Ident(ident), .. } => {
+            let symbol = lookup_qualified(scanner, scope, ident)?;
+            if !matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter { .. }) {
+                return Err(unexpected(current, &[]));
+            }
+            let (designator, designator_type) = parse_designator(scanner, scope, symbol)?;
+            match designator_type {
+                VarType::Array(capacity, element_type) if *element_type == VarType::Char => Ok((designator, capacity as i32)),
+                _ => Err(unexpected(current, &[])),
+            }
+        }
+        _ => Err(unexpected(current, &[Token::String(String::new()), IDENT])),
+    }
+}
+
+fn parse_assignment(subject: Rc<Tree>, subject_type: &VarType, scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    debug!("parse_assignment {:?}", current_token(scanner));
+
+    let mut object = parse_expression(scanner, scope)?;
+
+    // NOTE(pht) a string object is copied from the data area, a single character is stored as a value
+    if let Some(NodeInfo::String(value)) = ast::info(&object) {
+        match subject_type {
+            VarType::Array(capacity, element_type) if **element_type == VarType::Char && value.len() < *capacity as usize => {}
+            VarType::Char if value.len() == 1 => {
+                object = ast::leaf_at(NodeInfo::Char(value.as_bytes()[0]), ast::span(&object).unwrap_or_default());
+            }
+            _ => return Err(ParseError::StringNotAssignable(String::from(value), ast::span(&object).unwrap_or_default().start)),
+        }
+    }
+
+    let start = ast::span(&subject).unwrap_or_default().start;
+    Ok(Rc::new(Tree::Node(TreeNode {
+        info: NodeInfo::Assignement,
+        child: subject,
+        sibling: object,
+        span: span_from(scanner, start),
+    })))
+}
+
+// Statements start at their keyword, which has been consumed already
+pub fn parse_if_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = scanner.previous_start();
+    debug!("parse_if_statement {:?}", current_token(scanner));
+    let test_expression = parse_expression(scanner, scope)?;
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Then, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::Then]));
+    }
+    let then_statement_sequence = parse_statement_sequence(scanner, scope)?;
+    let then_span = span_from(scanner, current.context);
+
+    let else_statement_sequence = parse_else_branches(scanner, scope)?;
+    let current = current_token(scanner)?;
+
+    if let Scan { token: Token::End, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::node_at(
+            NodeInfo::IfStatement,
+            test_expression,
+            ast::node_at(NodeInfo::Then, then_statement_sequence, else_statement_sequence, then_span),
+            span_from(scanner, start),
+        ));
+    }
+
+    Err(unexpected(current, &[Token::End]))
+}
+
+// ELSIF branches are chained like nested IF statements, and end with the optional ELSE branch
+fn parse_else_branches(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let current = current_token(scanner)?;
+    match current.as_ref() {
+        Scan { token: Token::Elsif, context } => {
+            scan_next(scanner)?;
+            let test_expression = parse_expression(scanner, scope)?;
+
+            let current = current_token(scanner)?;
+            if let Scan { token: Token::Then, .. } = current.as_ref() {
+                scan_next(scanner)?;
             } else {
-                ast::empty()
+                return Err(unexpected(current, &[Token::Then]));
             }
+            let then_statement_sequence = parse_statement_sequence(scanner, scope)?;
+            let then_span = span_from(scanner, current.context);
+            // The ELSIF branch ends with its statements, the next branches are chained to it
+            let span = span_from(scanner, *context);
+
+            let else_statement_sequence = parse_else_branches(scanner, scope)?;
+            Ok(ast::node_at(
+                NodeInfo::Elsif,
+                test_expression,
+                ast::node_at(NodeInfo::Then, then_statement_sequence, else_statement_sequence, then_span),
+                span,
+            ))
+        }
+        Scan { token: Token::Else, context } => {
+            scan_next(scanner)?;
+            let statement_sequence = parse_statement_sequence(scanner, scope)?;
+            Ok(ast::node_at(NodeInfo::Else, statement_sequence, ast::empty(), span_from(scanner, *context)))
+        }
+        _ => Ok(ast::empty()),
+    }
+}
+
+pub fn parse_while_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = scanner.previous_start();
+    debug!("parse_while_statement {:?}", current_token(scanner));
+    let test_expression = parse_expression(scanner, scope)?;
+
+    let do_statement_sequence;
+    let do_span;
+    let current = current_token(scanner)?;
+    let current = match current.as_ref() {
+        Scan { token: Token::Do, context } => {
+            scan_next(scanner)?;
+            do_statement_sequence = parse_statement_sequence(scanner, scope)?;
+            do_span = span_from(scanner, *context);
+            current_token(scanner)?
+        }
+        _ => {
+            return Err(unexpected(current, &[Token::Do]));
         }
-        _ => ast::empty(),
     };
 
-    Ok(ast::node(NodeInfo::StatementSequence, first_statement, next_statement))
-}
+    if let Scan { token: Token::End, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::node_at(
+            NodeInfo::WhileStatement,
+            test_expression,
+            ast::node_at(NodeInfo::Do, do_statement_sequence, ast::empty(), do_span),
+            span_from(scanner, start),
+        ));
+    }
+
+    Err(unexpected(current, &[Token::End]))
+}
+
+pub fn parse_repeat_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = scanner.previous_start();
+    debug!("parse_repeat_statement {:?}", current_token(scanner));
+    let statement_sequence = parse_statement_sequence(scanner, scope)?;
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Until, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        let test_expression = parse_expression(scanner, scope)?;
+        return Ok(ast::node_at(NodeInfo::RepeatStatement, statement_sequence, test_expression, span_from(scanner, start)));
+    }
+
+    Err(unexpected(current, &[Token::Until]))
+}
+
+// The increment of the control variable is added at the end of the statements of the loop,
+// so that `FOR i := 0 TO 10 BY 2 DO S END` gives the tree:
+// ForStatement(2) -> (i := 0), To -> (10), Do -> (S; i := i + 2)
+// The nodes added for the increment are located at the control variable and the step.
+pub fn parse_for_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = scanner.previous_start();
+    debug!("parse_for_statement {:?}", current_token(scanner));
+
+    let current = current_token(scanner)?;
+    let context = current.context;
+    let control_symbol = match current.as_ref() {
+        Scan { token: Token::Ident(ident), .. } => {
+            let symbol = lookup(scope, ident)?;
+            match symbol.kind {
+                SymbolKind::Variable | SymbolKind::Parameter { .. } if symbol.declared_type == Some(VarType::Integer) => {
+                    scan_next(scanner)?;
+                    symbol
+                }
+                _ => return Err(unexpected(current, &[])),
+            }
+        }
+        _ => return Err(unexpected(current, &[IDENT])),
+    };
+
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::Becomes, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::Becomes]));
+    }
+    let begin_expression = parse_expression(scanner, scope)?;
+    let control_span = ident_span(scanner.file(), context, &control_symbol.name);
+    let initialization_span = span_from(scanner, context);
+
+    let current = current_token(scanner)?;
+    let to_context = current.context;
+    if let Scan { token: Token::To, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::To]));
+    }
+    let limit_expression = parse_expression(scanner, scope)?;
 
-pub fn parse_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    debug!("parse_statement {:?}", current_token(scanner));
+    let mut step = 1;
+    let mut step_span = control_span;
     let mut current = current_token(scanner)?;
-
-    if let Scan { token: Token::Ident(ident), .. } = current.as_ref() {
-        let subject = parse_ident_with_selector(scanner, scope, ident)?;
-
+    if let Scan { token: Token::By, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        let step_start = current_start(scanner);
+        step = parse_step(scanner, scope)?;
+        step_span = span_from(scanner, step_start);
         current = current_token(scanner)?;
-        if current.as_ref().token == Token::Becomes {
-            scan_next(scanner)?;
-            return parse_assignment(subject, scanner, scope);
-        }
-        return Err(ParseError::UnexpectedToken(current));
     }
 
-    if let Scan { token: Token::If, .. } = current.as_ref() {
+    let do_context = current.context;
+    if let Scan { token: Token::Do, .. } = current.as_ref() {
         scan_next(scanner)?;
-        return parse_if_statement(scanner, scope);
+    } else {
+        return Err(unexpected(current, &[Token::Do]));
     }
+    let statement_sequence = parse_statement_sequence(scanner, scope)?;
+    let statement_span = span_from(scanner, do_context);
 
-    if let Scan { token: Token::While, .. } = current.as_ref() {
+    let current = current_token(scanner)?;
+    if let Scan { token: Token::End, .. } = current.as_ref() {
         scan_next(scanner)?;
-        return parse_while_statement(scanner, scope);
+    } else {
+        return Err(unexpected(current, &[Token::End]));
     }
 
-    Err(ParseError::UnexpectedToken(current))
+    let increment_operator = if step > 0 { SimpleExpressionOp::Plus } else { SimpleExpressionOp::Minus };
+    let increment = ast::node_at(
+        NodeInfo::Assignement,
+        ast::leaf_at(NodeInfo::Ident(control_symbol.clone()), control_span),
+        ast::node_at(
+            NodeInfo::SimpleExpression(increment_operator),
+            ast::leaf_at(NodeInfo::Ident(control_symbol.clone()), control_span),
+            ast::leaf_at(NodeInfo::Constant(step.abs()), step_span),
+            step_span,
+        ),
+        step_span,
+    );
+    let statement_sequence = ast::node_at(
+        NodeInfo::StatementSequence(context),
+        statement_sequence,
+        ast::node_at(NodeInfo::StatementSequence(context), increment, ast::empty(), step_span),
+        statement_span,
+    );
+
+    let initialization = ast::node_at(
+        NodeInfo::Assignement,
+        ast::leaf_at(NodeInfo::Ident(control_symbol), control_span),
+        begin_expression,
+        initialization_span,
+    );
+    Ok(ast::node_at(
+        NodeInfo::ForStatement(step),
+        initialization,
+        ast::node_at(
+            NodeInfo::To,
+            limit_expression,
+            ast::node_at(NodeInfo::Do, statement_sequence, ast::empty(), statement_span),
+            Span {
+                start: to_context,
+                ..statement_span
+            },
+        ),
+        span_from(scanner, start),
+    ))
 }
 
-fn parse_assignment(subject: Rc<Tree>, scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    debug!("parse_assignment {:?}", current_token(scanner));
-
-    let object = parse_expression(scanner, scope)?;
-
-    Ok(Rc::new(Tree::Node(TreeNode {
-        info: NodeInfo::Assignement,
-        child: subject,
-        sibling: object,
-    })))
+// The step of a FOR loop is a constant expression, that can be negative, but not zero
+fn parse_step(scanner: &mut Scanner, scope: &Scope) -> Result<i32, ParseError> {
+    let current = current_token(scanner)?;
+    match parse_constant_expression(scanner, scope)? {
+        (step, VarType::Integer) if step != 0 => Ok(step),
+        _ => Err(unexpected(current, &[])),
+    }
 }
 
-pub fn parse_if_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    debug!("parse_if_statement {:?}", current_token(scanner));
-    let test_expression = parse_expression(scanner, scope)?;
+// `CASE x OF 1, 3..5: S1 | 7: S2 ELSE S3 END` gives the tree:
+// CaseStatement -> (x), CaseLabels([(1, 1), (3, 5)], INTEGER) -> (S1), CaseLabels([(7, 7)], INTEGER) -> (S2), Else -> (S3)
+// Labels are constant expressions, of the same type as the selector, which the checker verifies
+pub fn parse_case_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = scanner.previous_start();
+    debug!("parse_case_statement {:?}", current_token(scanner));
+    let selector_expression = parse_expression(scanner, scope)?;
 
-    let then_statement_sequence;
     let current = current_token(scanner)?;
-    let current = match current.as_ref() {
-        Scan { token: Token::Then, .. } => {
-            scan_next(scanner)?;
-            then_statement_sequence = parse_statement_sequence(scanner, scope)?;
-            current_token(scanner)?
-        }
-        _ => {
-            return Err(ParseError::UnexpectedToken(current));
+    if let Scan { token: Token::Of, .. } = current.as_ref() {
+        scan_next(scanner)?;
+    } else {
+        return Err(unexpected(current, &[Token::Of]));
+    }
+
+    let mut cases = vec![];
+    let mut labels: Vec<(i32, i32)> = vec![];
+    loop {
+        let current = current_token(scanner)?;
+        match current.as_ref() {
+            // Empty cases are allowed
+            Scan { token: Token::Bar, .. } => {
+                scan_next(scanner)?;
+            }
+            Scan {
+                token: Token::Else | Token::End, ..
+            } => {
+                break;
+            }
+            _ => {
+                let case_start = current.context;
+                let (case_labels, label_type) = parse_case_label_list(scanner, scope)?;
+                for (low, high) in case_labels.iter() {
+                    if let Some((other_low, _)) = labels.iter().find(|(other_low, other_high)| low <= other_high && other_low <= high) {
+                        return Err(ParseError::DuplicateCaseLabel(*low.max(other_low)));
+                    }
+                    labels.push((*low, *high));
+                }
+
+                let current = current_token(scanner)?;
+                if let Scan { token: Token::Colon, .. } = current.as_ref() {
+                    scan_next(scanner)?;
+                } else {
+                    return Err(unexpected(current, &[Token::Colon]));
+                }
+
+                let statement_sequence = parse_statement_sequence(scanner, scope)?;
+                cases.push((case_labels, label_type, statement_sequence, span_from(scanner, case_start)));
+
+                let current = current_token(scanner)?;
+                match current.as_ref() {
+                    Scan { token: Token::Bar, .. } => {
+                        scan_next(scanner)?;
+                    }
+                    Scan {
+                        token: Token::Else | Token::End, ..
+                    } => {
+                        break;
+                    }
+                    _ => return Err(unexpected(current, &[Token::Bar, Token::Else, Token::End])),
+                }
+            }
         }
-    };
+    }
 
-    let else_statement_sequence;
-    let current = match current.as_ref() {
-        Scan { token: Token::Else, .. } => {
+    let current = current_token(scanner)?;
+    let mut branches = match current.as_ref() {
+        Scan { token: Token::Else, context } => {
             scan_next(scanner)?;
-            else_statement_sequence = ast::node(NodeInfo::Else, parse_statement_sequence(scanner, scope)?, ast::empty());
-            current_token(scanner)?
-        }
-        _ => {
-            else_statement_sequence = ast::empty();
-            current
+            let statement_sequence = parse_statement_sequence(scanner, scope)?;
+            ast::node_at(NodeInfo::Else, statement_sequence, ast::empty(), span_from(scanner, *context))
         }
+        _ => ast::empty(),
     };
 
+    let current = current_token(scanner)?;
     if let Scan { token: Token::End, .. } = current.as_ref() {
         scan_next(scanner)?;
-        return Ok(ast::node(
-            NodeInfo::IfStatement,
-            test_expression,
-            ast::node(NodeInfo::Then, then_statement_sequence, else_statement_sequence),
-        ));
+    } else {
+        return Err(unexpected(current, &[Token::End]));
     }
 
-    Err(ParseError::UnexpectedToken(current))
+    while let Some((case_labels, label_type, statement_sequence, span)) = cases.pop() {
+        branches = ast::node_at(NodeInfo::CaseLabels(case_labels, label_type), statement_sequence, branches, span);
+    }
+    Ok(ast::node_at(NodeInfo::CaseStatement, selector_expression, branches, span_from(scanner, start)))
 }
 
-pub fn parse_while_statement(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    debug!("parse_while_statement {:?}", current_token(scanner));
-    let test_expression = parse_expression(scanner, scope)?;
+// The labels of a branch all have the type of the first one
+fn parse_case_label_list(scanner: &mut Scanner, scope: &Scope) -> Result<(Vec<(i32, i32)>, VarType), ParseError> {
+    let mut case_labels = vec![];
+    let mut label_type = None;
+    loop {
+        let (low, low_type) = parse_case_label(scanner, scope, label_type.as_ref())?;
+        label_type = Some(low_type.clone());
+        let mut high = low;
 
-    let do_statement_sequence;
-    let current = current_token(scanner)?;
-    let current = match current.as_ref() {
-        Scan { token: Token::Do, .. } => {
+        let current = current_token(scanner)?;
+        if let Scan { token: Token::Upto, .. } = current.as_ref() {
             scan_next(scanner)?;
-            do_statement_sequence = parse_statement_sequence(scanner, scope)?;
-            current_token(scanner)?
-        }
-        _ => {
-            return Err(ParseError::UnexpectedToken(current));
+            (high, _) = parse_case_label(scanner, scope, label_type.as_ref())?;
+            if high < low {
+                return Err(unexpected(current, &[]));
+            }
         }
-    };
+        case_labels.push((low, high));
 
-    if let Scan { token: Token::End, .. } = current.as_ref() {
-        scan_next(scanner)?;
-        return Ok(ast::node(
-            NodeInfo::WhileStatement,
-            test_expression,
-            ast::node(NodeInfo::Do, do_statement_sequence, ast::empty()),
-        ));
+        let current = current_token(scanner)?;
+        if let Scan { token: Token::Comma, .. } = current.as_ref() {
+            scan_next(scanner)?;
+        } else {
+            return Ok((case_labels, low_type));
+        }
     }
+}
 
-    Err(ParseError::UnexpectedToken(current))
+// Characters are given by their code, so that they can be compared to integers in jump tables
+fn parse_case_label(scanner: &mut Scanner, scope: &Scope, expected: Option<&VarType>) -> Result<(i32, VarType), ParseError> {
+    let context = current_token(scanner)?.context;
+    let (value, found) = parse_constant_expression(scanner, scope)?;
+    match expected {
+        Some(expected) if *expected != found => Err(ParseError::TypeMismatch {
+            expected: expected.clone(),
+            found,
+            context,
+        }),
+        _ => Ok((value, found)),
+    }
 }
 
 pub fn parse_expression(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
@@ -441,6 +1545,7 @@ pub fn parse_expression(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             Token::Leq => parse_expression_relation(scanner, scope, first_expression, ExpressionOp::Leq),
             Token::Gtr => parse_expression_relation(scanner, scope, first_expression, ExpressionOp::Gtr),
             Token::Geq => parse_expression_relation(scanner, scope, first_expression, ExpressionOp::Geq),
+            Token::In => parse_expression_relation(scanner, scope, first_expression, ExpressionOp::In),
             _ => Ok(first_expression),
         },
     }
@@ -449,11 +1554,18 @@ pub fn parse_expression(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
 pub fn parse_expression_relation(scanner: &mut Scanner, scope: &Scope, first_expression: Rc<Tree>, expression_op: ExpressionOp) -> ParseResult {
     scan_next(scanner)?;
     let second_expression = parse_simple_expression(scanner, scope)?;
-    Ok(ast::node(NodeInfo::Expression(expression_op), first_expression, second_expression))
+    let start = ast::span(&first_expression).unwrap_or_default().start;
+    Ok(ast::node_at(
+        NodeInfo::Expression(expression_op),
+        first_expression,
+        second_expression,
+        span_from(scanner, start),
+    ))
 }
 
 pub fn parse_simple_expression(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
-    let mut tree = parse_term(scanner, scope)?;
+    let start = current_start(scanner);
+    let mut tree = parse_signed_term(scanner, scope)?;
     debug!("parse_simple_expression ; parsed term {:?}", tree);
 
     loop {
@@ -464,6 +1576,7 @@ pub fn parse_simple_expression(scanner: &mut Scanner, scope: &Scope) -> ParseRes
             let operator: Option<SimpleExpressionOp> = match scan.as_ref() {
                 Scan { token: Token::Plus, .. } => Some(SimpleExpressionOp::Plus),
                 Scan { token: Token::Minus, .. } => Some(SimpleExpressionOp::Minus),
+                Scan { token: Token::Or, .. } => Some(SimpleExpressionOp::Or),
                 _ => None,
             };
 
@@ -472,10 +1585,16 @@ pub fn parse_simple_expression(scanner: &mut Scanner, scope: &Scope) -> ParseRes
                     debug!("parse_simple_expression in loop, + found");
                     scan_next(scanner)?;
                     let sibling = parse_term(scanner, scope)?;
+                    let operator = match operator {
+                        SimpleExpressionOp::Plus if is_set_operation(&tree, &sibling) => SimpleExpressionOp::Union,
+                        SimpleExpressionOp::Minus if is_set_operation(&tree, &sibling) => SimpleExpressionOp::Difference,
+                        _ => operator,
+                    };
                     let node = TreeNode {
                         info: NodeInfo::SimpleExpression(operator),
                         child: tree,
                         sibling,
+                        span: span_from(scanner, start),
                     };
                     tree = Rc::new(Tree::Node(node));
                     continue;
@@ -490,7 +1609,41 @@ pub fn parse_simple_expression(scanner: &mut Scanner, scope: &Scope) -> ParseRes
     Ok(tree)
 }
 
+// The sign applies to the whole first term, so `-a * b` is `-(a * b)`
+fn parse_signed_term(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let current = current_token(scanner)?;
+    match current.as_ref() {
+        Scan { token: Token::Plus, .. } => {
+            scan_next(scanner)?;
+            parse_term(scanner, scope)
+        }
+        Scan { token: Token::Minus, context } => {
+            scan_next(scanner)?;
+            let term = parse_term(scanner, scope)?;
+            let span = span_from(scanner, *context);
+            // Negative literals are folded, the scanner never produces them
+            match ast::info(&term) {
+                Some(NodeInfo::Constant(value)) => match value.checked_neg() {
+                    Some(value) => Ok(ast::leaf_at(NodeInfo::Constant(value), span)),
+                    None => Err(ParseError::InvalidConstantExpression),
+                },
+                // The complement of a set is its difference with the full set, which is located at the sign
+                Some(NodeInfo::Set(value)) => Ok(ast::leaf_at(NodeInfo::Set(!value), span)),
+                _ if is_set(&term) => Ok(ast::node_at(
+                    NodeInfo::SimpleExpression(SimpleExpressionOp::Difference),
+                    ast::leaf_at(NodeInfo::Set(u32::MAX), ident_span(scanner.file(), *context, "-")),
+                    term,
+                    span,
+                )),
+                _ => Ok(ast::node_at(NodeInfo::Negate, term, ast::empty(), span)),
+            }
+        }
+        _ => parse_term(scanner, scope),
+    }
+}
+
 pub fn parse_term(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let start = current_start(scanner);
     let mut tree = parse_factor(scanner, scope)?;
     loop {
         let current = current_token_or_none(scanner);
@@ -503,7 +1656,10 @@ pub fn parse_term(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             Some(scan) => {
                 let operator: Option<TermOp> = match scan.as_ref() {
                     Scan { token: Token::Times, .. } => Some(TermOp::Times),
-                    Scan { token: Token::Div, .. } => Some(TermOp::Div),
+                    // NOTE(pht) without REAL, '/' is kept as a synonym of DIV, like it was before the keyword existed
+                    Scan { token: Token::Div, .. } | Scan { token: Token::Rdiv, .. } => Some(TermOp::Div),
+                    Scan { token: Token::Mod, .. } => Some(TermOp::Mod),
+                    Scan { token: Token::And, .. } => Some(TermOp::And),
                     _ => None,
                 };
 
@@ -512,10 +1668,16 @@ pub fn parse_term(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
                         debug!("parse_simple_expression in loop, + found");
                         scan_next(scanner)?;
                         let sibling = parse_factor(scanner, scope)?;
+                        let operator = match operator {
+                            TermOp::Times if is_set_operation(&tree, &sibling) => TermOp::Intersection,
+                            TermOp::Div if is_set_operation(&tree, &sibling) && scan.token == Token::Rdiv => TermOp::SymmetricDifference,
+                            _ => operator,
+                        };
                         let node = TreeNode {
                             info: NodeInfo::Term(operator),
                             child: tree,
                             sibling,
+                            span: span_from(scanner, start),
                         };
                         tree = Rc::new(Tree::Node(node));
                         continue;
@@ -539,11 +1701,63 @@ pub fn parse_factor(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
     } = current.as_ref()
     {
         scan_next(scanner)?;
-        return Ok(ast::leaf(NodeInfo::Constant(*constant_value)));
+        // NOTE(pht) the scanner guarantees that literals fit in an i32
+        return Ok(ast::leaf_at(NodeInfo::Constant(*constant_value as i32), span_from(scanner, current.context)));
+    }
+
+    if let Scan { token: Token::Char(value), .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::leaf_at(NodeInfo::Char(*value), span_from(scanner, current.context)));
+    }
+
+    if let Scan { token: Token::String(value), .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::leaf_at(NodeInfo::String(String::from(value)), span_from(scanner, current.context)));
+    }
+
+    if let Scan { token: Token::Lbrace, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return parse_set_constructor(scanner, scope, current.context);
+    }
+
+    if let Scan { token: Token::True, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::leaf_at(NodeInfo::Boolean(true), span_from(scanner, current.context)));
+    }
+
+    if let Scan { token: Token::False, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        return Ok(ast::leaf_at(NodeInfo::Boolean(false), span_from(scanner, current.context)));
+    }
+
+    if let Scan { token: Token::Not, .. } = current.as_ref() {
+        scan_next(scanner)?;
+        let factor = parse_factor(scanner, scope)?;
+        return Ok(ast::node_at(NodeInfo::Not, factor, ast::empty(), span_from(scanner, current.context)));
     }
 
     if let Scan { token: Token::Ident(ident), .. } = current.as_ref() {
-        return parse_ident_with_selector(scanner, scope, ident);
+        let symbol = lookup_qualified(scanner, scope, ident)?;
+        match symbol.kind {
+            // NOTE(pht) procedures can only be called as statements, they do not return a value
+            SymbolKind::Procedure(_) | SymbolKind::Type => {
+                return Err(unexpected(current, &[]));
+            }
+            SymbolKind::StandardProcedure(procedure) => {
+                if !procedure.is_function() {
+                    return Err(unexpected(current, &[]));
+                }
+                scan_next(scanner)?;
+                return parse_standard_procedure_call(scanner, scope, procedure, current.context);
+            }
+            SymbolKind::Constant(value) => {
+                scan_next(scanner)?;
+                return Ok(constant_leaf(value, &symbol.declared_type, span_from(scanner, current.context)));
+            }
+            _ => {
+                return parse_ident_with_selector(scanner, scope, symbol);
+            }
+        }
     }
 
     if let Scan { token: Token::Lparen, .. } = current.as_ref() {
@@ -555,65 +1769,206 @@ pub fn parse_factor(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
             scan_next(scanner)?;
             return expression;
         }
+        return Err(unexpected(current, &[Token::Rparen]));
     }
 
-    Err(ParseError::UnexpectedToken(current))
+    Err(unexpected(current, &FACTOR_START))
 }
 
-pub fn parse_ident_with_selector(scanner: &mut Scanner, scope: &Scope, ident: &str) -> ParseResult {
-    let symbol = lookup(scope, ident)?;
+// `{1, 3..5}` ; sets made of constant elements only are computed at compile time
+fn parse_set_constructor(scanner: &mut Scanner, scope: &Scope, start: ScanContext) -> ParseResult {
+    let mut elements = vec![];
+
+    let mut current = current_token(scanner)?;
+    if current.token != Token::Rbrace {
+        loop {
+            let element_start = current_start(scanner);
+            let low = parse_set_element(scanner, scope)?;
+            current = current_token(scanner)?;
+            let element = if current.token == Token::Upto {
+                scan_next(scanner)?;
+                let high = parse_set_element(scanner, scope)?;
+                current = current_token(scanner)?;
+                ast::node_at(NodeInfo::Range, low, high, span_from(scanner, element_start))
+            } else {
+                low
+            };
+            elements.push((element, span_from(scanner, element_start)));
+
+            match current.token {
+                Token::Comma => scan_next(scanner)?,
+                Token::Rbrace => break,
+                _ => return Err(unexpected(current, &[Token::Comma, Token::Rbrace])),
+            }
+        }
+    }
+    scan_next(scanner)?;
+    let span = span_from(scanner, start);
+
+    let mut elements_tree = ast::empty();
+    for (element, element_span) in elements.into_iter().rev() {
+        elements_tree = ast::node_at(NodeInfo::SetElement, element, elements_tree, element_span);
+    }
+    let constructor = ast::node_at(NodeInfo::SetConstructor, elements_tree, ast::empty(), span);
+
+    match evaluate_constant(&constructor) {
+        Ok((value, VarType::Set)) => Ok(ast::leaf_at(NodeInfo::Set(value as u32), span)),
+        _ => Ok(constructor),
+    }
+}
+
+fn parse_set_element(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let element = parse_expression(scanner, scope)?;
+    match evaluate_constant(&element) {
+        Ok((value, VarType::Integer)) if !(0..=31).contains(&value) => Err(ParseError::SetElementOutOfRange(value)),
+        _ => Ok(element),
+    }
+}
+
+// NOTE(pht) the operators on sets are told apart from the arithmetic ones by the type of their operands.
+// Either operand being a set is enough ; the checker then rejects the other one if it is not a set too.
+fn is_set_operation(left: &Ast, right: &Ast) -> bool {
+    is_set(left) || is_set(right)
+}
+
+fn is_set(tree: &Ast) -> bool {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return false,
+    };
+    match &node.info {
+        NodeInfo::Set(_) | NodeInfo::SetConstructor => true,
+        NodeInfo::Term(TermOp::Intersection) | NodeInfo::Term(TermOp::SymmetricDifference) => true,
+        NodeInfo::SimpleExpression(SimpleExpressionOp::Union) | NodeInfo::SimpleExpression(SimpleExpressionOp::Difference) => true,
+        NodeInfo::Ident(symbol) => selected_type(symbol, &node.child) == Some(VarType::Set),
+        _ => false,
+    }
+}
+
+// Type of a variable once its selectors are applied
+fn selected_type(symbol: &Symbol, selector: &Ast) -> Option<VarType> {
+    let mut var_type = symbol.declared_type.clone()?;
+    let mut selector = selector;
+    while let Tree::Node(node) = selector.as_ref() {
+        var_type = match (&node.info, &var_type) {
+            (NodeInfo::Index(_, _, _), VarType::Array(_, element_type)) => element_type.as_ref().clone(),
+            (NodeInfo::Field(name, _), VarType::Record(fields)) => fields.iter().find(|field| &field.name == name)?.field_type.clone(),
+            _ => return None,
+        };
+        selector = &node.sibling;
+    }
+    Some(var_type)
+}
 
+pub fn parse_ident_with_selector(scanner: &mut Scanner, scope: &Scope, symbol: Rc<Symbol>) -> ParseResult {
+    let (designator, _) = parse_designator(scanner, scope, symbol)?;
+    Ok(designator)
+}
+
+// A variable with its selectors, and the type of what they select
+// The scanner is still on the (last) identifier of the designator
+fn parse_designator(scanner: &mut Scanner, scope: &Scope, symbol: Rc<Symbol>) -> Result<(Ast, VarType), ParseError> {
+    let start = current_token(scanner)?.context;
     scan_next(scanner)?;
 
+    // NOTE(pht) symbols declared without a type (in tests) are considered integers
+    let var_type = symbol.declared_type.clone().unwrap_or(VarType::Integer);
+    let (selector, selected_type) = parse_selector(scanner, scope, &var_type)?;
+    Ok((ast::node_at(NodeInfo::Ident(symbol), selector, ast::empty(), span_from(scanner, start)), selected_type))
+}
+
+// Selectors are chained as siblings of each other, each one applying to the type selected by the previous one
+fn parse_selector(scanner: &mut Scanner, scope: &Scope, var_type: &VarType) -> Result<(Ast, VarType), ParseError> {
     let maybe_selector_start = current_token_or_none(scanner);
     debug!("maybe selector start {:?}", maybe_selector_start);
 
-    match maybe_selector_start {
-        None => Ok(ast::leaf(NodeInfo::Ident(symbol))),
-        Some(scan) => {
-            if let Scan { token: Token::Lbrak, .. } = scan.as_ref() {
-                scan_next(scanner)?;
-                let current = current_token(scanner)?;
+    let scan = match maybe_selector_start {
+        None => return Ok((ast::empty(), var_type.clone())),
+        Some(scan) => scan,
+    };
 
-                // NOTE(pht) this only allows constant and ident access at the moment
-                if let Scan {
-                    token: Token::Int(constant_value),
-                    ..
-                } = current.as_ref()
-                {
-                    scan_next(scanner)?;
-                    let current = current_token(scanner)?;
+    match scan.as_ref() {
+        Scan { token: Token::Lbrak, .. } => {
+            scan_next(scanner)?;
+            parse_index_list(scanner, scope, var_type)
+        }
+        Scan { token: Token::Period, context } => {
+            scan_next(scanner)?;
 
-                    if let Scan { token: Token::Rbrak, .. } = current.as_ref() {
-                        scan_next(scanner)?;
-                        let child = ast::leaf(NodeInfo::Constant(*constant_value));
-                        return Ok(ast::node(NodeInfo::Ident(symbol), child, ast::empty()));
-                    }
-                    return Err(ParseError::UnexpectedToken(current));
-                }
+            let current = current_token(scanner)?;
+            let field_name = match current.as_ref() {
+                Scan {
+                    token: Token::Ident(field_name), ..
+                } => field_name,
+                _ => return Err(unexpected(current, &[IDENT])),
+            };
 
-                if let Scan {
-                    token: Token::Ident(index_ident), ..
-                } = current.as_ref()
-                {
-                    scan_next(scanner)?;
-                    let current = current_token(scanner)?;
+            let fields = match var_type {
+                VarType::Record(fields) => fields,
+                _ => return Err(ParseError::NotARecord(String::from(field_name))),
+            };
+            let field = fields
+                .iter()
+                .find(|field| &field.name == field_name)
+                .ok_or_else(|| ParseError::UndefinedField(String::from(field_name)))?;
+            scan_next(scanner)?;
+            let span = span_from(scanner, *context);
 
-                    if let Scan { token: Token::Rbrak, .. } = current.as_ref() {
-                        scan_next(scanner)?;
+            let (next, selected_type) = parse_selector(scanner, scope, &field.field_type)?;
+            Ok((
+                ast::node_at(NodeInfo::Field(String::from(field_name), field.offset), ast::empty(), next, span),
+                selected_type,
+            ))
+        }
+        _ => Ok((ast::empty(), var_type.clone())),
+    }
+}
 
-                        let index_symbol = lookup(scope, index_ident)?;
-                        let child = ast::leaf(NodeInfo::Ident(index_symbol));
-                        return Ok(ast::node(NodeInfo::Ident(symbol), child, ast::empty()));
-                    }
-                    return Err(ParseError::UnexpectedToken(current));
-                }
+// `m[i, j]` is a shorthand for `m[i][j]`
+// Each index spans its expression and the separator after it
+fn parse_index_list(scanner: &mut Scanner, scope: &Scope, var_type: &VarType) -> Result<(Ast, VarType), ParseError> {
+    let start = current_token(scanner)?.context;
+    let line = start.line;
+    let index = parse_index(scanner, scope)?;
+
+    // NOTE(pht) indexing a variable that is not an array is rejected by the checker
+    let (capacity, element_type) = match var_type {
+        VarType::Array(capacity, element_type) => (Some(*capacity), element_type.as_ref().clone()),
+        _ => (None, VarType::Integer),
+    };
 
-                Err(ParseError::UnexpectedToken(current))
-            } else {
-                Ok(ast::leaf(NodeInfo::Ident(symbol)))
-            }
+    // Constant indices are checked at compile time, other ones at runtime
+    if let (Some(NodeInfo::Constant(index)), Some(capacity)) = (ast::info(&index), capacity) {
+        if *index as u32 >= capacity {
+            return Err(ParseError::IndexOutOfBounds(*index));
+        }
+    }
+
+    let current = current_token(scanner)?;
+    let (next, selected_type, span) = match current.as_ref() {
+        Scan {
+            token: Token::Comma | Token::Rbrak,
+            ..
+        } => {
+            scan_next(scanner)?;
+            let span = span_from(scanner, start);
+            let (next, selected_type) = match current.token {
+                Token::Comma => parse_index_list(scanner, scope, &element_type)?,
+                _ => parse_selector(scanner, scope, &element_type)?,
+            };
+            (next, selected_type, span)
         }
+        _ => return Err(unexpected(current, &[Token::Comma, Token::Rbrak])),
+    };
+    Ok((ast::node_at(NodeInfo::Index(element_type.size(), capacity, line), index, next, span), selected_type))
+}
+
+// Indices that can be computed at compile time are replaced by their value
+fn parse_index(scanner: &mut Scanner, scope: &Scope) -> ParseResult {
+    let index = parse_expression(scanner, scope)?;
+    match evaluate_constant(&index) {
+        Ok((value, VarType::Integer)) if value >= 0 => Ok(ast::leaf_at(NodeInfo::Constant(value), ast::span(&index).unwrap_or_default())),
+        _ => Ok(index),
     }
 }
 
@@ -628,6 +1983,91 @@ pub fn scan_next(scanner: &mut Scanner) -> Result<(), ParseError> {
     }
 }
 
+// NOTE(pht) parameters are passed in R0 to R11 ; R12 is kept for scratch computations, and R13 to R15 hold
+// the static base, the frame pointer and the link.
+const MAX_PARAMETERS: usize = 12;
+
+// Tokens a statement can be followed by
+const STATEMENT_END: [Token; 6] = [Token::Semicolon, Token::End, Token::Else, Token::Elsif, Token::Until, Token::Bar];
+// Tokens a declaration can be followed by, when it ends or when the next section starts
+const DECLARATION_END: [Token; 7] = [Token::Semicolon, Token::Const, Token::Type, Token::Var, Token::Procedure, Token::Begin, Token::End];
+
+// Stands for any identifier in the tokens that were expected, the name is left empty
+const IDENT: Token = Token::Ident(String::new());
+// Tokens a factor can start with, the numbers, characters and strings standing for any of them
+const FACTOR_START: [Token; 9] = [
+    IDENT,
+    Token::Int(0),
+    Token::Char(0),
+    Token::String(String::new()),
+    Token::Lparen,
+    Token::Lbrace,
+    Token::Not,
+    Token::True,
+    Token::False,
+];
+
+fn unexpected(found: Rc<Scan>, expected: &[Token]) -> ParseError {
+    ParseError::UnexpectedToken {
+        expected: expected.to_vec(),
+        found,
+    }
+}
+
+// Scan errors and the end of the file can not be recovered from, nor any error when the scope does not collect them
+fn report_error(scanner: &mut Scanner, scope: &Scope, error: ParseError) -> Result<(), ParseError> {
+    match error {
+        ParseError::ScanError(_) | ParseError::PrematureEof => Err(error),
+        _ => scope.report_error(error, current_token_or_none(scanner).map(|scan| scan.context)),
+    }
+}
+
+// Panic mode: the error is reported, and tokens are skipped until one the parser can resume at
+fn recover<T>(scanner: &mut Scanner, scope: &Scope, result: Result<T, ParseError>, resume_at: &[Token]) -> Result<Option<T>, ParseError> {
+    let error = match result {
+        Ok(value) => return Ok(Some(value)),
+        Err(error) => error,
+    };
+    report_error(scanner, scope, error)?;
+    while let Some(current) = current_token_or_none(scanner) {
+        if resume_at.contains(&current.token) {
+            break;
+        }
+        scan_next(scanner)?;
+    }
+    Ok(None)
+}
+
+// Declarations are resumed after the semicolon they end with, or at the next section
+fn recover_declaration<T>(scanner: &mut Scanner, scope: &Scope, result: Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+    let declaration = recover(scanner, scope, result, &DECLARATION_END)?;
+    if declaration.is_none() && current_token_or_none(scanner).map_or(false, |scan| scan.token == Token::Semicolon) {
+        scan_next(scanner)?;
+    }
+    Ok(declaration)
+}
+
+// Where the next node starts, or the previous one ended at the end of the file
+fn current_start(scanner: &mut Scanner) -> ScanContext {
+    current_token_or_none(scanner).map_or(scanner.previous_end(), |scan| scan.context)
+}
+
+// Span of the tokens consumed since the one at `start`, which is empty when there are none
+fn span_from(scanner: &Scanner, start: ScanContext) -> Span {
+    let end = scanner.previous_end();
+    let end = if (end.line, end.column) < (start.line, start.column) { start } else { end };
+    Span { file: scanner.file(), start, end }
+}
+
+// Identifiers are never split across lines
+fn ident_span(file: FileId, start: ScanContext, ident: &str) -> Span {
+    let end = ScanContext {
+        line: start.line,
+        column: start.column + ident.len() as u32,
+    };
+    Span { file, start, end }
+}
+
 fn current_token(scanner: &mut Scanner) -> Result<Rc<Scan>, ParseError> {
     let current = scanner.current();
     match current {
@@ -640,10 +2080,11 @@ fn current_token_or_none(scanner: &mut Scanner) -> Option<Rc<Scan>> {
     scanner.current()
 }
 
-fn add_symbol(scope: &Scope, ident: &str, size: u32, context: ScanContext) -> Result<Rc<Symbol>, ParseError> {
-    match scope.lookup(ident) {
+fn add_symbol(scope: &Scope, ident: &str, var_type: VarType, context: ScanContext) -> Result<Rc<Symbol>, ParseError> {
+    // Symbols of enclosing scopes can be shadowed
+    match scope.lookup_local(ident) {
         None => {
-            scope.add_with_size(ident, size as usize);
+            scope.add_variable(ident, var_type);
             lookup(scope, ident)
         }
         Some(_symbol) => Err(ParseError::SymbolAlreadyDeclared(String::from(ident), context)),
@@ -651,5 +2092,72 @@ fn add_symbol(scope: &Scope, ident: &str, size: u32, context: ScanContext) -> Re
 }
 
 fn lookup(scope: &Scope, ident: &str) -> Result<Rc<Symbol>, ParseError> {
-    scope.lookup(ident).ok_or_else(|| ParseError::UndefinedSymbol(String::from(ident)))
+    let symbol = scope.lookup(ident).ok_or_else(|| ParseError::UndefinedSymbol(String::from(ident)))?;
+
+    // NOTE(pht) there is no static link in procedure frames, so only locals and
+    // module variables can be addressed.
+    match symbol.kind {
+        SymbolKind::Variable | SymbolKind::Parameter { .. } if symbol.level != 0 && symbol.level != scope.level() => Err(ParseError::InaccessibleSymbol(String::from(ident))),
+        _ => Ok(symbol),
+    }
+}
+
+// NOTE(pht) a qualified identifier is resolved in the exports of its module,
+// leaving the scanner on its last identifier, as for an unqualified one.
+fn lookup_qualified(scanner: &mut Scanner, scope: &Scope, ident: &str) -> Result<Rc<Symbol>, ParseError> {
+    let symbol = lookup(scope, ident)?;
+    let SymbolKind::Module(exports) = &symbol.kind else {
+        return Ok(symbol);
+    };
+
+    scan_next(scanner)?;
+    let current = current_token(scanner)?;
+    if current.token != Token::Period {
+        return Err(unexpected(current, &[Token::Period]));
+    }
+    scan_next(scanner)?;
+    let current = current_token(scanner)?;
+    let Scan { token: Token::Ident(export), .. } = current.as_ref() else {
+        return Err(unexpected(current, &[IDENT]));
+    };
+    exports
+        .iter()
+        .find(|e| e.name == *export)
+        .cloned()
+        .ok_or_else(|| ParseError::UndefinedSymbol(format!("{}.{}", ident, export)))
+}
+
+fn expect_writable(symbol: &Symbol) -> Result<(), ParseError> {
+    if symbol.module.is_some() && symbol.export == Some(Export::ReadOnly) {
+        return Err(ParseError::ReadOnlyVariable(symbol.name.clone()));
+    }
+    Ok(())
+}
+
+fn constant_leaf(value: i32, var_type: &Option<VarType>, span: Span) -> Ast {
+    match var_type {
+        Some(VarType::Boolean) => ast::leaf_at(NodeInfo::Boolean(value != 0), span),
+        Some(VarType::Char) => ast::leaf_at(NodeInfo::Char(value as u8), span),
+        Some(VarType::Set) => ast::leaf_at(NodeInfo::Set(value as u32), span),
+        _ => ast::leaf_at(NodeInfo::Constant(value), span),
+    }
+}
+
+// Unknown types are reported, and replaced by INTEGER for the parser to go on
+fn lookup_type_or_report(scanner: &mut Scanner, scope: &Scope, ident: &str) -> Result<VarType, ParseError> {
+    match lookup_type(scanner, scope, ident) {
+        Ok(var_type) => Ok(var_type),
+        Err(error) => {
+            report_error(scanner, scope, error)?;
+            Ok(VarType::Integer)
+        }
+    }
+}
+
+fn lookup_type(scanner: &mut Scanner, scope: &Scope, ident: &str) -> Result<VarType, ParseError> {
+    let symbol = lookup_qualified(scanner, scope, ident)?;
+    match (&symbol.kind, &symbol.declared_type) {
+        (SymbolKind::Type, Some(var_type)) => Ok(var_type.clone()),
+        _ => Err(ParseError::NotAType(String::from(ident))),
+    }
 }
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::parser::*;
+    use crate::scanner::*;
+    use crate::scope::*;
+    use crate::token::*;
+    use crate::tree::*;
+    use std::assert_matches::assert_matches;
+
+    fn parse_module(content: &str) -> ParseResult {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module(&mut scanner, &scope)
+    }
+
+    #[test]
+    fn can_check_well_typed_module() {
+        let root_tree = parse_module(
+            "MODULE M;
+              TYPE Point = RECORD x, y: INTEGER END;
+              VAR i: INTEGER; b: BOOLEAN; c: CHAR; s: SET; a: ARRAY 3 OF Point; name: ARRAY 4 OF CHAR;
+              PROCEDURE P(VAR p: Point; n: INTEGER); BEGIN p.x := n END P;
+            BEGIN
+              i := a[i MOD 3].y * 2 - ABS(i); b := (i < 3) OR ~(c = \"x\") & ODD(i);
+              s := s + {i, 1..3} * {2}; b := i IN s;
+              name := \"abc\"; c := name[0];
+              IF b THEN P(a[0], i) ELSIF c >= 41X THEN INC(i) END;
+              WHILE i > 0 DO DEC(i, 2) END;
+              REPEAT i := i + 1 UNTIL i = 10;
+              FOR i := 0 TO a[1].x BY 2 DO Out.Int(i, 3) END;
+              CASE c OF 0X, \"a\"..\"z\": Out.Char(c) END;
+              ASSERT(b # FALSE)
+            END M.",
+        );
+        assert!(root_tree.is_ok(), "{:?}", root_tree);
+    }
+
+    #[test]
+    fn fails_on_mismatched_operands() {
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; b: BOOLEAN; BEGIN i := 1;\n  i := i + b END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Boolean,
+                context: ScanContext { line: 1, column: 11 }
+            })
+        );
+
+        // The operand of the wrong type is located, not the statement it is found in
+        let root_tree = parse_module("MODULE M; VAR x: INTEGER; b: BOOLEAN; BEGIN\n  x := x + (b OR TRUE) END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Boolean,
+                context: ScanContext { line: 1, column: 12 }
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; s: SET; BEGIN s := s + i END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Set,
+                found: VarType::Integer,
+                ..
+            })
+        );
+
+        // The set operator is chosen from the right operand, so the left one is the mismatch
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; s: SET; BEGIN i := i + s END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Set,
+                found: VarType::Integer,
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; c: CHAR; BEGIN IF i < c THEN i := 0 END END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Char,
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR b: BOOLEAN; BEGIN b := ~3 END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Boolean,
+                found: VarType::Integer,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn fails_on_mismatched_statements() {
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := TRUE END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Boolean,
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; BEGIN WHILE i DO i := 0 END END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Boolean,
+                found: VarType::Integer,
+                ..
+            })
+        );
+
+        // Conditions are located at their expression, not at the last statement before them
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; BEGIN REPEAT\n  i := 0 UNTIL i END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                context: ScanContext { line: 1, column: 15 },
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; b: BOOLEAN; BEGIN FOR i := 0 TO b DO b := TRUE END END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Boolean,
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR s: SET; BEGIN CASE s OF 1: s := {} END END M.");
+        assert_matches!(root_tree, Err(ParseError::TypeMismatch { found: VarType::Set, .. }));
+
+        // Labels are located at the start of their branch
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; c: CHAR; BEGIN CASE c OF \"a\": i := 1 | 2: i := 2 END END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Char,
+                found: VarType::Integer,
+                context: ScanContext { line: 0, column: 65 }
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; VAR i: INTEGER; BEGIN Out.Char(i) END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Char,
+                found: VarType::Integer,
+                ..
+            })
+        );
+
+        let root_tree = parse_module("MODULE M; PROCEDURE P(b: BOOLEAN); END P; BEGIN P(1) END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Boolean,
+                found: VarType::Integer,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn fails_on_indexing_non_array() {
+        let root_tree = parse_module("MODULE M; VAR i, j: INTEGER; BEGIN j := i[0] END M.");
+        assert_matches!(root_tree, Err(ParseError::NotAnArray(name, ScanContext { line: 0, column: 40 })) if name == "i");
+
+        let root_tree = parse_module("MODULE M; VAR a: ARRAY 3 OF INTEGER; BEGIN a[TRUE] := 0 END M.");
+        assert_matches!(
+            root_tree,
+            Err(ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Boolean,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn fails_on_assignment_to_structured_variable() {
+        let root_tree = parse_module("MODULE M; VAR a: ARRAY 3 OF INTEGER; BEGIN a := 1 END M.");
+        assert_matches!(root_tree, Err(ParseError::NotAssignable(name, _)) if name == "a");
+
+        let root_tree = parse_module("MODULE M; TYPE Point = RECORD x, y: INTEGER END; VAR p, q: Point; BEGIN p := q END M.");
+        assert_matches!(root_tree, Err(ParseError::NotAssignable(name, _)) if name == "p");
+    }
+}
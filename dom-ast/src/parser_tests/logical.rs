@@ -27,43 +27,43 @@ mod tests {
 
     #[test]
     fn fails_on_if_expression_without_condition() {
-        let mut scope = Scope::new();
-        let error = parse_statement(&mut scope, "IF").unwrap_err();
+        let scope = Scope::new();
+        let error = parse_statement(&scope, "IF").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn fails_on_invalid_if_expression() {
-        let mut scope = Scope::new();
-        let error = parse_statement(&mut scope, "IF 0 =").unwrap_err();
+        let scope = Scope::new();
+        let error = parse_statement(&scope, "IF 0 =").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn fails_on_if_expression_without_then() {
-        let mut scope = Scope::new();
-        let error = parse_statement(&mut scope, "IF 0 = 1").unwrap_err();
+        let scope = Scope::new();
+        let error = parse_statement(&scope, "IF 0 = 1").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn fails_on_if_expression_without_then_statements() {
-        let mut scope = Scope::new();
-        let error = parse_statement(&mut scope, "IF 0 = 1 THEN").unwrap_err();
+        let scope = Scope::new();
+        let error = parse_statement(&scope, "IF 0 = 1 THEN").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn fails_on_if_expression_without_then_statements_ending() {
-        let mut scope = scope(vec!["x"]);
-        let error = parse_statement(&mut scope, "IF 0 = 1 THEN x:= 1; x:= 2").unwrap_err();
+        let scope = scope(vec!["x"]);
+        let error = parse_statement(&scope, "IF 0 = 1 THEN x:= 1; x:= 2").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn can_parse_complete_if_expression() {
-        let mut scope = scope(vec!["x"]);
-        let root_tree = parse_statement(&mut scope, "IF 0 = 1 THEN x:= 1; x:= 2 END").unwrap();
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "IF 0 = 1 THEN x:= 1; x:= 2 END").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::IfStatement);
@@ -81,30 +81,30 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Then);
 
         let path = root.sibling().child();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().child().sibling();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
     }
 
     #[test]
     fn fails_on_if_expression_with_incomplete_else() {
-        let mut scope = scope(vec!["x"]);
-        let error = parse_statement(&mut scope, "IF 0 = 1 THEN x:= 1; x:= 2 ELSE").unwrap_err();
+        let scope = scope(vec!["x"]);
+        let error = parse_statement(&scope, "IF 0 = 1 THEN x:= 1; x:= 2 ELSE").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn fails_on_if_expression_with_incomplete_else_statements() {
-        let mut scope = scope(vec!["x"]);
-        let error = parse_statement(&mut scope, "IF 0 = 1 THEN x:= 1; x:= 2 ELSE x:=3").unwrap_err();
+        let scope = scope(vec!["x"]);
+        let error = parse_statement(&scope, "IF 0 = 1 THEN x:= 1; x:= 2 ELSE x:=3").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn can_parse_complete_if_then_else_expression() {
-        let mut scope = scope(vec!["x"]);
-        let root_tree = parse_statement(&mut scope, "IF 0 = 1 THEN x:= 1 ELSE x:=2 END").unwrap();
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "IF 0 = 1 THEN x:= 1 ELSE x:=2 END").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::IfStatement);
@@ -122,15 +122,112 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Then);
 
         let path = root.sibling().child();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().sibling();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Else);
 
         let path = root.sibling().sibling().child();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().sibling().child().child();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
     }
+
+    #[test]
+    fn can_parse_boolean_operators_with_precedence() {
+        let scope = scope(vec!["a", "b", "c"]);
+        let root_tree = parse_statement(&scope, "IF (a < b) & ~c OR TRUE THEN a := 1 END").unwrap();
+
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Or));
+        assert_matches!(ast::Path::root().child().child().follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::And));
+        assert_matches!(
+            ast::Path::root().child().child().child().follow(&root_tree).unwrap(),
+            NodeInfo::Expression(ExpressionOp::Lss)
+        );
+        assert_matches!(ast::Path::root().child().child().sibling().follow(&root_tree).unwrap(), NodeInfo::Not);
+        assert_matches!(ast::Path::root().child().child().sibling().child().follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "c");
+        assert_matches!(ast::Path::root().child().sibling().follow(&root_tree).unwrap(), NodeInfo::Boolean(true));
+    }
+
+    #[test]
+    fn can_parse_boolean_assignment() {
+        let scope = Scope::new();
+        scope.add_variable("b", VarType::Boolean);
+        let root_tree = parse_statement(&scope, "b := FALSE").unwrap();
+
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::Assignement);
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::Boolean(false));
+    }
+
+    #[test]
+    fn can_parse_elsif_chains() {
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "IF x = 1 THEN x := 2 ELSIF x = 2 THEN x := 3 ELSIF x = 3 THEN x := 4 ELSE x := 0 END").unwrap();
+
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::IfStatement);
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::Then);
+        assert_matches!(ast::Path::root().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::Elsif);
+        assert_matches!(
+            ast::Path::root().sibling().sibling().child().follow(&root_tree).unwrap(),
+            NodeInfo::Expression(ExpressionOp::Eql)
+        );
+        assert_matches!(ast::Path::root().sibling().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::Then);
+        assert_matches!(ast::Path::root().sibling().sibling().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::Elsif);
+        assert_matches!(
+            ast::Path::root().sibling().sibling().sibling().sibling().sibling().sibling().follow(&root_tree).unwrap(),
+            NodeInfo::Else
+        );
+    }
+
+    #[test]
+    fn fails_on_elsif_without_then() {
+        let scope = scope(vec!["x"]);
+        let error = parse_statement(&scope, "IF x = 1 THEN x := 2 ELSIF x = 2 x := 3 END").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+    }
+
+    #[test]
+    fn can_parse_case_statement() {
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_statement(&scope, "CASE x OF 1, 3..5: y := 1 | | -2: y := 2 ELSE y := 3 END").unwrap();
+
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::CaseStatement);
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::CaseLabels(labels, VarType::Integer) if *labels == vec![(1, 1), (3, 5)]);
+        assert_matches!(ast::Path::root().sibling().child().follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
+        assert_matches!(ast::Path::root().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::CaseLabels(labels, VarType::Integer) if *labels == vec![(-2, -2)]);
+        assert_matches!(ast::Path::root().sibling().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::Else);
+    }
+
+    #[test]
+    fn can_parse_case_statement_with_constant_and_character_labels() {
+        let scope = scope(vec!["x", "y"]);
+        scope.add_constant("Add", VarType::Integer, 1);
+        let root_tree = parse_statement(&scope, "CASE x OF Add, Add + 2..Add * 5: y := 1 END").unwrap();
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::CaseLabels(labels, VarType::Integer) if *labels == vec![(1, 1), (3, 5)]);
+
+        let root_tree = parse_statement(&scope, "CASE x OF \"a\"..\"c\", 7AX: y := 1 END").unwrap();
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::CaseLabels(labels, VarType::Char) if *labels == vec![(97, 99), (122, 122)]);
+
+        let error = parse_statement(&scope, "CASE x OF 1, \"a\": y := 1 END").unwrap_err();
+        assert_matches!(
+            error,
+            ParseError::TypeMismatch {
+                expected: VarType::Integer,
+                found: VarType::Char,
+                ..
+            }
+        );
+
+        let error = parse_statement(&scope, "CASE x OF y: y := 1 END").unwrap_err();
+        assert_matches!(error, ParseError::NotAConstant(ident) if ident == "y");
+    }
+
+    #[test]
+    fn fails_on_duplicate_case_labels() {
+        let scope = scope(vec!["x", "y"]);
+        let error = parse_statement(&scope, "CASE x OF 1..5: y := 1 | 7, 4: y := 2 END").unwrap_err();
+        assert_matches!(error, ParseError::DuplicateCaseLabel(4));
+    }
 }
@@ -27,24 +27,24 @@ mod tests {
 
     #[test]
     fn fails_on_invalid_while_expressions() {
-        let mut scope = scope(vec!["x"]);
-        let mut error = parse_statement(&mut scope, "WHILE").unwrap_err();
+        let scope = scope(vec!["x"]);
+        let mut error = parse_statement(&scope, "WHILE").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
 
-        error = parse_statement(&mut scope, "WHILE 0").unwrap_err();
+        error = parse_statement(&scope, "WHILE 0").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
 
-        error = parse_statement(&mut scope, "WHILE 0 = 1").unwrap_err();
+        error = parse_statement(&scope, "WHILE 0 = 1").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
 
-        error = parse_statement(&mut scope, "WHILE 0 = 1 DO x:= 1; x:= 2").unwrap_err();
+        error = parse_statement(&scope, "WHILE 0 = 1 DO x:= 1; x:= 2").unwrap_err();
         assert_matches!(error, ParseError::PrematureEof);
     }
 
     #[test]
     fn can_parse_complete_while_expression() {
-        let mut scope = scope(vec!["x"]);
-        let root_tree = parse_statement(&mut scope, "WHILE 0 = 1 DO x:= 1; x:= 2 END").unwrap();
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "WHILE 0 = 1 DO x:= 1; x:= 2 END").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::WhileStatement);
@@ -56,6 +56,85 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Do);
 
         let path = root.sibling().child();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
+    }
+
+    #[test]
+    fn can_parse_repeat_statement() {
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "REPEAT x := x + 1 UNTIL x > 10").unwrap();
+
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::RepeatStatement);
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
+        assert_matches!(ast::Path::root().child().child().follow(&root_tree).unwrap(), NodeInfo::Assignement);
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::Expression(ExpressionOp::Gtr));
+    }
+
+    #[test]
+    fn fails_on_repeat_without_until() {
+        let scope = scope(vec!["x"]);
+        let error = parse_statement(&scope, "REPEAT x := 1 END").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+    }
+
+    #[test]
+    fn can_parse_for_statement() {
+        let scope = scope(vec!["i", "x"]);
+        let root_tree = parse_statement(&scope, "FOR i := 10 TO x BY -2 DO x := x + i END").unwrap();
+
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::ForStatement(-2));
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::Assignement);
+        assert_matches!(ast::Path::root().child().child().follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "i");
+        assert_matches!(ast::Path::root().child().sibling().follow(&root_tree).unwrap(), NodeInfo::Constant(10));
+        assert_matches!(ast::Path::root().sibling().follow(&root_tree).unwrap(), NodeInfo::To);
+        assert_matches!(ast::Path::root().sibling().child().follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
+        assert_matches!(ast::Path::root().sibling().sibling().follow(&root_tree).unwrap(), NodeInfo::Do);
+
+        // The increment is added after the body of the loop
+        assert_matches!(
+            ast::Path::root().sibling().sibling().child().sibling().child().follow(&root_tree).unwrap(),
+            NodeInfo::Assignement
+        );
+        assert_matches!(
+            ast::Path::root().sibling().sibling().child().sibling().child().sibling().follow(&root_tree).unwrap(),
+            NodeInfo::SimpleExpression(SimpleExpressionOp::Minus)
+        );
+        assert_matches!(
+            ast::Path::root()
+                .sibling()
+                .sibling()
+                .child()
+                .sibling()
+                .child()
+                .sibling()
+                .sibling()
+                .follow(&root_tree)
+                .unwrap(),
+            NodeInfo::Constant(2)
+        );
+    }
+
+    #[test]
+    fn fails_on_for_statement_with_invalid_step() {
+        let scope = scope(vec!["i", "x"]);
+        let mut error = parse_statement(&scope, "FOR i := 0 TO 10 BY 0 DO x := i END").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+
+        error = parse_statement(&scope, "FOR i := 0 TO 10 BY x DO x := i END").unwrap_err();
+        assert_matches!(error, ParseError::NotAConstant(ident) if ident == "x");
+
+        error = parse_statement(&scope, "FOR i := 0 TO 10 BY TRUE DO x := i END").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+    }
+
+    #[test]
+    fn can_parse_for_statement_with_constant_step() {
+        let scope = scope(vec!["i", "x"]);
+        scope.add_constant("Step", VarType::Integer, -2);
+        let root_tree = parse_statement(&scope, "FOR i := 10 TO 0 BY Step DO x := i END").unwrap();
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::ForStatement(-2));
+
+        let root_tree = parse_statement(&scope, "FOR i := 0 TO 10 BY -Step * 3 DO x := i END").unwrap();
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::ForStatement(6));
     }
 }
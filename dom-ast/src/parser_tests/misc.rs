@@ -24,32 +24,32 @@ mod tests {
 
     #[test]
     fn fails_on_premature_eof() {
-        let mut scope = scope(vec!["x"]);
+        let scope = scope(vec!["x"]);
         for content in &["", "x", "x:="] {
-            let tree = parse_statement(&mut scope, content);
+            let tree = parse_statement(&scope, content);
             assert_matches!(tree.unwrap_err(), ParseError::PrematureEof, "Expected PrematureEof while parsing {}", content);
         }
     }
 
     #[test]
     fn fails_on_scan_eof() {
-        let mut scope = Scope::new();
-        let tree = parse_statement(&mut scope, " ❤");
+        let scope = Scope::new();
+        let tree = parse_statement(&scope, " ❤");
         assert_matches!(tree.unwrap_err(), ParseError::ScanError(_));
     }
 
     #[test]
     fn fails_parsing_statement_for_unknown_identifier() {
-        let mut scope = Scope::new();
+        let scope = Scope::new();
 
-        let tree = parse_statement(&mut scope, "y:=42");
+        let tree = parse_statement(&scope, "y:=42");
         assert_matches!(tree.unwrap_err(), ParseError::UndefinedSymbol(s) if s == "y");
     }
 
     #[test]
     fn can_parse_statement() {
-        let mut scope = scope(vec!["x"]);
-        let root_tree = parse_statement(&mut scope, "x:=42").unwrap();
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_statement(&scope, "x:=42").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Assignement);
@@ -69,17 +69,17 @@ mod tests {
 
     #[test]
     fn can_parse_statement_sequence() {
-        let mut scope = scope(vec!["x", "y"]);
-        let root_tree = parse_statement_sequence(&mut scope, "x:=42;\ny:=x").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_statement_sequence(&scope, "x:=42;\ny:=x").unwrap();
 
         let mut root = ast::Path::root();
-        assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.child();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
 
         let path = root.sibling();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().child();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
@@ -93,40 +93,86 @@ mod tests {
 
     #[test]
     fn can_parse_factor() {
-        let mut scope = scope(vec!["x", "y"]);
-        let tree = parse_factor(&mut scope, "42").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let tree = parse_factor(&scope, "42").unwrap();
         assert_matches!(ast::info(&tree).unwrap(), NodeInfo::Constant(42));
 
-        let tree = parse_factor(&mut scope, "x").unwrap();
+        let tree = parse_factor(&scope, "x").unwrap();
         assert_matches!(ast::info(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
     }
 
     #[test]
     fn can_parse_factor_with_constant_selector() {
-        let mut scope = scope(vec!["x"]);
+        let scope = scope(vec!["x"]);
 
-        let tree = parse_factor(&mut scope, "x[0]").unwrap();
+        let tree = parse_factor(&scope, "x[0]").unwrap();
         assert_matches!(ast::info(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
 
         let mut root = ast::Path::root();
 
-        let path = root.child();
+        let mut path = root.child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Index(1, None, 0));
+
+        path = root.child().child();
         assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Constant(index) if *index == 0);
     }
 
     #[test]
     fn can_parse_factor_with_variable_selector() {
-        let mut scope = scope(vec!["x", "i"]);
+        let scope = scope(vec!["x", "i"]);
 
-        let tree = parse_factor(&mut scope, "x[i]").unwrap();
+        let tree = parse_factor(&scope, "x[i]").unwrap();
         assert_matches!(ast::info(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
 
         let mut root = ast::Path::root();
 
-        let path = root.child();
+        let mut path = root.child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Index(1, None, 0));
+
+        path = root.child().child();
         assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "i");
     }
 
+    #[test]
+    fn can_parse_factor_with_chained_selectors() {
+        let scope = scope(vec!["i"]);
+        let mut scanner = Scanner::new("TYPE Point = RECORD x, y: INTEGER END; VAR p: ARRAY 3 OF Point;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(ast::empty())).unwrap();
+
+        let tree = parse_factor(&scope, "p[i].y").unwrap();
+        assert_matches!(ast::info(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "p");
+
+        assert_matches!(ast::Path::root().child().follow(&tree).unwrap(), NodeInfo::Index(2, Some(3), 0));
+        assert_matches!(ast::Path::root().child().child().follow(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "i");
+        assert_matches!(ast::Path::root().child().sibling().follow(&tree).unwrap(), NodeInfo::Field(name, 1) if name == "y");
+
+        assert_matches!(parse_factor(&scope, "p[i].z").unwrap_err(), ParseError::UndefinedField(name) if name == "z");
+        assert_matches!(parse_factor(&scope, "i.x").unwrap_err(), ParseError::NotARecord(name) if name == "x");
+    }
+
+    #[test]
+    fn can_parse_factor_with_expression_selectors() {
+        let scope = scope(vec!["i"]);
+        let mut scanner = Scanner::new("CONST N = 3; VAR m: ARRAY N, 4 OF INTEGER;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(ast::empty())).unwrap();
+
+        // Constant indices are computed at compile time
+        let tree = parse_factor(&scope, "m[N - 1][i + 1]").unwrap();
+        assert_matches!(ast::Path::root().child().follow(&tree).unwrap(), NodeInfo::Index(4, Some(3), 0));
+        assert_matches!(ast::Path::root().child().child().follow(&tree).unwrap(), NodeInfo::Constant(2));
+        assert_matches!(ast::Path::root().child().sibling().follow(&tree).unwrap(), NodeInfo::Index(1, Some(4), 0));
+        assert_matches!(ast::Path::root().child().sibling().child().follow(&tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Plus));
+
+        // Indices separated by commas select in nested arrays
+        let tree = parse_factor(&scope, "m[m[0, 0], i]").unwrap();
+        assert_matches!(ast::Path::root().child().child().follow(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "m");
+        assert_matches!(ast::Path::root().child().child().child().sibling().follow(&tree).unwrap(), NodeInfo::Index(1, Some(4), 0));
+        assert_matches!(ast::Path::root().child().sibling().child().follow(&tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "i");
+        assert!(ast::is_empty(ast::sibling(ast::child(&tree).unwrap()).and_then(ast::sibling).unwrap()));
+    }
+
     fn parse_term(scope: &Scope, content: &str) -> ParseResult {
         let mut scanner = Scanner::new(content);
         parser::scan_next(&mut scanner)?;
@@ -135,8 +181,8 @@ mod tests {
 
     #[test]
     fn can_parse_term_with_one_level() {
-        let mut scope = scope(vec!["x", "y"]);
-        let root_tree = parse_term(&mut scope, "x*42").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_term(&scope, "x*42").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Times));
@@ -150,10 +196,10 @@ mod tests {
 
     #[test]
     fn can_parse_term_with_multiple_operators() {
-        let mut scope = scope(vec!["x", "y"]);
+        let scope = scope(vec!["x", "y"]);
 
         // NOTE: the tree here is a bit ambiguous, so the user will have to use parentheses.
-        let root_tree = parse_term(&mut scope, "x/42*y").unwrap();
+        let root_tree = parse_term(&scope, "x/42*y").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Times));
@@ -171,6 +217,19 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "y");
     }
 
+    #[test]
+    fn can_parse_div_and_mod_terms() {
+        let scope = scope(vec!["x", "y"]);
+
+        let root_tree = parse_term(&scope, "x DIV 2 MOD y").unwrap();
+
+        let mut root = ast::Path::root();
+        assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Mod));
+
+        let path = root.child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Div));
+    }
+
     // NOTE(pht) maybe those functions can be automagically created with macros ?
     fn parse_simple_expression(scope: &Scope, content: &str) -> ParseResult {
         let mut scanner = Scanner::new(content);
@@ -180,8 +239,8 @@ mod tests {
 
     #[test]
     fn can_parse_simple_expression_with_one_level() {
-        let mut scope = scope(vec!["x", "y"]);
-        let root_tree = parse_simple_expression(&mut scope, "x*y+42").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_simple_expression(&scope, "x*y+42").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Plus));
@@ -195,8 +254,8 @@ mod tests {
 
     #[test]
     fn can_parse_simple_expression_with_multiple_level() {
-        let mut scope = scope(vec!["x", "y"]);
-        let root_tree = parse_simple_expression(&mut scope, "x*y+42*13-12").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_simple_expression(&scope, "x*y+42*13-12").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Minus));
@@ -205,10 +264,30 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Plus));
     }
 
+    #[test]
+    fn can_parse_simple_expression_with_sign() {
+        let scope = scope(vec!["x", "y"]);
+
+        let root_tree = parse_simple_expression(&scope, "-5 + x").unwrap();
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::Constant(-5));
+
+        // The sign applies to the first term
+        let root_tree = parse_simple_expression(&scope, "-x*y - 2").unwrap();
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Minus));
+        assert_matches!(ast::Path::root().child().follow(&root_tree).unwrap(), NodeInfo::Negate);
+        assert_matches!(ast::Path::root().child().child().follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Times));
+
+        let root_tree = parse_simple_expression(&scope, "+x").unwrap();
+        assert_matches!(ast::Path::root().follow(&root_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "x");
+
+        let root_tree = parse_simple_expression(&scope, "x * (-2147483647 - 1)").unwrap();
+        assert_matches!(ast::Path::root().sibling().child().follow(&root_tree).unwrap(), NodeInfo::Constant(-2147483647));
+    }
+
     #[test]
     fn can_parse_term_with_parens() {
-        let mut scope = scope(vec!["x", "y"]);
-        let root_tree = parse_term(&mut scope, "(x*42)").unwrap();
+        let scope = scope(vec!["x", "y"]);
+        let root_tree = parse_term(&scope, "(x*42)").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Term(TermOp::Times));
@@ -1,5 +1,9 @@
+mod checker;
 mod logical;
 mod looping;
 mod misc;
 mod module;
+mod procedures;
+mod recovery;
+mod spans;
 mod types;
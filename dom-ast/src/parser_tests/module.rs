@@ -24,50 +24,50 @@ mod tests {
 
     #[test]
     fn can_not_parse_invalid_module() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "x");
-        assert_matches!(root_tree, Err(ParseError::UnexpectedToken(_)));
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "x");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
     }
 
     #[test]
     fn can_not_redefine_module_name() {
-        let mut scope = scope(vec!["x"]);
-        let root_tree = parse_module(&mut scope, "MODULE x; END x.");
+        let scope = scope(vec!["x"]);
+        let root_tree = parse_module(&scope, "MODULE x; END x.");
         assert_matches!(root_tree, Err(ParseError::SymbolAlreadyDeclared(s, _)) if s == "x");
     }
 
     #[test]
     fn can_not_parse_module_without_ending() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE x;");
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE x;");
         assert_matches!(root_tree, Err(ParseError::PrematureEof));
     }
 
     #[test]
     fn can_not_parse_module_with_invalid_ending_name() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; END OtherModuleName");
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; END OtherModuleName");
         assert_matches!(root_tree, Err(ParseError::UnexpectedBlockEnding{ expected, found}) if expected == "ModuleName" && found == "OtherModuleName");
     }
 
     #[test]
     fn can_not_parse_module_without_ending_period() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; END ModuleName");
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; END ModuleName");
         assert_matches!(root_tree, Err(ParseError::PrematureEof));
     }
 
     #[test]
     fn can_not_parse_module_with_anything_after_the_period() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; END ModuleName. 42");
-        assert_matches!(root_tree, Err(ParseError::UnexpectedToken(_)));
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; END ModuleName. 42");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
     }
 
     #[test]
     fn can_parse_module_without_declarations_or_body() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; END ModuleName.").unwrap();
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; END ModuleName.").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Module);
@@ -83,8 +83,8 @@ mod tests {
 
     #[test]
     fn can_parse_module_without_body() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; VAR x: INTEGER; y: INTEGER; END ModuleName.").unwrap();
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; VAR x: INTEGER; y: INTEGER; END ModuleName.").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Module);
@@ -103,8 +103,8 @@ mod tests {
 
     #[test]
     fn can_parse_module_wit_declarations_and_body() {
-        let mut scope = Scope::new();
-        let root_tree = parse_module(&mut scope, "MODULE ModuleName; VAR x,y: INTEGER; z: INTEGER; BEGIN x:= 1; y:= 2 END ModuleName.").unwrap();
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE ModuleName; VAR x,y: INTEGER; z: INTEGER; BEGIN x:= 1; y:= 2 END ModuleName.").unwrap();
 
         let mut root = ast::Path::root();
         assert_matches!(root.follow(&root_tree).unwrap(), NodeInfo::Module);
@@ -122,15 +122,96 @@ mod tests {
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Declaration);
 
         let path = root.sibling().sibling();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().sibling().child();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
 
         let path = root.sibling().sibling().sibling();
-        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence);
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StatementSequence(_));
 
         let path = root.sibling().sibling().sibling().child();
         assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
     }
+
+    #[test]
+    fn can_use_lowercase_keywords_as_identifiers() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR in, to, for, repeat, case: INTEGER; BEGIN for := 1; case := for DIV to; in := case END M.");
+        assert!(root_tree.is_ok(), "{:?}", root_tree);
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "module M; end M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+    }
+
+    fn parse_module_importing(scope: &Scope, content: &str, symbol_file: &str) -> ParseResult {
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module_with_imports(&mut scanner, scope, &mut |module| match crate::symbol_file::read(symbol_file)? {
+            (name, symbols) if name == module => Ok(symbols),
+            _ => Err(ParseError::UndefinedModule(String::from(module))),
+        })
+    }
+
+    #[test]
+    fn can_parse_export_marks() {
+        let scope = Scope::new();
+        parse_module(&scope, "MODULE M; CONST N* = 1; TYPE T* = INTEGER; VAR x-, y: INTEGER; PROCEDURE P*; END P; END M.").unwrap();
+
+        assert_eq!(scope.lookup("N").unwrap().export, Some(Export::ReadWrite));
+        assert_eq!(scope.lookup("T").unwrap().export, Some(Export::ReadWrite));
+        assert_eq!(scope.lookup("x").unwrap().export, Some(Export::ReadOnly));
+        assert_eq!(scope.lookup("y").unwrap().export, None);
+        assert_eq!(scope.lookup("P").unwrap().export, Some(Export::ReadWrite));
+    }
+
+    #[test]
+    fn can_not_export_local_or_read_only_constants() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P; VAR x*: INTEGER; END P; END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; CONST N- = 1; END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+    }
+
+    #[test]
+    fn can_use_imported_symbols() {
+        let scope = Scope::new();
+        let root_tree = parse_module_importing(
+            &scope,
+            "MODULE M; IMPORT S := Shapes; VAR p: S.Point; BEGIN p.x := S.Max; S.Move(p, S.origin.y) END M.",
+            "MODULE Shapes\nCONST Max * 10 INTEGER\nTYPE Point * RECORD 2 x INTEGER y INTEGER\nVAR origin - 2 RECORD 2 x INTEGER y INTEGER\nPROCEDURE Move * VAR p RECORD 2 x INTEGER y INTEGER VALUE dx INTEGER\n",
+        )
+        .unwrap();
+
+        assert_matches!(scope.lookup("S").unwrap().kind, SymbolKind::Module(_));
+        assert_matches!(scope.lookup("p").unwrap().declared_type, Some(VarType::Record(_)));
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().sibling().child().sibling();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Constant(10));
+
+        let path = root.sibling().sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::ProcedureCall(procedure) if procedure.name == "Move" && procedure.module.as_deref() == Some("Shapes"));
+    }
+
+    #[test]
+    fn fails_on_invalid_imports() {
+        let symbol_file = "MODULE Shapes\nVAR origin - 2 INTEGER\n";
+
+        let scope = Scope::new();
+        let root_tree = parse_module_importing(&scope, "MODULE M; IMPORT Lines; END M.", symbol_file);
+        assert_matches!(root_tree, Err(ParseError::UndefinedModule(module)) if module == "Lines");
+
+        let scope = Scope::new();
+        let root_tree = parse_module_importing(&scope, "MODULE M; IMPORT Shapes; BEGIN Shapes.origin := 1 END M.", symbol_file);
+        assert_matches!(root_tree, Err(ParseError::ReadOnlyVariable(name)) if name == "origin");
+
+        let scope = Scope::new();
+        let root_tree = parse_module_importing(&scope, "MODULE M; IMPORT Shapes; VAR x: INTEGER; BEGIN x := Shapes.hidden END M.", symbol_file);
+        assert_matches!(root_tree, Err(ParseError::UndefinedSymbol(name)) if name == "Shapes.hidden");
+    }
 }
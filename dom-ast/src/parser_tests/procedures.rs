@@ -0,0 +1,212 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast;
+    use crate::parser;
+    use crate::parser::*;
+    use crate::scanner::*;
+    use crate::scope::*;
+    use crate::tree::*;
+    use std::assert_matches::assert_matches;
+
+    fn parse_module(scope: &Scope, content: &str) -> ParseResult {
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module(&mut scanner, scope)
+    }
+
+    #[test]
+    fn can_parse_procedure_declaration() {
+        let scope = Scope::new();
+        let root_tree = parse_module(
+            &scope,
+            "MODULE M; VAR x: INTEGER; PROCEDURE P(a, b: INTEGER; VAR c: INTEGER); VAR y: INTEGER; BEGIN c := a + b END P; END M.",
+        )
+        .unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Declaration);
+
+        // Return address, 3 parameters and a local
+        let path = root.sibling().child().sibling();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::ProcedureDeclaration(symbol, 5) if symbol.name == "P");
+
+        let path = root.sibling().child().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Declarations);
+
+        let path = root.sibling().child().sibling().child().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Assignement);
+
+        let procedure = scope.lookup("P").unwrap();
+        assert_eq!(procedure.level, 0);
+        match &procedure.kind {
+            SymbolKind::Procedure(parameters) => {
+                assert_eq!(parameters.len(), 3);
+                assert_matches!(parameters[0].as_ref(), Symbol{ name, adr: 1, level: 1, kind: SymbolKind::Parameter { by_reference: false }, .. } if name == "a");
+                assert_matches!(parameters[2].as_ref(), Symbol{ name, adr: 3, level: 1, kind: SymbolKind::Parameter { by_reference: true }, .. } if name == "c");
+            }
+            _ => panic!("P should be a procedure"),
+        }
+
+        assert_matches!(scope.lookup("y"), None);
+    }
+
+    #[test]
+    fn can_parse_procedure_call() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; PROCEDURE P(a: INTEGER; VAR b: INTEGER); END P; BEGIN P(x + 1, x) END M.").unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::ProcedureCall(symbol) if symbol.name == "P");
+
+        let path = root.sibling().sibling().child().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::ActualParameter);
+
+        let path = root.sibling().sibling().child().child().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Plus));
+
+        let path = root.sibling().sibling().child().child().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Ident(symbol) if symbol.name == "x");
+    }
+
+    #[test]
+    fn can_parse_recursive_procedure() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P(n: INTEGER); BEGIN IF n > 0 THEN P(n - 1) END END P; BEGIN P(3) END M.");
+        assert!(root_tree.is_ok());
+    }
+
+    #[test]
+    fn locals_can_shadow_module_variables() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; PROCEDURE P(x: INTEGER); BEGIN x := 1 END P; END M.").unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().child().sibling().child().sibling().child().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Ident(symbol) if symbol.name == "x" && symbol.level == 1);
+    }
+
+    #[test]
+    fn fails_on_wrong_number_of_parameters() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P(a, b: INTEGER); END P; BEGIN P(1) END M.");
+        assert_matches!(root_tree, Err(ParseError::ParameterCountMismatch { procedure, expected: 2, found: 1 }) if procedure == "P");
+    }
+
+    #[test]
+    fn fails_on_more_parameters_than_registers() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P(a, b, c, d, e, f, g, h, i, j, k, l, m: INTEGER); END P; END M.");
+        assert_matches!(root_tree, Err(ParseError::TooManyParameters(procedure, _)) if procedure == "P");
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P(a, b, c, d, e, f, g, h, i, j, k, l: INTEGER); END P; END M.");
+        assert_matches!(root_tree, Ok(_));
+    }
+
+    #[test]
+    fn fails_on_expression_passed_as_var_parameter() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P(VAR a: INTEGER); END P; BEGIN P(1) END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+    }
+
+    #[test]
+    fn fails_on_invalid_procedure_ending_name() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P; END Q; END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedBlockEnding { expected, found }) if expected == "P" && found == "Q");
+    }
+
+    #[test]
+    fn fails_on_access_to_variables_of_enclosing_procedure() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; PROCEDURE P; VAR x: INTEGER; PROCEDURE Q; BEGIN x := 1 END Q; END P; END M.");
+        assert_matches!(root_tree, Err(ParseError::InaccessibleSymbol(s)) if s == "x");
+    }
+
+    #[test]
+    fn can_parse_standard_procedure_calls() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; BEGIN\n INC(x, 2);\n ASSERT(ODD(x)) END M.").unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::Inc, 1));
+
+        let path = root.sibling().sibling().child().child().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Ident(symbol) if symbol.name == "x");
+
+        let path = root.sibling().sibling().child().child().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Constant(2));
+
+        let path = root.sibling().sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::Assert, 2));
+
+        let path = root.sibling().sibling().sibling().child().child().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::Odd, 2));
+    }
+
+    #[test]
+    fn fails_on_misused_standard_procedures() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; BEGIN ODD(x) END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; BEGIN x := INC(x) END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; BEGIN INC(3) END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; BEGIN x := LSL(x) END M.");
+        assert_matches!(root_tree, Err(ParseError::ParameterCountMismatch { procedure, expected: 2, found: 1 }) if procedure == "LSL");
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR x: INTEGER; BEGIN HALT(x) END M.");
+        assert_matches!(root_tree, Err(ParseError::NotAConstant(ident)) if ident == "x");
+    }
+
+    #[test]
+    fn can_parse_console_calls() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; VAR s: ARRAY 4 OF CHAR; a: ARRAY 2 OF INTEGER; BEGIN Out.String(s); Out.Ln; In.Int(a[0]) END M.").unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::OutString, 0));
+
+        // The capacity of the array is passed along with the printed string
+        let path = root.sibling().sibling().child().child().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::Constant(4));
+
+        let path = root.sibling().sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::OutLn, 0));
+
+        let path = root.sibling().sibling().sibling().sibling().child();
+        assert_matches!(path.follow(&root_tree).unwrap(), NodeInfo::StandardProcedureCall(StandardProcedure::InInt, 0));
+    }
+
+    #[test]
+    fn fails_on_misused_console_procedures() {
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; BEGIN Out.Real(3) END M.");
+        assert_matches!(root_tree, Err(ParseError::UndefinedSymbol(ident)) if ident == "Out.Real");
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; BEGIN Out.String(3) END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; BEGIN Out.Int END M.");
+        assert_matches!(root_tree, Err(ParseError::UnexpectedToken { .. }));
+
+        let scope = Scope::new();
+        let root_tree = parse_module(&scope, "MODULE M; BEGIN Out.Int(1, 2, 3) END M.");
+        assert_matches!(root_tree, Err(ParseError::ParameterCountMismatch { procedure, expected: 2, found: 3 }) if procedure == "Out.Int");
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast;
+    use crate::ast::Ast;
+    use crate::parser;
+    use crate::parser::*;
+    use crate::scanner::*;
+    use crate::scope::*;
+    use crate::token::*;
+    use crate::tree::*;
+    use std::assert_matches::assert_matches;
+
+    fn parse_module(content: &str) -> (Option<Ast>, ParseErrors) {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module_with_recovery(&mut scanner, &scope, &mut |module| Err(ParseError::UndefinedModule(String::from(module))))
+    }
+
+    #[test]
+    fn can_report_every_error_of_a_statement_sequence() {
+        let (module, errors) = parse_module(
+            "MODULE M; VAR i: INTEGER;
+            BEGIN
+              i := 1 +;
+              i := 2;
+              IF i > 0 THEN i := ) 3 ELSE i := 4 END;
+              j := 0
+            END M.",
+        );
+        assert!(module.is_some());
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_matches!(&errors[0], (ParseError::UnexpectedToken { found: scan, .. }, Some(ScanContext { line: 2, column: 22 })) if scan.token == Token::Semicolon);
+        assert_matches!(&errors[1], (ParseError::UnexpectedToken { found: scan, .. }, Some(ScanContext { line: 4, column: 33 })) if scan.token == Token::Rparen);
+        assert_matches!(&errors[2], (ParseError::UndefinedSymbol(name), Some(ScanContext { line: 5, column: 14 })) if name == "j");
+    }
+
+    #[test]
+    fn can_go_on_after_missing_semicolon() {
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := 1 i := 2; WHILE i > 0 DO DEC(i) END END M.");
+        assert!(module.is_some());
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_matches!(
+            &errors[0],
+            (ParseError::UnexpectedToken { expected, found }, Some(ScanContext { line: 0, column: 39 }))
+                if found.token == Token::Ident(String::from("i")) && expected == &vec![Token::Semicolon]
+        );
+    }
+
+    #[test]
+    fn can_report_every_error_of_declarations() {
+        let (module, errors) = parse_module(
+            "MODULE M;
+              CONST N = 1 +; K = 2;
+              TYPE T = ARRAY 3 OF Foo; U = RECORD x: INTEGER END;
+              VAR a: T; b: U c: INTEGER;
+              VAR d: INTEGER;
+              PROCEDURE P(x: Bar); BEGIN x := K END Q;
+            BEGIN a[0] := d + b.x END M.",
+        );
+        assert!(module.is_some());
+        let errors: Vec<_> = errors.into_iter().map(|(error, context)| (error, context.map(|context| context.line))).collect();
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert_matches!(&errors[0], (ParseError::UnexpectedToken { .. }, Some(1)));
+        assert_matches!(&errors[1], (ParseError::UndefinedSymbol(name), Some(2)) if name == "Foo");
+        assert_matches!(&errors[2], (ParseError::UnexpectedToken { .. }, Some(3)));
+        assert_matches!(&errors[3], (ParseError::UndefinedSymbol(name), Some(5)) if name == "Bar");
+        assert_matches!(&errors[4], (ParseError::UnexpectedBlockEnding { expected, found }, Some(5)) if expected == "P" && found == "Q");
+    }
+
+    #[test]
+    fn leaves_statements_with_errors_out_of_the_tree() {
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := ); i := 1 END M.");
+        assert_eq!(errors.len(), 1);
+        let module = module.unwrap();
+        let statements = ast::sibling(&module).and_then(ast::sibling).unwrap();
+        assert_matches!(ast::info(statements), Some(NodeInfo::StatementSequence(_)));
+        assert_matches!(ast::child(statements).map(|child| child.as_ref()), Some(Tree::Nil));
+        assert_matches!(ast::sibling(statements).and_then(ast::info), Some(NodeInfo::StatementSequence(_)));
+    }
+
+    #[test]
+    fn stops_at_errors_that_can_not_be_recovered_from() {
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := ); i := 99999999999; i := ) END M.");
+        assert!(module.is_none());
+        assert_eq!(errors.len(), 2);
+        assert_matches!(&errors[1], (ParseError::ScanError(_), _));
+
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := 0 END");
+        assert!(module.is_none());
+        assert_matches!(errors[..], [(ParseError::PrematureEof, None)]);
+    }
+
+    #[test]
+    fn only_checks_modules_without_syntax_errors() {
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := TRUE; i := ) END M.");
+        assert!(module.is_some());
+        assert_matches!(errors[..], [(ParseError::UnexpectedToken { .. }, _)]);
+
+        let (module, errors) = parse_module("MODULE M; VAR i: INTEGER; BEGIN i := TRUE END M.");
+        assert!(module.is_some());
+        assert_matches!(errors[..], [(ParseError::TypeMismatch { .. }, None)]);
+    }
+}
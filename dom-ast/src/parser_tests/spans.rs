@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast;
+    use crate::ast::Ast;
+    use crate::parser;
+    use crate::scanner::*;
+    use crate::scope::*;
+    use crate::token::*;
+    use crate::tree::*;
+    use std::assert_matches::assert_matches;
+
+    fn parse_module(content: &str) -> Ast {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module(&mut scanner, &scope).unwrap()
+    }
+
+    fn span(start: (u32, u32), end: (u32, u32)) -> Option<Span> {
+        Some(Span {
+            file: 0,
+            start: ScanContext { line: start.0, column: start.1 },
+            end: ScanContext { line: end.0, column: end.1 },
+        })
+    }
+
+    // The statements of the module body, in order
+    fn statements(module: &Ast) -> Vec<Ast> {
+        let declarations = ast::sibling(module).unwrap();
+        let mut statements = vec![];
+        let mut sequence = ast::sibling(declarations).unwrap();
+        while let Tree::Node(TreeNode {
+            info: NodeInfo::StatementSequence(_),
+            child,
+            sibling,
+            ..
+        }) = sequence.as_ref()
+        {
+            statements.push(child.clone());
+            sequence = sibling;
+        }
+        statements
+    }
+
+    #[test]
+    fn can_locate_statements() {
+        let module = parse_module(
+            "MODULE M; VAR i: INTEGER;
+            BEGIN
+              i := 1;
+              WHILE i < 10 DO
+                i := i * 2
+              END
+            END M.",
+        );
+        assert_eq!(ast::span(&module), span((0, 0), (6, 18)));
+
+        let statements = statements(&module);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(ast::span(&statements[0]), span((2, 14), (2, 20)));
+        assert_eq!(ast::span(&statements[1]), span((3, 14), (5, 17)));
+    }
+
+    #[test]
+    fn can_locate_expressions() {
+        let module = parse_module("MODULE M; VAR i: INTEGER; a: ARRAY 3 OF INTEGER; BEGIN a[2] := -i + 3 * i END M.");
+
+        let assignment = &statements(&module)[0];
+        assert_eq!(ast::span(assignment), span((0, 55), (0, 73)));
+
+        let designator = ast::child(assignment).unwrap();
+        assert_eq!(ast::span(designator), span((0, 55), (0, 59)));
+        let index = ast::child(designator).unwrap();
+        assert_eq!(ast::span(index), span((0, 57), (0, 59)));
+
+        let expression = ast::sibling(assignment).unwrap();
+        assert_eq!(ast::span(expression), span((0, 63), (0, 73)));
+        assert_eq!(ast::span(ast::child(expression).unwrap()), span((0, 63), (0, 65)));
+        assert_eq!(ast::span(ast::sibling(expression).unwrap()), span((0, 68), (0, 73)));
+    }
+
+    #[test]
+    fn can_locate_declarations() {
+        let module = parse_module(
+            "MODULE M;
+            VAR x, y: INTEGER;
+            PROCEDURE P;
+            BEGIN x := 1
+            END P;
+            END M.",
+        );
+        let declarations = ast::sibling(&module).unwrap();
+        let var = ast::child(declarations).unwrap();
+        assert_eq!(ast::span(var), span((1, 16), (1, 29)));
+        let procedure = ast::sibling(var).and_then(ast::sibling).unwrap();
+        assert_matches!(ast::info(procedure), Some(NodeInfo::ProcedureDeclaration(..)));
+        assert_eq!(ast::span(procedure), span((2, 12), (4, 17)));
+    }
+}
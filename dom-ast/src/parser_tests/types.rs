@@ -10,6 +10,7 @@ mod tests {
     use crate::scope::*;
     use crate::token::*;
     use crate::tree::*;
+    use std::rc::Rc;
 
     // NOTE(pht) move some of those to utils ?
     fn scope(symbols: Vec<&str>) -> Scope {
@@ -119,10 +120,10 @@ mod tests {
 
     #[test]
     fn can_parse_empty_declarations() {
-        let mut scope = Scope::new();
+        let scope = Scope::new();
         let mut scanner = Scanner::new("");
 
-        let root_tree = parser::parse_declarations(&mut scanner, &mut scope, &mut finish_parsing).unwrap();
+        let root_tree = parser::parse_declarations(&mut scanner, &scope, &mut finish_parsing).unwrap();
         assert_matches!(ast::info(&root_tree).unwrap(), NodeInfo::Declarations);
 
         assert!(ast::is_empty(ast::child(&root_tree).unwrap()));
@@ -130,10 +131,10 @@ mod tests {
 
     #[test]
     fn can_parse_declarations() {
-        let mut scope = Scope::new();
+        let scope = Scope::new();
         let mut scanner = Scanner::new("VAR x,y: INTEGER;");
         parser::scan_next(&mut scanner).unwrap();
-        let root_tree = parser::parse_declarations(&mut scanner, &mut scope, &mut finish_parsing).unwrap();
+        let root_tree = parser::parse_declarations(&mut scanner, &scope, &mut finish_parsing).unwrap();
         assert_matches!(ast::info(&root_tree).unwrap(), NodeInfo::Declarations);
         assert!(!ast::is_empty(ast::child(&root_tree).unwrap()));
     }
@@ -167,11 +168,266 @@ mod tests {
         assert_matches!(path.follow(child_tree).unwrap(), NodeInfo::Ident(ident) if ident.name == "a");
 
         path = root.child().sibling();
-        assert_matches!(path.follow(child_tree).unwrap(), NodeInfo::Type(VarType::Array(capacity)) if *capacity == 4);
+        assert_matches!(path.follow(child_tree).unwrap(), NodeInfo::Type(VarType::Array(capacity, element_type)) if *capacity == 4 && **element_type == VarType::Integer);
 
         assert_matches!(scope.lookup("a").unwrap().as_ref(), Symbol{name, size, ..} if name == "a" && *size == 4);
     }
 
+    #[test]
+    fn records_the_declared_type_of_variables() {
+        let mut scope = scope(vec![]);
+        parse_var_declarations(&mut scope, "VAR x: INTEGER; a: ARRAY 4 OF INTEGER;").unwrap();
+
+        let x = scope.lookup("x").unwrap();
+        assert_eq!(x.kind, SymbolKind::Variable);
+        assert_eq!(x.declared_type, Some(VarType::Integer));
+        assert_eq!(x.level, 0);
+
+        let a = scope.lookup("a").unwrap();
+        assert_eq!(a.declared_type, Some(VarType::Array(4, Rc::new(VarType::Integer))));
+    }
+
+    #[test]
+    fn fails_on_variable_used_as_a_type() {
+        let mut scope = scope(vec!["x"]);
+        let error = parse_var_declarations(&mut scope, "VAR y: x;").unwrap_err();
+        assert_matches!(error, ParseError::NotAType(ident) if ident == "x");
+    }
+
+    #[test]
+    fn can_shadow_predeclared_types() {
+        let mut scope = scope(vec![]);
+        parse_var_declarations(&mut scope, "VAR INTEGER: INTEGER;").unwrap();
+
+        let integer = scope.lookup("INTEGER").unwrap();
+        assert_eq!(integer.kind, SymbolKind::Variable);
+
+        let error = parse_var_declarations(&mut scope, "VAR y: INTEGER;").unwrap_err();
+        assert_matches!(error, ParseError::NotAType(ident) if ident == "INTEGER");
+    }
+
+    fn parse_declarations(scope: &Scope, content: &str) -> ParseResult {
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner)?;
+        parser::parse_declarations(&mut scanner, scope, &mut finish_parsing)
+    }
+
+    #[test]
+    fn can_parse_constant_declarations() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "CONST N = 10; M = (N + 2) * 3 - N / 2; B = (N > M) OR ~(M = 31); P = -M + 1; Q = (-7) DIV 2; R = (-7) MOD 2; VAR x: INTEGER;").unwrap();
+
+        let n = scope.lookup("N").unwrap();
+        assert_eq!(n.kind, SymbolKind::Constant(10));
+        assert_eq!(n.declared_type, Some(VarType::Integer));
+        assert_eq!(scope.lookup("M").unwrap().kind, SymbolKind::Constant(31));
+        assert_eq!(scope.lookup("P").unwrap().kind, SymbolKind::Constant(-30));
+        assert_eq!(scope.lookup("Q").unwrap().kind, SymbolKind::Constant(-4));
+        assert_eq!(scope.lookup("R").unwrap().kind, SymbolKind::Constant(1));
+
+        let b = scope.lookup("B").unwrap();
+        assert_eq!(b.kind, SymbolKind::Constant(0));
+        assert_eq!(b.declared_type, Some(VarType::Boolean));
+
+        // Constants do not take memory
+        assert_eq!(scope.lookup("x").unwrap().adr, 0);
+    }
+
+    #[test]
+    fn fails_on_invalid_constant_expressions() {
+        let error = parse_declarations(&scope(vec!["x"]), "CONST N = x + 1;").unwrap_err();
+        assert_matches!(error, ParseError::NotAConstant(ident) if ident == "x");
+
+        let error = parse_declarations(&scope(vec![]), "CONST N = 1 / 0;").unwrap_err();
+        assert_matches!(error, ParseError::InvalidConstantExpression);
+
+        let error = parse_declarations(&scope(vec![]), "CONST N = -(-2147483647 - 1);").unwrap_err();
+        assert_matches!(error, ParseError::InvalidConstantExpression);
+
+        // Constants are replaced by their value, which is then negated like a literal
+        let error = parse_declarations(&scope(vec![]), "CONST M = -2147483647 - 1; N = -M;").unwrap_err();
+        assert_matches!(error, ParseError::InvalidConstantExpression);
+
+        let error = parse_declarations(&scope(vec![]), "CONST N = 1 + TRUE;").unwrap_err();
+        assert_matches!(error, ParseError::InvalidConstantExpression);
+
+        let error = parse_declarations(&scope(vec![]), "CONST N = 1; N = 2;").unwrap_err();
+        assert_matches!(error, ParseError::SymbolAlreadyDeclared(ident, _) if ident == "N");
+    }
+
+    #[test]
+    fn can_parse_type_declarations() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "CONST N = 2; TYPE Int = INTEGER; Vec = ARRAY N * 2 OF Int; VAR v: Vec; i: Int;").unwrap();
+
+        let vec = scope.lookup("Vec").unwrap();
+        assert_eq!(vec.kind, SymbolKind::Type);
+        assert_eq!(vec.declared_type, Some(VarType::Array(4, Rc::new(VarType::Integer))));
+
+        let v = scope.lookup("v").unwrap();
+        assert_eq!(v.declared_type, Some(VarType::Array(4, Rc::new(VarType::Integer))));
+        assert_eq!(v.size, 4);
+        assert_eq!(scope.lookup("i").unwrap().declared_type, Some(VarType::Integer));
+    }
+
+    #[test]
+    fn fails_on_invalid_array_sizes() {
+        let error = parse_declarations(&scope(vec![]), "CONST N = 0; VAR a: ARRAY N OF INTEGER;").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+
+        let error = parse_declarations(&scope(vec![]), "VAR a: ARRAY TRUE OF INTEGER;").unwrap_err();
+        assert_matches!(error, ParseError::UnexpectedToken { .. });
+    }
+
+    #[test]
+    fn can_parse_record_types() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "TYPE Point = RECORD x, y: INTEGER; visible: BOOLEAN END; Shape = RECORD corners: ARRAY 4 OF Point; END; VAR s: Shape;").unwrap();
+
+        let point = scope.lookup("Point").unwrap().declared_type.clone().unwrap();
+        assert_eq!(point.size(), 3);
+        assert_matches!(&point, VarType::Record(fields) if fields[1].name == "y" && fields[1].offset == 1 && fields[2].offset == 2 && fields[2].field_type == VarType::Boolean);
+
+        let s = scope.lookup("s").unwrap();
+        assert_eq!(s.size, 12);
+        assert_matches!(s.declared_type.as_ref().unwrap(), VarType::Record(fields) if fields[0].field_type == VarType::Array(4, Rc::new(point)));
+    }
+
+    #[test]
+    fn can_parse_multi_dimensional_arrays() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "TYPE Row = ARRAY 3 OF INTEGER; VAR a: ARRAY 2 OF Row; b: ARRAY 2, 3 OF INTEGER;").unwrap();
+
+        let row = VarType::Array(3, Rc::new(VarType::Integer));
+        let a = scope.lookup("a").unwrap();
+        assert_eq!(a.size, 6);
+        assert_eq!(a.declared_type, Some(VarType::Array(2, Rc::new(row))));
+        assert_eq!(scope.lookup("b").unwrap().declared_type, a.declared_type);
+    }
+
+    #[test]
+    fn fails_on_constant_index_out_of_bounds() {
+        let scope = scope(vec![]);
+        let mut scanner = Scanner::new("CONST N = 3; VAR a: ARRAY N OF INTEGER; BEGIN a[N] := 1");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut finish_parsing).unwrap();
+        parser::scan_next(&mut scanner).unwrap();
+        let error = parser::parse_statement_sequence(&mut scanner, &scope).unwrap_err();
+        assert_matches!(error, ParseError::IndexOutOfBounds(3));
+    }
+
+    #[test]
+    fn can_parse_characters_and_strings() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "CONST A = 41X; B = \"B\"; LF = 0AX; VAR c: CHAR; s: ARRAY 4 OF CHAR;").unwrap();
+
+        let a = scope.lookup("A").unwrap();
+        assert_eq!(a.kind, SymbolKind::Constant(65));
+        assert_eq!(a.declared_type, Some(VarType::Char));
+        assert_eq!(scope.lookup("B").unwrap().kind, SymbolKind::Constant(66));
+        assert_eq!(scope.lookup("LF").unwrap().kind, SymbolKind::Constant(10));
+        assert_eq!(scope.lookup("s").unwrap().size, 4);
+
+        let mut scanner = Scanner::new("c := A; s := \"abc\"; s[0] := \"x\"");
+        parser::scan_next(&mut scanner).unwrap();
+        let tree = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Char(65));
+
+        let path = root.sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::String(value) if value == "abc");
+
+        // A single character is stored as a value in an element of the array
+        let path = root.sibling().sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Char(120));
+    }
+
+    #[test]
+    fn fails_on_strings_that_can_not_be_assigned() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "VAR c: CHAR; s: ARRAY 4 OF CHAR; a: ARRAY 8 OF INTEGER;").unwrap();
+
+        for statement in ["s := \"abcd\"", "c := \"ab\"", "a := \"abc\""] {
+            let mut scanner = Scanner::new(statement);
+            parser::scan_next(&mut scanner).unwrap();
+            let error = parser::parse_statement_sequence(&mut scanner, &scope).unwrap_err();
+            assert_matches!(error, ParseError::StringNotAssignable(_, _));
+        }
+    }
+
+    #[test]
+    fn can_parse_sets() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "CONST S = {0, 2..4} + {31}; C = -{0..30}; I = 3 IN S; VAR s: SET; i: INTEGER;").unwrap();
+
+        let s = scope.lookup("S").unwrap();
+        assert_eq!(s.kind, SymbolKind::Constant(0b11101 | i32::MIN));
+        assert_eq!(s.declared_type, Some(VarType::Set));
+        assert_eq!(scope.lookup("C").unwrap().kind, SymbolKind::Constant(i32::MIN));
+        assert_eq!(scope.lookup("I").unwrap().kind, SymbolKind::Constant(1));
+
+        let mut scanner = Scanner::new("s := s * {i} / {}; s := -s - {i..7}; i := i * 2 / 3");
+        parser::scan_next(&mut scanner).unwrap();
+        let tree = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Term(TermOp::SymmetricDifference));
+        let path = root.child().sibling().child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Term(TermOp::Intersection));
+        let path = root.child().sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::SetConstructor);
+        let path = root.child().sibling().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Set(0));
+
+        // The complement of a set is its difference with the full set
+        let path = root.sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Difference));
+        let path = root.sibling().child().sibling().child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Difference));
+        let path = root.sibling().child().sibling().child().child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Set(u32::MAX));
+        let path = root.sibling().child().sibling().sibling().child().child();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Range);
+
+        // Operators keep their arithmetic meaning on integers
+        let path = root.sibling().sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Term(TermOp::Div));
+    }
+
+    #[test]
+    fn chooses_set_operators_from_both_operands() {
+        let scope = scope(vec![]);
+        parse_declarations(&scope, "VAR s: SET; i: INTEGER;").unwrap();
+
+        let mut scanner = Scanner::new("s := i + s; s := i * s");
+        parser::scan_next(&mut scanner).unwrap();
+        let tree = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut root = ast::Path::root();
+        let path = root.child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::SimpleExpression(SimpleExpressionOp::Union));
+        let path = root.sibling().child().sibling();
+        assert_matches!(path.follow(&tree).unwrap(), NodeInfo::Term(TermOp::Intersection));
+    }
+
+    #[test]
+    fn fails_on_set_elements_out_of_range() {
+        let error = parse_declarations(&scope(vec![]), "CONST S = {1, 32};").unwrap_err();
+        assert_matches!(error, ParseError::SetElementOutOfRange(32));
+
+        let error = parse_declarations(&scope(vec![]), "CONST S = {-1..3};").unwrap_err();
+        assert_matches!(error, ParseError::SetElementOutOfRange(-1));
+    }
+
+    #[test]
+    fn fails_on_duplicate_fields() {
+        let error = parse_declarations(&scope(vec![]), "TYPE Point = RECORD x, y: INTEGER; x: INTEGER END;").unwrap_err();
+        assert_matches!(error, ParseError::SymbolAlreadyDeclared(ident, _) if ident == "x");
+    }
+
     fn finish_parsing(_scanner: &mut Scanner, _scope: &Scope) -> ParseResult {
         Ok(ast::empty())
     }
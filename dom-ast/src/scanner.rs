@@ -5,51 +5,87 @@ use std::str::Lines;
 
 #[derive(Debug)]
 pub struct Scanner<'a> {
+    file: FileId,
     line_number: u32,
     lines: Lines<'a>,
     line_scanner: LineScanner<'a>,
+    // Where the token before the current one starts and ends
+    previous_start: ScanContext,
+    previous_end: ScanContext,
 }
 
 impl Scanner<'_> {
     pub fn new(s: &str) -> Scanner {
+        Scanner::for_file(s, 0)
+    }
+
+    pub fn for_file(s: &str, file: FileId) -> Scanner {
         let mut lines = s.lines();
         match lines.next() {
             Some(line) => {
                 return Scanner {
+                    file,
                     line_number: 0,
                     lines,
                     line_scanner: LineScanner::new(0, line),
+                    previous_start: ScanContext::default(),
+                    previous_end: ScanContext::default(),
                 }
             }
             None => {
                 return Scanner {
+                    file,
                     line_number: 0,
                     lines,
                     line_scanner: LineScanner::new(0, ""),
+                    previous_start: ScanContext::default(),
+                    previous_end: ScanContext::default(),
                 }
             }
         }
     }
 
+    pub fn file(&self) -> FileId {
+        self.file
+    }
+
+    // Where the last token the parser consumed starts
+    pub fn previous_start(&self) -> ScanContext {
+        self.previous_start
+    }
+
+    // Where the tokens the parser consumed end
+    pub fn previous_end(&self) -> ScanContext {
+        self.previous_end
+    }
+
     pub fn current(&mut self) -> Option<Rc<Scan>> {
         self.line_scanner.current()
     }
-}
 
-impl Iterator for Scanner<'_> {
-    type Item = ScanResult;
-
-    fn next<'a>(&mut self) -> Option<ScanResult> {
+    fn scan(&mut self) -> Option<ScanResult> {
         match self.line_scanner.next() {
             Some(scan) => Some(scan),
             None => match self.lines.next() {
                 Some(line) => {
                     self.line_number += 1;
                     self.line_scanner = LineScanner::new(self.line_number, line);
-                    self.next()
+                    self.scan()
                 }
                 None => None,
             },
         }
     }
 }
+
+impl Iterator for Scanner<'_> {
+    type Item = ScanResult;
+
+    fn next<'a>(&mut self) -> Option<ScanResult> {
+        if let Some(current) = self.line_scanner.current.as_ref() {
+            self.previous_start = current.context;
+            self.previous_end = self.line_scanner.end();
+        }
+        self.scan()
+    }
+}
@@ -48,4 +48,19 @@ mod tests {
 
         assert_eq!(None, scanner.next());
     }
+
+    #[test]
+    fn test_remembers_where_the_previous_token_is() {
+        let content = "foo := 12\n  bar";
+        let mut scanner = Scanner::new(content);
+        scanner.next();
+        scanner.next();
+        assert_eq!(ScanContext { line: 0, column: 0 }, scanner.previous_start());
+        assert_eq!(ScanContext { line: 0, column: 3 }, scanner.previous_end());
+
+        scanner.next();
+        scanner.next();
+        assert_eq!(ScanContext { line: 0, column: 7 }, scanner.previous_start());
+        assert_eq!(ScanContext { line: 0, column: 9 }, scanner.previous_end());
+    }
 }
@@ -1,13 +1,102 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+use crate::parser::{ParseError, ParseErrors};
+use crate::token::ScanContext;
+use crate::tree::VarType;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    // by_reference is true for VAR parameters, whose slot holds the address of the actual variable
+    Parameter { by_reference: bool },
+    Procedure(Vec<Rc<Symbol>>),
+    Constant(i32),
+    Type,
+    StandardProcedure(StandardProcedure),
+    // Exported symbols, reached with a qualified identifier
+    Module(Vec<Rc<Symbol>>),
+}
+
+// Predeclared procedures and functions, compiled inline instead of being called
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StandardProcedure {
+    Inc,
+    Dec,
+    Assert,
+    Halt,
+    Odd,
+    Abs,
+    Lsl,
+    Asr,
+    Ror,
+    // Console procedures of the predeclared Out and In modules
+    OutInt,
+    OutChar,
+    OutString,
+    OutLn,
+    InInt,
+}
+
+impl StandardProcedure {
+    // Functions return a value, and can only be used in expressions
+    pub fn is_function(&self) -> bool {
+        matches!(self, StandardProcedure::Odd | StandardProcedure::Abs | StandardProcedure::Lsl | StandardProcedure::Asr | StandardProcedure::Ror)
+    }
+
+    // Minimum and maximum number of parameters
+    pub fn parameter_count(&self) -> (usize, usize) {
+        match self {
+            StandardProcedure::Inc | StandardProcedure::Dec | StandardProcedure::OutInt => (1, 2),
+            StandardProcedure::OutLn => (0, 0),
+            StandardProcedure::Lsl | StandardProcedure::Asr | StandardProcedure::Ror => (2, 2),
+            _ => (1, 1),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandardProcedure::Inc => "INC",
+            StandardProcedure::Dec => "DEC",
+            StandardProcedure::Assert => "ASSERT",
+            StandardProcedure::Halt => "HALT",
+            StandardProcedure::Odd => "ODD",
+            StandardProcedure::Abs => "ABS",
+            StandardProcedure::Lsl => "LSL",
+            StandardProcedure::Asr => "ASR",
+            StandardProcedure::Ror => "ROR",
+            StandardProcedure::OutInt => "Out.Int",
+            StandardProcedure::OutChar => "Out.Char",
+            StandardProcedure::OutString => "Out.String",
+            StandardProcedure::OutLn => "Out.Ln",
+            StandardProcedure::InInt => "In.Int",
+        }
+    }
+}
+
+// Export mark of a module level declaration, other modules can not see unmarked ones
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Export {
+    ReadWrite, // `*`
+    ReadOnly,  // `-`, only for variables
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Symbol {
     pub name: String,
-    // offset from stack base
+    // offset from stack base (or from the frame pointer, for procedure locals)
     pub adr: usize,
     // size of the variable in bytes
     pub size: usize,
+    // 0 for module level symbols, incremented for each nested procedure
+    pub level: usize,
+    pub kind: SymbolKind,
+    // Type of variables, parameters and constants, or type named by a type symbol.
+    // Procedures have no type.
+    pub declared_type: Option<VarType>,
+    pub export: Option<Export>,
+    // Module the symbol was imported from, None for symbols of the module being compiled
+    pub module: Option<String>,
 }
 
 struct Content {
@@ -15,34 +104,189 @@ struct Content {
     next_adr: usize,
 }
 
-pub struct Scope {
+pub struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    // Predeclared identifiers, only owned by the module scope
+    universe: Option<Box<Scope<'static>>>,
+    level: usize,
     content: RefCell<Content>,
+    // Errors the parser recovered from, only kept by the module scope when it collects them
+    errors: RefCell<Option<ParseErrors>>,
 }
-impl Scope {
-    pub fn new() -> Scope {
+
+impl<'a> Scope<'a> {
+    // Scope of the module, enclosed in the universe
+    pub fn new() -> Scope<'a> {
         Scope {
+            parent: None,
+            universe: Some(Box::new(Scope::universe())),
+            level: 0,
             content: RefCell::new(Content { symbols: vec![], next_adr: 0 }),
+            errors: RefCell::new(None),
         }
     }
 
+    // Scope of the predeclared identifiers, which can be shadowed by module declarations
+    pub fn universe() -> Scope<'a> {
+        let universe = Scope::new_module();
+        universe.add_type("INTEGER", VarType::Integer);
+        universe.add_type("BOOLEAN", VarType::Boolean);
+        universe.add_type("CHAR", VarType::Char);
+        universe.add_type("SET", VarType::Set);
+        universe.add_standard_procedure("INC", StandardProcedure::Inc);
+        universe.add_standard_procedure("DEC", StandardProcedure::Dec);
+        universe.add_standard_procedure("ASSERT", StandardProcedure::Assert);
+        universe.add_standard_procedure("HALT", StandardProcedure::Halt);
+        universe.add_standard_procedure("ODD", StandardProcedure::Odd);
+        universe.add_standard_procedure("ABS", StandardProcedure::Abs);
+        universe.add_standard_procedure("LSL", StandardProcedure::Lsl);
+        universe.add_standard_procedure("ASR", StandardProcedure::Asr);
+        universe.add_standard_procedure("ROR", StandardProcedure::Ror);
+
+        let out = Scope::new_module();
+        out.add_standard_procedure("Int", StandardProcedure::OutInt);
+        out.add_standard_procedure("Char", StandardProcedure::OutChar);
+        out.add_standard_procedure("String", StandardProcedure::OutString);
+        out.add_standard_procedure("Ln", StandardProcedure::OutLn);
+        universe.add_module("Out", out.symbols());
+
+        let input = Scope::new_module();
+        input.add_standard_procedure("Int", StandardProcedure::InInt);
+        universe.add_module("In", input.symbols());
+        universe
+    }
+
+    // Scope of a module, without access to the universe
+    fn new_module() -> Scope<'a> {
+        Scope {
+            parent: None,
+            universe: None,
+            level: 0,
+            content: RefCell::new(Content { symbols: vec![], next_adr: 0 }),
+            errors: RefCell::new(None),
+        }
+    }
+
+    // NOTE(pht) the first slot of a procedure frame holds the return address,
+    // so parameters and locals start right after it.
+    pub fn new_procedure(parent: &'a Scope<'a>) -> Scope<'a> {
+        Scope {
+            parent: Some(parent),
+            universe: None,
+            level: parent.level + 1,
+            content: RefCell::new(Content { symbols: vec![], next_adr: 1 }),
+            errors: RefCell::new(None),
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    // Number of memory slots used by the symbols of this scope
+    pub fn size(&self) -> usize {
+        self.content.borrow().next_adr
+    }
+
     pub fn add(&self, s: &str) {
-        self.add_with_size(s, 1);
+        self.add_variable(s, VarType::Integer);
     }
 
     pub fn add_with_size(&self, s: &str, size: usize) {
+        self.add_variable(s, VarType::Array(size as u32, Rc::new(VarType::Integer)));
+    }
+
+    pub fn add_variable(&self, s: &str, var_type: VarType) {
+        self.add_symbol(s, var_type.size(), SymbolKind::Variable, Some(var_type));
+    }
+
+    pub fn add_parameter(&self, s: &str, var_type: VarType, by_reference: bool) {
+        self.add_symbol(s, 1, SymbolKind::Parameter { by_reference }, Some(var_type));
+    }
+
+    // Procedures, constants and types do not take any room in memory, so they do not move next_adr
+    pub fn add_procedure(&self, s: &str, parameters: Vec<Rc<Symbol>>) {
+        self.add_symbol(s, 0, SymbolKind::Procedure(parameters), None);
+    }
+
+    pub fn add_constant(&self, s: &str, var_type: VarType, value: i32) {
+        self.add_symbol(s, 0, SymbolKind::Constant(value), Some(var_type));
+    }
+
+    pub fn add_type(&self, s: &str, var_type: VarType) {
+        self.add_symbol(s, 0, SymbolKind::Type, Some(var_type));
+    }
+
+    pub fn add_standard_procedure(&self, s: &str, procedure: StandardProcedure) {
+        self.add_symbol(s, 0, SymbolKind::StandardProcedure(procedure), None);
+    }
+
+    pub fn add_module(&self, s: &str, exports: Vec<Rc<Symbol>>) {
+        self.add_symbol(s, 0, SymbolKind::Module(exports), None);
+    }
+
+    // NOTE(pht) symbols are shared once looked up, so the export mark has to be set
+    // right after the symbol is added.
+    pub fn export(&self, s: &str, export: Export) {
+        let mut content = self.content.borrow_mut();
+        if let Some(symbol) = content.symbols.iter_mut().rev().find(|symbol| symbol.name == s) {
+            *symbol = Rc::new(Symbol {
+                export: Some(export),
+                ..symbol.as_ref().clone()
+            });
+        }
+    }
+
+    // Parsers stop at the first error, unless the errors are collected
+    pub fn collect_errors(&self) {
+        self.errors.replace(Some(vec![]));
+    }
+
+    // NOTE(pht) an error found at the same token as the previous one is usually caused by it,
+    // so it is not reported again
+    pub fn report_error(&self, error: ParseError, context: Option<ScanContext>) -> Result<(), ParseError> {
+        if let Some(parent) = self.parent {
+            return parent.report_error(error, context);
+        }
+        match self.errors.borrow_mut().as_mut() {
+            None => Err(error),
+            Some(errors) => {
+                if context.is_none() || errors.last().map_or(true, |(_, last)| *last != context) {
+                    errors.push((error, context));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn take_errors(&self) -> ParseErrors {
+        self.errors.take().unwrap_or_default()
+    }
+
+    pub fn symbols(&self) -> Vec<Rc<Symbol>> {
+        self.content.borrow().symbols.clone()
+    }
+
+    fn add_symbol(&self, s: &str, size: usize, kind: SymbolKind, declared_type: Option<VarType>) {
         let mut content = self.content.borrow_mut();
 
         let symbol = Symbol {
             name: String::from(s),
             adr: content.next_adr,
             size,
+            level: self.level,
+            kind,
+            declared_type,
+            export: None,
+            module: None,
         };
 
         content.next_adr += size;
         content.symbols.push(Rc::new(symbol));
     }
 
-    pub fn lookup(&self, s: &str) -> Option<Rc<Symbol>> {
+    // Only looks for the symbol in this scope, not in the enclosing ones
+    pub fn lookup_local(&self, s: &str) -> Option<Rc<Symbol>> {
         let content = self.content.borrow();
 
         for symbol in content.symbols.iter() {
@@ -52,9 +296,19 @@ impl Scope {
         }
         None
     }
+
+    pub fn lookup(&self, s: &str) -> Option<Rc<Symbol>> {
+        if let Some(symbol) = self.lookup_local(s) {
+            return Some(symbol);
+        }
+        if let Some(parent) = self.parent {
+            return parent.lookup(s);
+        }
+        self.universe.as_ref().and_then(|universe| universe.lookup(s))
+    }
 }
 
-impl Default for Scope {
+impl Default for Scope<'_> {
     fn default() -> Self {
         Self::new()
     }
@@ -107,6 +361,79 @@ mod tests {
         assert_eq!(y.size, 1);
     }
 
+    #[test]
+    fn can_find_symbol_in_enclosing_scope() {
+        let s = Scope::new();
+        s.add("x");
+        let p = Scope::new_procedure(&s);
+        p.add_parameter("y", VarType::Integer, true);
+
+        let x = p.lookup("x").unwrap();
+        assert_eq!(x.level, 0);
+        assert_matches!(p.lookup_local("x"), None);
+
+        let y = p.lookup("y").unwrap();
+        assert_eq!(y.level, 1);
+        assert_eq!(y.adr, 1);
+        assert_matches!(y.kind, SymbolKind::Parameter { by_reference: true });
+        assert_matches!(s.lookup("y"), None);
+    }
+
+    #[test]
+    fn procedures_do_not_take_memory() {
+        let s = Scope::new();
+        s.add("x");
+        s.add_procedure("P", vec![]);
+        s.add("y");
+        assert_eq!(s.lookup("y").unwrap().adr, 1);
+        assert_eq!(s.size(), 2);
+    }
+
+    #[test]
+    fn can_find_predeclared_types() {
+        let s = Scope::new();
+        let p = Scope::new_procedure(&s);
+
+        let integer = p.lookup("INTEGER").unwrap();
+        assert_eq!(integer.kind, SymbolKind::Type);
+        assert_eq!(integer.declared_type, Some(VarType::Integer));
+        assert_matches!(s.lookup_local("INTEGER"), None);
+
+        let inc = p.lookup("INC").unwrap();
+        assert_eq!(inc.kind, SymbolKind::StandardProcedure(StandardProcedure::Inc));
+        assert!(!StandardProcedure::Inc.is_function());
+        assert!(StandardProcedure::Odd.is_function());
+
+        let out = p.lookup("Out").unwrap();
+        assert_matches!(&out.kind, SymbolKind::Module(exports) if exports.iter().any(|e| e.name == "Ln" && e.kind == SymbolKind::StandardProcedure(StandardProcedure::OutLn)));
+    }
+
+    #[test]
+    fn declarations_shadow_enclosing_scopes() {
+        let s = Scope::new();
+        s.add_constant("INTEGER", VarType::Integer, 42);
+        s.add("x");
+        let p = Scope::new_procedure(&s);
+        p.add_with_size("x", 3);
+
+        assert_eq!(s.lookup("INTEGER").unwrap().kind, SymbolKind::Constant(42));
+
+        let x = p.lookup("x").unwrap();
+        assert_eq!(x.level, 1);
+        assert_eq!(x.declared_type, Some(VarType::Array(3, Rc::new(VarType::Integer))));
+        assert_eq!(s.lookup("x").unwrap().declared_type, Some(VarType::Integer));
+    }
+
+    #[test]
+    fn constants_and_types_do_not_take_memory() {
+        let s = Scope::new();
+        s.add_constant("N", VarType::Integer, 10);
+        s.add_type("Vector", VarType::Array(10, Rc::new(VarType::Integer)));
+        s.add("x");
+        assert_eq!(s.lookup("x").unwrap().adr, 0);
+        assert_eq!(s.size(), 1);
+    }
+
     fn function_that_adds(s: &str, scope: &mut Scope) {
         scope.add(s);
     }
@@ -0,0 +1,229 @@
+// Symbol files describe what a module exports, so that its importers can be compiled without its source.
+//
+// The first line names the module, then each exported symbol takes one line, with its
+// type written in prefix form :
+//
+//   MODULE Shapes
+//   CONST Max * 10 INTEGER
+//   TYPE Point * RECORD 2 x INTEGER y INTEGER
+//   VAR origin - 3 RECORD 2 x INTEGER y INTEGER
+//   PROCEDURE Move * VAR p RECORD 2 x INTEGER y INTEGER VALUE dx INTEGER
+use std::rc::Rc;
+
+use crate::parser::ParseError;
+use crate::scope::{Export, Symbol, SymbolKind};
+use crate::tree::{Field, VarType};
+
+pub fn write(module: &str, symbols: &[Rc<Symbol>]) -> String {
+    let mut content = format!("MODULE {}\n", module);
+    for symbol in symbols.iter() {
+        let mark = match symbol.export {
+            Some(Export::ReadWrite) => "*",
+            Some(Export::ReadOnly) => "-",
+            None => continue,
+        };
+        let declared_type = symbol.declared_type.as_ref().map(write_type).unwrap_or_default();
+        let line = match &symbol.kind {
+            SymbolKind::Constant(value) => format!("CONST {} {} {} {}", symbol.name, mark, value, declared_type),
+            SymbolKind::Type => format!("TYPE {} {} {}", symbol.name, mark, declared_type),
+            SymbolKind::Variable => format!("VAR {} {} {} {}", symbol.name, mark, symbol.adr, declared_type),
+            SymbolKind::Procedure(parameters) => {
+                let mut line = format!("PROCEDURE {} {}", symbol.name, mark);
+                for parameter in parameters.iter() {
+                    let mode = match parameter.kind {
+                        SymbolKind::Parameter { by_reference: true } => "VAR",
+                        _ => "VALUE",
+                    };
+                    let parameter_type = parameter.declared_type.as_ref().map(write_type).unwrap_or_default();
+                    line.push_str(&format!(" {} {} {}", mode, parameter.name, parameter_type));
+                }
+                line
+            }
+            _ => continue,
+        };
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content
+}
+
+fn write_type(var_type: &VarType) -> String {
+    match var_type {
+        VarType::Integer => String::from("INTEGER"),
+        VarType::Boolean => String::from("BOOLEAN"),
+        VarType::Char => String::from("CHAR"),
+        VarType::Set => String::from("SET"),
+        VarType::Array(capacity, element_type) => format!("ARRAY {} {}", capacity, write_type(element_type)),
+        VarType::Record(fields) => {
+            let mut record = format!("RECORD {}", fields.len());
+            for field in fields.iter() {
+                record.push_str(&format!(" {} {}", field.name, write_type(&field.field_type)));
+            }
+            record
+        }
+    }
+}
+
+// Gives the name of the module, and the symbols it exports
+pub fn read(content: &str) -> Result<(String, Vec<Rc<Symbol>>), ParseError> {
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or_default();
+    let module = match header.split_whitespace().collect::<Vec<_>>()[..] {
+        ["MODULE", module] => String::from(module),
+        _ => return Err(ParseError::InvalidSymbolFile(String::from(header))),
+    };
+
+    let mut symbols = vec![];
+    for line in lines {
+        let symbol = read_symbol(&module, line).ok_or_else(|| ParseError::InvalidSymbolFile(String::from(line)))?;
+        symbols.push(Rc::new(symbol));
+    }
+    Ok((module, symbols))
+}
+
+fn read_symbol(module: &str, line: &str) -> Option<Symbol> {
+    let mut words = line.split_whitespace();
+    let kind = words.next()?;
+    let name = String::from(words.next()?);
+    let export = match words.next()? {
+        "*" => Export::ReadWrite,
+        "-" if kind == "VAR" => Export::ReadOnly,
+        _ => return None,
+    };
+
+    let (adr, kind, declared_type) = match kind {
+        "CONST" => {
+            let value = words.next()?.parse().ok()?;
+            (0, SymbolKind::Constant(value), Some(read_type(&mut words)?))
+        }
+        "TYPE" => (0, SymbolKind::Type, Some(read_type(&mut words)?)),
+        "VAR" => {
+            let adr = words.next()?.parse().ok()?;
+            (adr, SymbolKind::Variable, Some(read_type(&mut words)?))
+        }
+        "PROCEDURE" => {
+            let mut parameters = vec![];
+            while let Some(mode) = words.next() {
+                let by_reference = match mode {
+                    "VAR" => true,
+                    "VALUE" => false,
+                    _ => return None,
+                };
+                let parameter_name = String::from(words.next()?);
+                let parameter_type = read_type(&mut words)?;
+                // Parameters follow the return address in the frame of the procedure
+                parameters.push(Rc::new(Symbol {
+                    name: parameter_name,
+                    adr: parameters.len() + 1,
+                    size: 1,
+                    level: 1,
+                    kind: SymbolKind::Parameter { by_reference },
+                    declared_type: Some(parameter_type),
+                    export: None,
+                    module: Some(String::from(module)),
+                }));
+            }
+            (0, SymbolKind::Procedure(parameters), None)
+        }
+        _ => return None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+
+    Some(Symbol {
+        name,
+        adr,
+        size: declared_type.as_ref().filter(|_| kind == SymbolKind::Variable).map_or(0, VarType::size),
+        level: 0,
+        kind,
+        declared_type,
+        export: Some(export),
+        module: Some(String::from(module)),
+    })
+}
+
+fn read_type<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<VarType> {
+    match words.next()? {
+        "INTEGER" => Some(VarType::Integer),
+        "BOOLEAN" => Some(VarType::Boolean),
+        "CHAR" => Some(VarType::Char),
+        "SET" => Some(VarType::Set),
+        "ARRAY" => {
+            let capacity = words.next()?.parse().ok()?;
+            Some(VarType::Array(capacity, Rc::new(read_type(words)?)))
+        }
+        "RECORD" => {
+            let count: usize = words.next()?.parse().ok()?;
+            let mut fields = vec![];
+            let mut offset = 0;
+            for _ in 0..count {
+                let name = String::from(words.next()?);
+                let field_type = read_type(words)?;
+                let size = field_type.size();
+                fields.push(Field { name, offset, field_type });
+                offset += size;
+            }
+            Some(VarType::Record(Rc::new(fields)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::parser;
+    use crate::scanner::Scanner;
+    use crate::scope::Scope;
+
+    #[test]
+    fn can_read_written_symbols() {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new(
+            "MODULE Shapes;
+              CONST Max* = 10; Min = 0;
+              TYPE Point* = RECORD x, y: INTEGER END;
+              VAR hidden: INTEGER; origin-: Point; names*: ARRAY 2, 3 OF CHAR;
+              PROCEDURE Move*(VAR p: Point; dx: INTEGER); BEGIN p.x := p.x + dx END Move;
+            END Shapes.",
+        );
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_module(&mut scanner, &scope).unwrap();
+
+        let content = write("Shapes", &scope.symbols());
+        assert_eq!(
+            content,
+            "MODULE Shapes
+CONST Max * 10 INTEGER
+TYPE Point * RECORD 2 x INTEGER y INTEGER
+VAR origin - 2 RECORD 2 x INTEGER y INTEGER
+VAR names * 4 ARRAY 2 ARRAY 3 CHAR
+PROCEDURE Move * VAR p RECORD 2 x INTEGER y INTEGER VALUE dx INTEGER
+"
+        );
+
+        let (module, symbols) = read(&content).unwrap();
+        assert_eq!(module, "Shapes");
+        assert_eq!(symbols.len(), 5);
+        for symbol in symbols.iter() {
+            let original = scope.lookup(&symbol.name).unwrap();
+            if symbol.kind == SymbolKind::Variable {
+                assert_eq!(symbol.adr, original.adr);
+            }
+            assert_eq!(symbol.size, original.size);
+            assert_eq!(symbol.declared_type, original.declared_type);
+            assert_eq!(symbol.export, original.export);
+            assert_eq!(symbol.module.as_deref(), Some("Shapes"));
+        }
+    }
+
+    #[test]
+    fn fails_on_invalid_symbol_files() {
+        assert_matches!(read("Shapes"), Err(ParseError::InvalidSymbolFile(line)) if line == "Shapes");
+        assert_matches!(read("MODULE Shapes\nCONST Max - 10 INTEGER"), Err(ParseError::InvalidSymbolFile(_)));
+        assert_matches!(read("MODULE Shapes\nVAR x * 1 ARRAY 3"), Err(ParseError::InvalidSymbolFile(_)));
+    }
+}
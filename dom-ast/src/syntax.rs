@@ -0,0 +1,576 @@
+// Typed syntax tree of a module, with a node type for each construct of the language.
+//
+// NOTE(pht) the parser still builds the child / sibling tree, that the checker works on ; the typed
+// tree is converted from it once the module is parsed and checked, until the parser builds it directly.
+use std::rc::Rc;
+
+use crate::ast::Ast;
+use crate::scope::{StandardProcedure, Symbol};
+use crate::token::Span;
+use crate::tree::{ExpressionOp, NodeInfo, SimpleExpressionOp, TermOp, Tree, TreeNode, VarType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: Rc<Symbol>,
+    pub declarations: Vec<Decl>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+// Constants and types only live in the scope, they are not part of the tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decl {
+    Var {
+        symbol: Rc<Symbol>,
+        var_type: VarType,
+        span: Span,
+    },
+    Procedure {
+        symbol: Rc<Symbol>,
+        frame_size: usize,
+        declarations: Vec<Decl>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assignment {
+        target: Designator,
+        value: Expr,
+        span: Span,
+    },
+    ProcedureCall {
+        procedure: Rc<Symbol>,
+        parameters: Vec<Expr>,
+        span: Span,
+    },
+    // Predeclared procedures that do not return a value, like INC or Out.Int
+    StandardProcedureCall {
+        procedure: StandardProcedure,
+        parameters: Vec<Expr>,
+        span: Span,
+    },
+    // The IF branch comes first, followed by the ELSIF ones
+    If {
+        branches: Vec<Branch>,
+        else_branch: Option<Vec<Stmt>>,
+        span: Span,
+    },
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    Repeat {
+        body: Vec<Stmt>,
+        condition: Expr,
+        span: Span,
+    },
+    // The control variable is incremented after the body, by the step
+    For {
+        control: Designator,
+        begin: Expr,
+        limit: Expr,
+        step: i32,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    Case {
+        selector: Expr,
+        cases: Vec<Case>,
+        else_branch: Option<Vec<Stmt>>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub condition: Expr,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    // Ranges of labels, bounds included
+    pub labels: Vec<(i32, i32)>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Constant {
+        value: i32,
+        span: Span,
+    },
+    Boolean {
+        value: bool,
+        span: Span,
+    },
+    Char {
+        value: u8,
+        span: Span,
+    },
+    // Laid out in the data area, unless it is a single character used as a value
+    String {
+        value: String,
+        span: Span,
+    },
+    // Constant set, one bit per element
+    Set {
+        value: u32,
+        span: Span,
+    },
+    SetConstructor {
+        elements: Vec<SetElement>,
+        span: Span,
+    },
+    Designator(Designator),
+    Relation {
+        operator: ExpressionOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Addition {
+        operator: SimpleExpressionOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Multiplication {
+        operator: TermOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Not {
+        operand: Box<Expr>,
+        span: Span,
+    },
+    Negate {
+        operand: Box<Expr>,
+        span: Span,
+    },
+    // Predeclared functions, like ODD or ABS
+    StandardFunctionCall {
+        procedure: StandardProcedure,
+        parameters: Vec<Expr>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetElement {
+    Single(Expr),
+    Range { low: Expr, high: Expr, span: Span },
+}
+
+// A variable with its selectors, applied in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Designator {
+    pub symbol: Rc<Symbol>,
+    pub selectors: Vec<Selector>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    // Offset of the field, in words, from the start of the record
+    Field {
+        name: String,
+        offset: usize,
+        span: Span,
+    },
+    // Capacity of the array if known, to check the index against
+    Index {
+        index: Expr,
+        element_size: usize,
+        capacity: Option<u32>,
+        span: Span,
+    },
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Assignment { span, .. }
+            | Stmt::ProcedureCall { span, .. }
+            | Stmt::StandardProcedureCall { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Repeat { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::Case { span, .. } => *span,
+        }
+    }
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Designator(designator) => designator.span,
+            Expr::Constant { span, .. }
+            | Expr::Boolean { span, .. }
+            | Expr::Char { span, .. }
+            | Expr::String { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::SetConstructor { span, .. }
+            | Expr::Relation { span, .. }
+            | Expr::Addition { span, .. }
+            | Expr::Multiplication { span, .. }
+            | Expr::Not { span, .. }
+            | Expr::Negate { span, .. }
+            | Expr::StandardFunctionCall { span, .. } => *span,
+        }
+    }
+}
+
+// Trees that the parser does not build, located at the node where the problem is found
+#[derive(Debug, PartialEq)]
+pub enum TreeError {
+    // A part of the node that the parser always builds is missing
+    MissingNode(Span),
+    // A node that only appears as part of another one, like a selector or an ELSIF branch, found on its own
+    UnexpectedNode(String, Span),
+    // Only variables can be assigned
+    NotAVariable(Span),
+}
+
+type TreeResult<T> = Result<T, TreeError>;
+
+pub fn module(tree: &Ast) -> TreeResult<Module> {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return Err(TreeError::MissingNode(Span::default())),
+    };
+    if node.info != NodeInfo::Module {
+        return Err(unexpected(node));
+    }
+
+    let name = match expect_node(&node.child, node)?.info {
+        NodeInfo::Ident(ref symbol) => symbol.clone(),
+        _ => return Err(unexpected(expect_node(&node.child, node)?)),
+    };
+    let (declarations, body) = block(&node.sibling, node)?;
+    Ok(Module {
+        name,
+        declarations,
+        body,
+        span: node.span,
+    })
+}
+
+// Declarations in the child, statements in the sibling
+fn block(tree: &Ast, parent: &TreeNode) -> TreeResult<(Vec<Decl>, Vec<Stmt>)> {
+    let node = expect_node(tree, parent)?;
+    match node.info {
+        NodeInfo::Declarations => Ok((declarations(&node.child)?, statements(&node.sibling)?)),
+        _ => Err(unexpected(node)),
+    }
+}
+
+fn declarations(tree: &Ast) -> TreeResult<Vec<Decl>> {
+    let mut declarations = vec![];
+    let mut declaration = tree;
+    while let Tree::Node(node) = declaration.as_ref() {
+        match &node.info {
+            NodeInfo::Declaration => {
+                let var = expect_node(&node.child, node)?;
+                let symbol = match &expect_node(&var.child, var)?.info {
+                    NodeInfo::Ident(symbol) => symbol.clone(),
+                    _ => return Err(unexpected(expect_node(&var.child, var)?)),
+                };
+                let var_type = match &expect_node(&var.sibling, var)?.info {
+                    NodeInfo::Type(var_type) => var_type.clone(),
+                    _ => return Err(unexpected(expect_node(&var.sibling, var)?)),
+                };
+                declarations.push(Decl::Var { symbol, var_type, span: var.span });
+            }
+            NodeInfo::ProcedureDeclaration(symbol, frame_size) => {
+                let (procedure_declarations, body) = block(&node.child, node)?;
+                declarations.push(Decl::Procedure {
+                    symbol: symbol.clone(),
+                    frame_size: *frame_size,
+                    declarations: procedure_declarations,
+                    body,
+                    span: node.span,
+                });
+            }
+            _ => return Err(unexpected(node)),
+        }
+        declaration = &node.sibling;
+    }
+    Ok(declarations)
+}
+
+// Empty statements are left out
+pub fn statements(tree: &Ast) -> TreeResult<Vec<Stmt>> {
+    let mut statements = vec![];
+    let mut sequence = tree;
+    while let Tree::Node(node) = sequence.as_ref() {
+        match node.info {
+            NodeInfo::StatementSequence(_) if matches!(node.child.as_ref(), Tree::Nil) => {}
+            NodeInfo::StatementSequence(_) => statements.push(statement(&node.child)?),
+            _ => return Err(unexpected(node)),
+        }
+        sequence = &node.sibling;
+    }
+    Ok(statements)
+}
+
+pub fn statement(tree: &Ast) -> TreeResult<Stmt> {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return Err(TreeError::MissingNode(Span::default())),
+    };
+    let span = node.span;
+    match &node.info {
+        NodeInfo::Assignement => Ok(Stmt::Assignment {
+            target: designator(&node.child, node)?,
+            value: expression(expect_tree(&node.sibling, node)?)?,
+            span,
+        }),
+
+        NodeInfo::ProcedureCall(procedure) => Ok(Stmt::ProcedureCall {
+            procedure: procedure.clone(),
+            parameters: actual_parameters(&node.child)?,
+            span,
+        }),
+
+        &NodeInfo::StandardProcedureCall(procedure, _) => Ok(Stmt::StandardProcedureCall {
+            procedure,
+            parameters: actual_parameters(&node.child)?,
+            span,
+        }),
+
+        NodeInfo::IfStatement => {
+            let mut branches = vec![];
+            let mut else_branch = None;
+            let mut branch = node;
+            loop {
+                // The span of the IF branch ends with its statements, like the ones of the ELSIF branches
+                let then = expect_node(&branch.sibling, branch)?;
+                let branch_span = match branch.info {
+                    NodeInfo::IfStatement => Span { end: then.span.end, ..span },
+                    _ => branch.span,
+                };
+                branches.push(Branch {
+                    condition: expression(expect_tree(&branch.child, branch)?)?,
+                    body: statements(&then.child)?,
+                    span: branch_span,
+                });
+
+                match then.sibling.as_ref() {
+                    Tree::Node(next) if next.info == NodeInfo::Elsif => branch = next,
+                    Tree::Node(next) if next.info == NodeInfo::Else => {
+                        else_branch = Some(statements(&next.child)?);
+                        break;
+                    }
+                    Tree::Node(next) => return Err(unexpected(next)),
+                    Tree::Nil => break,
+                }
+            }
+            Ok(Stmt::If { branches, else_branch, span })
+        }
+
+        NodeInfo::WhileStatement => Ok(Stmt::While {
+            condition: expression(expect_tree(&node.child, node)?)?,
+            body: statements(&expect_node(&node.sibling, node)?.child)?,
+            span,
+        }),
+
+        NodeInfo::RepeatStatement => Ok(Stmt::Repeat {
+            body: statements(&node.child)?,
+            condition: expression(expect_tree(&node.sibling, node)?)?,
+            span,
+        }),
+
+        &NodeInfo::ForStatement(step) => {
+            let initialization = expect_node(&node.child, node)?;
+            let to = expect_node(&node.sibling, node)?;
+            let body = expect_node(&expect_node(&to.sibling, to)?.child, to)?;
+            // NOTE(pht) the parser adds the increment after the statements of the body, in the
+            // sibling ; it is left out, and generated from the step
+            Ok(Stmt::For {
+                control: designator(&initialization.child, initialization)?,
+                begin: expression(expect_tree(&initialization.sibling, initialization)?)?,
+                limit: expression(expect_tree(&to.child, to)?)?,
+                step,
+                body: statements(&body.child)?,
+                span,
+            })
+        }
+
+        NodeInfo::CaseStatement => {
+            let selector = expression(expect_tree(&node.child, node)?)?;
+            let mut cases = vec![];
+            let mut else_branch = None;
+            let mut branch = &node.sibling;
+            while let Tree::Node(branch_node) = branch.as_ref() {
+                match &branch_node.info {
+                    NodeInfo::CaseLabels(labels, _) => cases.push(Case {
+                        labels: labels.clone(),
+                        body: statements(&branch_node.child)?,
+                        span: branch_node.span,
+                    }),
+                    NodeInfo::Else => else_branch = Some(statements(&branch_node.child)?),
+                    _ => return Err(unexpected(branch_node)),
+                }
+                branch = &branch_node.sibling;
+            }
+            Ok(Stmt::Case {
+                selector,
+                cases,
+                else_branch,
+                span,
+            })
+        }
+
+        _ => Err(unexpected(node)),
+    }
+}
+
+pub fn expression(tree: &Ast) -> TreeResult<Expr> {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return Err(TreeError::MissingNode(Span::default())),
+    };
+    let span = node.span;
+    let operands = || -> TreeResult<(Box<Expr>, Box<Expr>)> {
+        let left = expression(expect_tree(&node.child, node)?)?;
+        let right = expression(expect_tree(&node.sibling, node)?)?;
+        Ok((Box::new(left), Box::new(right)))
+    };
+
+    match &node.info {
+        &NodeInfo::Constant(value) => Ok(Expr::Constant { value, span }),
+        &NodeInfo::Boolean(value) => Ok(Expr::Boolean { value, span }),
+        &NodeInfo::Char(value) => Ok(Expr::Char { value, span }),
+        NodeInfo::String(value) => Ok(Expr::String { value: value.clone(), span }),
+        &NodeInfo::Set(value) => Ok(Expr::Set { value, span }),
+        NodeInfo::SetConstructor => Ok(Expr::SetConstructor {
+            elements: set_elements(&node.child)?,
+            span,
+        }),
+        NodeInfo::Ident(_) => Ok(Expr::Designator(designator(tree, node)?)),
+        &NodeInfo::Expression(operator) => {
+            let (left, right) = operands()?;
+            Ok(Expr::Relation { operator, left, right, span })
+        }
+        &NodeInfo::SimpleExpression(operator) => {
+            let (left, right) = operands()?;
+            Ok(Expr::Addition { operator, left, right, span })
+        }
+        &NodeInfo::Term(operator) => {
+            let (left, right) = operands()?;
+            Ok(Expr::Multiplication { operator, left, right, span })
+        }
+        NodeInfo::Not => Ok(Expr::Not {
+            operand: Box::new(expression(expect_tree(&node.child, node)?)?),
+            span,
+        }),
+        NodeInfo::Negate => Ok(Expr::Negate {
+            operand: Box::new(expression(expect_tree(&node.child, node)?)?),
+            span,
+        }),
+        &NodeInfo::StandardProcedureCall(procedure, _) => Ok(Expr::StandardFunctionCall {
+            procedure,
+            parameters: actual_parameters(&node.child)?,
+            span,
+        }),
+        _ => Err(unexpected(node)),
+    }
+}
+
+fn set_elements(tree: &Ast) -> TreeResult<Vec<SetElement>> {
+    let mut elements = vec![];
+    let mut element = tree;
+    while let Tree::Node(node) = element.as_ref() {
+        if node.info != NodeInfo::SetElement {
+            return Err(unexpected(node));
+        }
+        let value = expect_node(&node.child, node)?;
+        elements.push(match value.info {
+            NodeInfo::Range => SetElement::Range {
+                low: expression(expect_tree(&value.child, value)?)?,
+                high: expression(expect_tree(&value.sibling, value)?)?,
+                span: value.span,
+            },
+            _ => SetElement::Single(expression(&node.child)?),
+        });
+        element = &node.sibling;
+    }
+    Ok(elements)
+}
+
+// Selectors are chained from the child of the variable
+fn designator(tree: &Ast, parent: &TreeNode) -> TreeResult<Designator> {
+    let node = match tree.as_ref() {
+        Tree::Node(node) => node,
+        Tree::Nil => return Err(TreeError::NotAVariable(parent.span)),
+    };
+    let symbol = match &node.info {
+        NodeInfo::Ident(symbol) => symbol.clone(),
+        _ => return Err(TreeError::NotAVariable(node.span)),
+    };
+
+    let mut selectors = vec![];
+    let mut selector = &node.child;
+    while let Tree::Node(selector_node) = selector.as_ref() {
+        selectors.push(match &selector_node.info {
+            NodeInfo::Field(name, offset) => Selector::Field {
+                name: name.clone(),
+                offset: *offset,
+                span: selector_node.span,
+            },
+            &NodeInfo::Index(element_size, capacity, _) => Selector::Index {
+                index: expression(expect_tree(&selector_node.child, selector_node)?)?,
+                element_size,
+                capacity,
+                span: selector_node.span,
+            },
+            _ => return Err(unexpected(selector_node)),
+        });
+        selector = &selector_node.sibling;
+    }
+
+    Ok(Designator {
+        symbol,
+        selectors,
+        span: node.span,
+    })
+}
+
+fn actual_parameters(tree: &Ast) -> TreeResult<Vec<Expr>> {
+    let mut parameters = vec![];
+    let mut parameter = tree;
+    while let Tree::Node(node) = parameter.as_ref() {
+        if node.info != NodeInfo::ActualParameter {
+            return Err(unexpected(node));
+        }
+        parameters.push(expression(expect_tree(&node.child, node)?)?);
+        parameter = &node.sibling;
+    }
+    Ok(parameters)
+}
+
+// Parts of a node that can not be empty are reported at their parent
+fn expect_node<'a>(tree: &'a Ast, parent: &TreeNode) -> TreeResult<&'a TreeNode> {
+    match tree.as_ref() {
+        Tree::Node(node) => Ok(node),
+        Tree::Nil => Err(TreeError::MissingNode(parent.span)),
+    }
+}
+
+fn expect_tree<'a>(tree: &'a Ast, parent: &TreeNode) -> TreeResult<&'a Ast> {
+    expect_node(tree, parent)?;
+    Ok(tree)
+}
+
+fn unexpected(node: &TreeNode) -> TreeError {
+    TreeError::UnexpectedNode(format!("{:?}", node.info), node.span)
+}
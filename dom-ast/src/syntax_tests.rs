@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast;
+    use crate::parser;
+    use crate::scanner::*;
+    use crate::scope::*;
+    use crate::syntax;
+    use crate::syntax::*;
+    use crate::tree::*;
+    use std::assert_matches::assert_matches;
+
+    fn module(content: &str) -> Module {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new(content);
+        parser::scan_next(&mut scanner).unwrap();
+        syntax::module(&parser::parse_module(&mut scanner, &scope).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_converts_declarations_and_body() {
+        let module = module("MODULE Foo; VAR x: INTEGER; PROCEDURE P; BEGIN x := 1 END P; BEGIN P; x := x + 1 END Foo.");
+
+        assert_eq!(module.name.name, "Foo");
+        assert_matches!(&module.declarations[..], [Decl::Var { var_type: VarType::Integer, .. }, Decl::Procedure { body, .. }] if body.len() == 1);
+        assert_matches!(
+            &module.body[..],
+            [
+                Stmt::ProcedureCall { parameters, .. },
+                Stmt::Assignment {
+                    value: Expr::Addition {
+                        operator: SimpleExpressionOp::Plus,
+                        ..
+                    },
+                    ..
+                }
+            ] if parameters.is_empty()
+        );
+    }
+
+    #[test]
+    fn test_converts_if_branches() {
+        let module = module("MODULE Foo; VAR x: INTEGER; BEGIN IF x = 0 THEN x := 1 ELSIF x = 1 THEN x := 2; x := 3 ELSE x := 4 END END Foo.");
+
+        let (branches, else_branch) = match &module.body[..] {
+            [Stmt::If { branches, else_branch, .. }] => (branches, else_branch),
+            other => panic!("Unexpected statements {:?}", other),
+        };
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[1].body.len(), 2);
+        assert_matches!(branches[0].condition, Expr::Relation { operator: ExpressionOp::Eql, .. });
+        assert_matches!(else_branch, Some(statements) if statements.len() == 1);
+    }
+
+    #[test]
+    fn test_leaves_out_the_increment_of_for_loops() {
+        let module = module("MODULE Foo; VAR i, s: INTEGER; BEGIN FOR i := 1 TO 10 BY 2 DO s := s + i END END Foo.");
+
+        assert_matches!(&module.body[..], [Stmt::For { step: 2, body, control, .. }] if body.len() == 1 && control.symbol.name == "i");
+    }
+
+    #[test]
+    fn test_converts_selectors() {
+        let module = module("MODULE Foo; VAR a: ARRAY 3 OF RECORD x, y: INTEGER END; i: INTEGER; BEGIN a[i].y := 1 END Foo.");
+
+        let target = match &module.body[..] {
+            [Stmt::Assignment { target, .. }] => target,
+            other => panic!("Unexpected statements {:?}", other),
+        };
+        assert_matches!(
+            &target.selectors[..],
+            [
+                Selector::Index {
+                    element_size: 2,
+                    capacity: Some(3),
+                    ..
+                },
+                Selector::Field { offset: 1, .. }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fails_on_malformed_tree() {
+        assert_matches!(syntax::statement(&ast::leaf(NodeInfo::Elsif)), Err(TreeError::UnexpectedNode(_, _)));
+        assert_matches!(syntax::expression(&ast::empty()), Err(TreeError::MissingNode(_)));
+
+        let assignment = ast::node(NodeInfo::Assignement, ast::leaf(NodeInfo::Constant(1)), ast::leaf(NodeInfo::Constant(2)));
+        assert_matches!(syntax::statement(&assignment), Err(TreeError::NotAVariable(_)));
+    }
+}
@@ -4,29 +4,35 @@ use std::rc::Rc;
 #[derive(Clone, PartialEq, Debug)]
 pub enum Token {
     Times,
+    Rdiv,
     Div,
-    // Mod,
-    // And,
+    Mod,
+    And,
     Plus,
     Minus,
-    // Or,
+    Or,
     Eql,
     Neq,
     Lss,
     Geq,
     Leq,
     Gtr,
+    In,
     Period,
+    Upto,
+    Bar,
     Comma,
     Colon,
     Rparen,
     Rbrak,
+    Rbrace,
     Of,
     Then,
     Do,
     Lparen,
     Lbrak,
-    // Not,
+    Lbrace,
+    Not,
     Becomes,
     Semicolon,
     End,
@@ -34,29 +40,47 @@ pub enum Token {
     Elsif,
     If,
     While,
+    Case,
     Array,
-    // Record,
-    // Const,
-    // Type,
+    Record,
+    Const,
+    Type,
     Var,
-    // Procedure,
+    Procedure,
     Begin,
     Module,
-    // False,
-    // True,
-    // Repeat,
-    // Until,
+    Import,
+    False,
+    True,
+    Repeat,
+    Until,
+    For,
+    To,
+    By,
     Int(u32),
+    Char(u8),       // `41X`
+    String(String), // `"abc"` ; a string of length 1 can also be used as a character
     Ident(String),
 }
 // @>scanner/tokens
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct ScanContext {
     pub line: u32,
     pub column: u32,
 }
 
+// Index of a source file among the ones given to the compiler, the first one is 0
+pub type FileId = u32;
+
+// Part of a source file, from the first character of a token to the character after another one
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: ScanContext,
+    pub end: ScanContext,
+}
+
 // <@scanner/scan
 #[derive(Clone, PartialEq, Debug)]
 pub struct Scan {
@@ -69,6 +93,9 @@ pub enum ScanErrorType {
     InvalidChar(char), // char is not ascii
     UnexpectedNewLine,
     UnterminatedComment,
+    IntegerOverflow, // Literals must fit in a signed 32-bit integer
+    InvalidCharacterCode, // `nX` literals must be below 100X
+    UnterminatedString, // Strings can not span lines
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -1,16 +1,24 @@
 use crate::scope::*;
+use crate::token::{ScanContext, Span};
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TermOp {
     Times,
     Div,
+    Mod,
+    And,
+    Intersection,        // `*` on sets
+    SymmetricDifference, // `/` on sets
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SimpleExpressionOp {
     Plus,
     Minus,
+    Or,
+    Union,      // `+` on sets
+    Difference, // `-` on sets
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -21,13 +29,39 @@ pub enum ExpressionOp {
     Leq,
     Gtr,
     Geq,
+    In,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum VarType {
     Integer,
-    Array(u32), // NOTE(pht) : this does not allow representing nested arrays, or arrays of record yet.
-                // We'll have to store the type definitions somewhere that can be accessible at runtime to allow that :/
+    Boolean,
+    Char, // Takes a whole word, like the other basic types
+    Set,  // Elements from 0 to 31, one bit each
+    Array(u32, Rc<VarType>), // Capacity and type of the elements
+    Record(Rc<Vec<Field>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub offset: usize, // In words, from the start of the record
+    pub field_type: VarType,
+}
+
+impl VarType {
+    // Number of words taken by a value of this type
+    pub fn size(&self) -> usize {
+        match self {
+            VarType::Integer | VarType::Boolean | VarType::Char | VarType::Set => 1,
+            VarType::Array(capacity, element_type) => *capacity as usize * element_type.size(),
+            VarType::Record(fields) => fields.iter().map(|field| field.field_type.size()).sum(),
+        }
+    }
+
+    pub fn is_structured(&self) -> bool {
+        matches!(self, VarType::Array(_, _) | VarType::Record(_))
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -37,18 +71,39 @@ pub enum NodeInfo {
     Declaration,
     Var,
     Type(VarType),
-    StatementSequence,
+    StatementSequence(ScanContext), // Context of the first token of the statement (in the child)
     Assignement,
-    Constant(u32),
-    Ident(Rc<Symbol>), //
+    Constant(i32),
+    Ident(Rc<Symbol>), // Selectors, if any, are chained from the child
+    Index(usize, Option<u32>, u32), // Size of the elements, capacity of the array if known, and line of the index expression (in the child)
+    Field(String, usize), // Name and offset of the field
     Term(TermOp),
     SimpleExpression(SimpleExpressionOp),
     Expression(ExpressionOp),
     IfStatement,
     Then,
     Else,
+    Elsif,
     WhileStatement,
     Do,
+    RepeatStatement,
+    ForStatement(i32), // Step of the loop
+    To,
+    CaseStatement,
+    CaseLabels(Vec<(i32, i32)>, VarType), // Ranges of labels, bounds included, and their type
+    ProcedureDeclaration(Rc<Symbol>, usize), // Size of the frame of the procedure
+    ProcedureCall(Rc<Symbol>),
+    StandardProcedureCall(StandardProcedure, u32), // Line of the call, for the traps of ASSERT and HALT
+    ActualParameter,
+    Boolean(bool),
+    Char(u8),
+    String(String), // Laid out in the data area, unless it is a single character used as a value
+    Set(u32),        // Constant set, one bit per element
+    SetConstructor,  // Elements are chained from the child
+    SetElement,      // Either an expression or a range in the child, next element in the sibling
+    Range,           // Low bound in the child, high bound in the sibling
+    Not,
+    Negate,
 }
 
 #[derive(Debug)]
@@ -56,6 +111,9 @@ pub struct TreeNode {
     pub info: NodeInfo,
     pub child: Rc<Tree>, // NOTE(pht) I wonder if those could be either Boxes. Or, If I don't want to allocate memory, a reference to a vec ?
     pub sibling: Rc<Tree>,
+    // Source of the node, without the nodes chained after it in the sibling, like the next statement
+    // of a sequence. Nodes that are not parsed from a source span nothing.
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -1,9 +1,27 @@
 // A RISC Computer.
 use crate::instructions::*;
 use log::debug;
+use std::fmt;
 
 pub const MEMORY_SIZE: usize = 4096;
 
+// Console device, reached by loading from and storing to negative addresses, like the
+// I/O devices of the original RISC board.
+// Storing prints a decimal integer, loading reads one from the input
+pub const IO_INT: i32 = -1;
+// Storing prints the character with this code
+pub const IO_CHAR: i32 = -2;
+// Storing sets the minimum width of the next printed integer
+pub const IO_WIDTH: i32 = -3;
+
+// What stopped the program, when it was not allowed to continue
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Trap {
+    pub code: TrapCode,
+    pub line: u32,
+    pub value: i32,
+}
+
 #[derive(Debug)]
 pub struct Computer {
     // Memory, represented as an array of 32-bit words ; byte-addressing is implicit
@@ -17,6 +35,60 @@ pub struct Computer {
     // Condition codes
     pub z_test: bool,
     pub neg_test: bool,
+    // Set when the last addition or subtraction overflowed, so that comparisons still hold at the extremes
+    pub overflow_test: bool,
+
+    pub trap: Option<Trap>,
+
+    pub console: Console,
+}
+
+pub struct Console {
+    // NOTE(pht) input is pulled lazily, so that stdin is only read by programs that need it
+    input: Box<dyn Iterator<Item = i32>>,
+    pub output: String,
+    width: usize,
+}
+
+impl Console {
+    pub fn set_input(&mut self, input: Box<dyn Iterator<Item = i32>>) {
+        self.input = input;
+    }
+
+    // Reading past the end of the input yields 0
+    fn read_int(&mut self) -> i32 {
+        self.input.next().unwrap_or(0)
+    }
+
+    // False when there is no device at the address
+    fn write(&mut self, adr: i32, value: i32) -> bool {
+        match adr {
+            IO_INT => {
+                self.output.push_str(&format!("{:>width$}", value, width = self.width));
+                self.width = 0;
+            }
+            IO_CHAR => self.output.push(char::from(value as u8)),
+            IO_WIDTH => self.width = value.max(0) as usize,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console {
+            input: Box::new(std::iter::empty()),
+            output: String::new(),
+            width: 0,
+        }
+    }
+}
+
+impl fmt::Debug for Console {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Console").field("output", &self.output).finish()
+    }
 }
 
 impl Computer {
@@ -27,6 +99,9 @@ impl Computer {
             pc: 0,
             z_test: false,
             neg_test: false,
+            overflow_test: false,
+            trap: None,
+            console: Console::default(),
         }
     }
 
@@ -36,8 +111,13 @@ impl Computer {
         }
     }
 
+    pub fn load_data(&mut self, address: usize, data: &[i32]) {
+        self.mem[address..address + data.len()].copy_from_slice(data);
+    }
+
     pub fn execute(&mut self, max_cycles: u32) {
         self.pc = 0;
+        self.trap = None;
 
         let mut cycles = 0;
 
@@ -73,6 +153,11 @@ impl Computer {
 
         self.execute_instruction(instruction);
 
+        if let Some(trap) = self.trap {
+            debug!("Program stopped by trap {:?}.", trap);
+            return true;
+        }
+
         if self.pc == 0 {
             debug!("Program finished succesfully.");
             return true;
@@ -84,23 +169,27 @@ impl Computer {
         match i {
             Instruction::Register { o, a, b, c } => self.execute_register(o, a, b, self.regs[c]),
             Instruction::RegisterIm { o, a, b, im } => self.execute_register(o, a, b, im),
+            Instruction::MovHigh { a, im } => self.execute_register(OpCode::MOV, a, 0, (im as i32) << 16),
             Instruction::Memory { u, a, b, offset } => self.execute_memory(u, a, b, offset),
             Instruction::Branch { cond, c, link } => self.execute_branch(cond, c, link),
             Instruction::BranchOff { cond, offset, link } => self.execute_branch_offset(cond, offset, link),
+            Instruction::Trap { cond, c, code, line } => self.execute_trap(cond, c, code, line),
         }
     }
 
     fn execute_register(&mut self, o: OpCode, a: usize, b: usize, value: i32) {
+        let mut overflow = false;
         match o {
             OpCode::MOV => {
                 self.regs[a] = value;
                 debug!("R[{}] <- {}", a, value);
             }
+            // Like the hardware, only the lowest 5 bits of the shift count are used
             OpCode::LSL => {
-                self.regs[a] = self.regs[b] << value;
+                self.regs[a] = self.regs[b].wrapping_shl(value as u32);
             }
             OpCode::ASR => {
-                self.regs[a] = self.regs[b] >> value;
+                self.regs[a] = self.regs[b].wrapping_shr(value as u32);
             }
             OpCode::ROR => {
                 if value > 0 {
@@ -121,40 +210,55 @@ impl Computer {
             OpCode::XOR => {
                 self.regs[a] = self.regs[b] ^ value;
             }
+            // NOTE(pht) like RISC5, additions and subtractions wrap around, and only record the overflow in V
             OpCode::ADD => {
                 let old_b = self.regs[b];
-                let new_a = old_b + value;
+                let new_a;
+                (new_a, overflow) = old_b.overflowing_add(value);
                 self.regs[a] = new_a;
                 debug!("R[{}] <- R[{}] ({}) + {} = {}", a, b, old_b, value, new_a);
             }
             OpCode::SUB => {
                 let old_b = self.regs[b];
-                let new_a = old_b - value;
-                self.regs[a] = self.regs[b] - value;
+                let new_a;
+                (new_a, overflow) = old_b.overflowing_sub(value);
+                self.regs[a] = new_a;
                 debug!("R[{}] <- R[{}] ({}) - {} = {}", a, b, old_b, value, new_a);
             }
             OpCode::MUL => {
-                self.regs[a] = self.regs[b] * value;
+                self.regs[a] = self.regs[b].wrapping_mul(value);
+            }
+            // Euclidean division : the remainder is never negative, so the quotient is floored when the divisor is
+            // positive, as Oberon requires, and rounded up when it is negative (7 DIV -2 = -3, 7 MOD -2 = 1)
+            OpCode::DIV | OpCode::MOD if value == 0 => {
+                // NOTE(pht) raised by the hardware, so the source line is unknown
+                self.trap = Some(Trap { code: TrapCode::DivisionByZero, line: 0, value: self.regs[b] });
+                return;
             }
             OpCode::DIV => {
-                self.regs[a] = self.regs[b] / value;
+                self.regs[a] = self.regs[b].wrapping_div_euclid(value);
             }
             OpCode::MOD => {
-                self.regs[a] = self.regs[b] % value;
+                self.regs[a] = self.regs[b].wrapping_rem_euclid(value);
             }
         }
         self.update_flags(a);
+        self.overflow_test = overflow;
     }
 
     fn execute_memory(&mut self, u: MemoryMode, a: usize, b: usize, offset: u32) {
         match u {
             MemoryMode::Load => {
                 let adr: i32 = self.regs[b] + offset as i32;
-                if adr < 0 {
-                    panic!("Attempt to load memory from negative address {}, not implemented.", adr);
+                if adr == IO_INT {
+                    self.regs[a] = self.console.read_int();
+                    debug!("R[{}] <- input {}", a, self.regs[a]);
+                    self.update_flags(a);
+                    return;
                 }
-                if adr > MEMORY_SIZE as i32 {
-                    panic!("Attempt to load memory from address {}, bigger than computer memory.", adr)
+                if adr < 0 || adr >= MEMORY_SIZE as i32 {
+                    self.invalid_address(adr);
+                    return;
                 }
 
                 let value = self.mem[adr as usize];
@@ -165,10 +269,15 @@ impl Computer {
             MemoryMode::Store => {
                 let adr: i32 = self.regs[b] + offset as i32;
                 if adr < 0 {
-                    panic!("Attempt to store data at negative address {}, not implemented.", adr);
+                    debug!("Output {} to device {}", self.regs[a], adr);
+                    if !self.console.write(adr, self.regs[a]) {
+                        self.invalid_address(adr);
+                    }
+                    return;
                 }
-                if adr > MEMORY_SIZE as i32 {
-                    panic!("Attempt to store data at address {}, bigger than computer memory.", adr)
+                if adr >= MEMORY_SIZE as i32 {
+                    self.invalid_address(adr);
+                    return;
                 }
 
                 debug!("M[R[{}] + {}] = M[{} + {}] = M[{}] <- R[{}] = {}", b, offset, self.regs[b], offset, adr, a, self.regs[a]);
@@ -178,6 +287,11 @@ impl Computer {
         }
     }
 
+    // NOTE(pht) raised by the hardware, like the division by zero, so the source line is unknown
+    fn invalid_address(&mut self, adr: i32) {
+        self.trap = Some(Trap { code: TrapCode::InvalidAddress, line: 0, value: adr });
+    }
+
     fn execute_branch(&mut self, cond: BranchCondition, c: usize, link: bool) {
         if self.matches_cond(cond) {
             if link {
@@ -191,6 +305,7 @@ impl Computer {
         debug!("Testing if condition {:?} matches", cond);
         debug!("Self.neg_test {:?}?", self.neg_test);
         debug!("Self.z_test {:?}?", self.z_test);
+        debug!("Self.overflow_test {:?}?", self.overflow_test);
         if self.matches_cond(cond) {
             if link {
                 self.regs[15] = self.pc as i32;
@@ -200,24 +315,33 @@ impl Computer {
         }
     }
 
+    fn execute_trap(&mut self, cond: BranchCondition, c: usize, code: TrapCode, line: u32) {
+        if self.matches_cond(cond) {
+            self.trap = Some(Trap { code, line, value: self.regs[c] });
+        }
+    }
+
     pub fn matches_cond(&self, cond: BranchCondition) -> bool {
         match cond {
             BranchCondition::MI => self.neg_test,
             BranchCondition::EQ => self.z_test,
-            BranchCondition::LT => self.neg_test,
-            BranchCondition::LE => self.neg_test || self.z_test,
+            // Comparisons are subtractions, whose sign is wrong when they overflow
+            BranchCondition::LT => self.neg_test != self.overflow_test,
+            BranchCondition::LE => self.neg_test != self.overflow_test || self.z_test,
             BranchCondition::AW => true,
             BranchCondition::PL => !self.neg_test,
             BranchCondition::NE => !self.z_test,
-            BranchCondition::GE => !self.neg_test,
-            BranchCondition::GT => !(self.neg_test || self.z_test),
+            BranchCondition::GE => self.neg_test == self.overflow_test,
+            BranchCondition::GT => !(self.neg_test != self.overflow_test || self.z_test),
             BranchCondition::NV => false,
         }
     }
 
+    // Only additions and subtractions can overflow
     pub fn update_flags(&mut self, a: usize) {
         self.z_test = self.regs[a] == 0;
         self.neg_test = self.regs[a] < 0;
+        self.overflow_test = false;
     }
 }
 
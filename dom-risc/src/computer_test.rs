@@ -6,6 +6,7 @@ mod tests {
     use crate::instructions::Instruction::*;
     use crate::instructions::MemoryMode;
     use crate::instructions::OpCode::*;
+    use crate::instructions::TrapCode;
 
     fn exec(c: &mut Computer, i: Instruction) {
         c.execute_instruction(i);
@@ -32,6 +33,13 @@ mod tests {
         assert_eq!(0, c.regs[0]);
         assert!(c.z_test);
         assert!(!c.neg_test);
+
+        // MOV' R.a := im << 16
+        exec(&mut c, MovHigh { a: 0, im: 0xFFFE });
+        assert_eq!(-0x20000, c.regs[0]);
+        assert!(c.neg_test);
+        exec(&mut c, RegisterIm { o: IOR, a: 0, b: 0, im: 0x7960 });
+        assert_eq!(-100000, c.regs[0]);
     }
 
     #[test]
@@ -147,9 +155,76 @@ mod tests {
         exec(&mut c, Register { o: MOD, a: 0, b: 1, c: 2 });
         assert_eq!(1, c.regs[0]);
 
+        // Quotient is floored, remainder is not negative
+        c.regs[1] = -7;
+        c.regs[2] = 2;
+        exec(&mut c, Register { o: DIV, a: 0, b: 1, c: 2 });
+        assert_eq!(-4, c.regs[0]);
+        exec(&mut c, Register { o: MOD, a: 0, b: 1, c: 2 });
+        assert_eq!(1, c.regs[0]);
+
+        // With a negative divisor, the remainder is still not negative, so the quotient is not floored
+        for (dividend, quotient, remainder) in [(7, -3, 1), (-7, 4, 1), (6, -3, 0)] {
+            c.regs[1] = dividend;
+            c.regs[2] = -2;
+            exec(&mut c, Register { o: DIV, a: 0, b: 1, c: 2 });
+            assert_eq!(quotient, c.regs[0], "{} DIV -2", dividend);
+            exec(&mut c, Register { o: MOD, a: 0, b: 1, c: 2 });
+            assert_eq!(remainder, c.regs[0], "{} MOD -2", dividend);
+        }
+
         // I'm a bit lazy, and I trust my implementation for the "imediate" part ;)
     }
 
+    #[test]
+    fn test_arithmetic_wraps_around() {
+        let mut c = Computer::new();
+        c.regs[1] = i32::MAX;
+        exec(&mut c, RegisterIm { o: ADD, a: 0, b: 1, im: 1 });
+        assert_eq!(i32::MIN, c.regs[0]);
+        assert!(c.neg_test);
+        assert!(c.overflow_test);
+
+        exec(&mut c, RegisterIm { o: SUB, a: 0, b: 0, im: 1 });
+        assert_eq!(i32::MAX, c.regs[0]);
+        assert!(!c.neg_test);
+        assert!(c.overflow_test);
+
+        exec(&mut c, RegisterIm { o: SUB, a: 0, b: 0, im: 1 });
+        assert!(!c.overflow_test);
+
+        c.regs[1] = 100000;
+        exec(&mut c, Register { o: MUL, a: 0, b: 1, c: 1 });
+        assert_eq!(1410065408, c.regs[0]);
+        assert!(!c.overflow_test);
+    }
+
+    #[test]
+    fn test_comparisons_hold_when_subtraction_overflows() {
+        let mut c = Computer::new();
+        // MAX > -1
+        c.regs[1] = i32::MAX;
+        exec(&mut c, RegisterIm { o: SUB, a: 0, b: 1, im: -1 });
+        assert!(c.matches_cond(GT));
+        assert!(c.matches_cond(GE));
+        assert!(!c.matches_cond(LT));
+        assert!(!c.matches_cond(LE));
+
+        // MIN < 1
+        c.regs[1] = i32::MIN;
+        exec(&mut c, RegisterIm { o: SUB, a: 0, b: 1, im: 1 });
+        assert!(c.matches_cond(LT));
+        assert!(c.matches_cond(LE));
+        assert!(!c.matches_cond(GT));
+        assert!(!c.matches_cond(GE));
+
+        // MIN < MAX
+        c.regs[2] = i32::MAX;
+        exec(&mut c, Register { o: SUB, a: 0, b: 1, c: 2 });
+        assert!(c.matches_cond(LT));
+        assert!(!c.matches_cond(GE));
+    }
+
     #[test]
     fn test_execute_memory_instruction() {
         let mut c = Computer::new();
@@ -309,6 +384,120 @@ mod tests {
         assert_eq!(c.pc, 0);
     }
 
+    #[test]
+    fn test_program_execution_stops_on_trap() {
+        let instructions = vec![
+            RegisterIm { o: MOV, a: 0, b: 0, im: 7 },
+            RegisterIm { o: SUB, a: 1, b: 0, im: 8 },
+            Trap {
+                cond: GE,
+                c: 0,
+                code: TrapCode::ArrayIndexOutOfBounds,
+                line: 3,
+            },
+            RegisterIm { o: SUB, a: 1, b: 0, im: 5 },
+            Trap {
+                cond: GE,
+                c: 0,
+                code: TrapCode::ArrayIndexOutOfBounds,
+                line: 4,
+            },
+            RegisterIm { o: MOV, a: 1, b: 0, im: 42 },
+        ];
+
+        let mut c = Computer::new();
+        c.load_instructions(instructions);
+        c.execute(50);
+
+        assert_eq!(
+            c.trap,
+            Some(crate::computer::Trap {
+                code: TrapCode::ArrayIndexOutOfBounds,
+                line: 4,
+                value: 7
+            })
+        );
+        assert_eq!(c.regs[1], 2);
+        assert_eq!(c.pc, 5);
+    }
+
+    #[test]
+    fn test_division_by_zero_traps() {
+        let instructions = vec![
+            RegisterIm { o: MOV, a: 0, b: 0, im: 7 },
+            RegisterIm { o: DIV, a: 1, b: 0, im: 0 },
+            RegisterIm { o: MOV, a: 1, b: 0, im: 42 },
+        ];
+
+        let mut c = Computer::new();
+        c.load_instructions(instructions);
+        c.execute(50);
+
+        assert_eq!(
+            c.trap,
+            Some(crate::computer::Trap {
+                code: TrapCode::DivisionByZero,
+                line: 0,
+                value: 7
+            })
+        );
+        assert_eq!(0, c.regs[1]);
+    }
+
+    #[test]
+    fn test_invalid_addresses_trap() {
+        // Loads and stores without a device, or out of memory
+        for (u, adr) in [(MemoryMode::Load, -2), (MemoryMode::Store, -7), (MemoryMode::Load, 4096), (MemoryMode::Store, 5000)] {
+            let instructions = vec![
+                RegisterIm { o: MOV, a: 0, b: 0, im: adr },
+                Memory { u, a: 1, b: 0, offset: 0 },
+                RegisterIm { o: MOV, a: 1, b: 0, im: 42 },
+            ];
+
+            let mut c = Computer::new();
+            c.load_instructions(instructions);
+            c.execute(50);
+
+            assert_eq!(
+                c.trap,
+                Some(crate::computer::Trap {
+                    code: TrapCode::InvalidAddress,
+                    line: 0,
+                    value: adr
+                }),
+                "{:?} at {}",
+                u,
+                adr
+            );
+            assert_eq!(0, c.regs[1]);
+        }
+    }
+
+    #[test]
+    fn test_console_is_mapped_at_negative_addresses() {
+        let instructions = vec![
+            RegisterIm { o: MOV, a: 0, b: 0, im: -1 },
+            Memory { u: MemoryMode::Load, a: 1, b: 0, offset: 0 },
+            RegisterIm { o: ADD, a: 1, b: 1, im: 1 },
+            RegisterIm { o: MOV, a: 2, b: 0, im: 4 },
+            Memory { u: MemoryMode::Store, a: 2, b: 0, offset: 0 },
+            RegisterIm { o: MOV, a: 0, b: 0, im: -3 },
+            Memory { u: MemoryMode::Store, a: 2, b: 0, offset: 0 },
+            RegisterIm { o: MOV, a: 0, b: 0, im: -1 },
+            Memory { u: MemoryMode::Store, a: 1, b: 0, offset: 0 },
+            RegisterIm { o: MOV, a: 0, b: 0, im: -2 },
+            RegisterIm { o: MOV, a: 2, b: 0, im: 10 },
+            Memory { u: MemoryMode::Store, a: 2, b: 0, offset: 0 },
+        ];
+
+        let mut c = Computer::new();
+        c.console.set_input(Box::new(vec![41].into_iter()));
+        c.load_instructions(instructions);
+        c.execute(50);
+
+        assert_eq!(c.console.output, "4  42\n");
+    }
+
     #[test]
     fn test_assembled_program() {
         // NOTE(pht) this is the same program as show in `assembler_test.rs`.
@@ -41,13 +41,29 @@ pub enum BranchCondition {
     NV = 15, // Never
 }
 
+// Traps keep the line they come from in 16 bits, lines past the last one that fits are all encoded as it
+pub const MAX_TRAP_LINE: u32 = 0xFFFF;
+
+// Reasons for a program to be stopped by a trap
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TrapCode {
+    ArrayIndexOutOfBounds = 1,
+    DivisionByZero = 2,
+    AssertionFailed = 3,
+    Halt = 4,           // R.c holds the code given to HALT
+    CaseNotMatched = 5, // R.c holds the selector of the CASE
+    InvalidAddress = 6, // Raised by the computer, for addresses out of memory without a device
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Instruction {
     Register { a: usize, b: usize, o: OpCode, c: usize },
     RegisterIm { a: usize, b: usize, o: OpCode, im: i32 },        // 'v' modified is implied by im > 0 or im < 0, I suppose ?
+    MovHigh { a: usize, im: u16 },                                 // MOV' in the book : R.a = im << 16, the 'u' modifier set on a MOV
     Memory { a: usize, b: usize, offset: u32, u: MemoryMode }, // memory offsets are positive, since they're mostly here to compute displacements from a known location - eg, a[i] if a is an array
     Branch { cond: BranchCondition, c: usize, link: bool },    // I don't understand u/v enough to give them better names, yet
     BranchOff { cond: BranchCondition, offset: i32, link: bool }, // I don't understand u/v enough to give them better names, yet
+    Trap { cond: BranchCondition, c: usize, code: TrapCode, line: u32 }, // Encoded as a branch with link, using the bits left unused by the register ; R.c holds the offending value
 }
 
 #[derive(Debug)]
@@ -55,6 +71,7 @@ pub enum InstructionParseError {
     InvalidInstruction(u32),
     InvalidOpCode(u8),
     InvalidBranchCondition(u32),
+    InvalidTrapCode(u32),
 }
 
 impl Instruction {
@@ -62,9 +79,11 @@ impl Instruction {
         match i {
             Instruction::Register { o, a, b, c } => Instruction::encode_register(*o, *a, *b, *c),
             Instruction::RegisterIm { o, a, b, im } => Instruction::encode_register_im(*o, *a, *b, *im),
+            Instruction::MovHigh { a, im } => Instruction::encode_mov_high(*a, *im),
             Instruction::Memory { a, b, offset, u } => Instruction::encode_memory(*a, *b, *offset, *u),
             Instruction::Branch { cond, c, link } => Instruction::encode_branch(*cond, *c, *link),
             Instruction::BranchOff { cond, offset, link } => Instruction::encode_branch_offset(*cond, *offset, *link),
+            Instruction::Trap { cond, c, code, line } => Instruction::encode_trap(*cond, *c, *code, *line),
         }
     }
 
@@ -90,6 +109,11 @@ impl Instruction {
             | (v as u32) << (32 - 4)
     }
 
+    fn encode_mov_high(a: usize, im: u16) -> u32 {
+        //  0110(4) a(4) 0000(4) [MOV](4) im(16)
+        0b0110_0000_0000_0000_0000_0000_0000_0000 | (im as u32) | (OpCode::MOV as u32) << (4 + 12) | (a as u32) << (4 + 4 + 12 + 4)
+    }
+
     fn encode_memory(a: usize, b: usize, offset: u32, u: MemoryMode) -> u32 {
         let mut uv = 0b1000;
         if u == MemoryMode::Store {
@@ -108,6 +132,11 @@ impl Instruction {
         (c as u32) | (cond as u32) << (20 + 4) | (uv as u32) << (32 - 4)
     }
 
+    fn encode_trap(cond: BranchCondition, c: usize, code: TrapCode, line: u32) -> u32 {
+        // 1101(4) cond(4) line(16) code(4) c(4)
+        Instruction::encode_branch(cond, c, true) | line.min(MAX_TRAP_LINE) << 8 | (code as u32) << 4
+    }
+
     fn encode_branch_offset(cond: BranchCondition, offset: i32, link: bool) -> u32 {
         let mut uv = 0b1110;
         if link {
@@ -145,6 +174,12 @@ impl Instruction {
         let o = Instruction::parse_op_code(op)?;
         if ((i / 0x40000000) % 2) == 0 {
             Ok(Instruction::Register { a, b, o, c })
+        } else if (i / 0x20000000) % 2 == 1 {
+            // Only MOV has a meaning for the 'u' modifier at the moment
+            match o {
+                OpCode::MOV => Ok(Instruction::MovHigh { a, im: im as u16 }),
+                _ => Err(InstructionParseError::InvalidInstruction(i)),
+            }
         } else if (i / 0x10000000) % 2 == 0 {
             Ok(Instruction::RegisterIm { a, b, o, im })
         } else {
@@ -188,9 +223,29 @@ impl Instruction {
         let cond = Instruction::parse_cond(a)?;
         let c = (i % 0x10) as usize;
         let link = (i & 0b0001_0000_0000_0000_0000_0000_0000_0000) > 0;
+
+        // Regular branches never use the bits between the condition and the register
+        let code = (i / 0x10) % 0x10;
+        if link && code != 0 {
+            let code = Instruction::parse_trap_code(code)?;
+            let line = (i / 0x100) % 0x10000;
+            return Ok(Instruction::Trap { cond, c, code, line });
+        }
         Ok(Instruction::Branch { cond, c, link })
     }
 
+    fn parse_trap_code(code: u32) -> Result<TrapCode, InstructionParseError> {
+        match code {
+            1 => Ok(TrapCode::ArrayIndexOutOfBounds),
+            2 => Ok(TrapCode::DivisionByZero),
+            3 => Ok(TrapCode::AssertionFailed),
+            4 => Ok(TrapCode::Halt),
+            5 => Ok(TrapCode::CaseNotMatched),
+            6 => Ok(TrapCode::InvalidAddress),
+            _ => Err(InstructionParseError::InvalidTrapCode(code)),
+        }
+    }
+
     fn parse_branch_offset(i: u32) -> Result<Instruction, InstructionParseError> {
         let a = ((i / 0x1000000) % 0x10) as usize;
         let cond = Instruction::parse_cond(a)?;
@@ -236,6 +291,19 @@ impl Instruction {
         bincode::serialize(&instruction_bits).unwrap()
     }
 
+    // Instructions followed by raw data words, as they are laid out in memory
+    pub fn serialize_image(instructions: Vec<Instruction>, data: &[i32]) -> Vec<u8> {
+        let mut words: Vec<u32> = instructions.iter().map(Instruction::encode).collect();
+        words.extend(data.iter().map(|word| *word as u32));
+        bincode::serialize(&words).unwrap()
+    }
+
+    // Words of an image, to be loaded at address 0
+    pub fn deserialize_image(bytes: &[u8]) -> Vec<i32> {
+        let words: Vec<u32> = bincode::deserialize_from(bytes).unwrap();
+        words.iter().map(|word| *word as i32).collect()
+    }
+
     pub fn deserialize_all(bytes: &[u8]) -> Vec<Instruction> {
         let instructions_bits: Vec<u32> = bincode::deserialize_from(bytes).unwrap();
         return instructions_bits.iter().map(|i: &u32| Instruction::parse(*i).unwrap()).collect();
@@ -279,6 +347,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mov_high() {
+        assert_both(Instruction::MovHigh { a: 3, im: 0xFFFE }, 0b0110_0011_0000_0000_1111_1111_1111_1110);
+    }
+
     #[test]
     fn test_memory() {
         assert_both(
@@ -304,6 +377,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_trap() {
+        assert_both(
+            Instruction::Trap {
+                cond: BranchCondition::GE,
+                c: 2,
+                code: TrapCode::ArrayIndexOutOfBounds,
+                line: 12,
+            },
+            0b1101_1101_0000_0000_0000_1100_0001_0010,
+        );
+    }
+
+    #[test]
+    fn test_trap_saturates_line() {
+        let trap = Instruction::Trap {
+            cond: BranchCondition::AW,
+            c: 0,
+            code: TrapCode::AssertionFailed,
+            line: 70000,
+        };
+        assert_matches!(Instruction::parse(Instruction::encode(&trap)), Ok(Instruction::Trap { line: MAX_TRAP_LINE, code: TrapCode::AssertionFailed, .. }));
+    }
+
     #[test]
     fn test_branch_off_positive() {
         assert_both(
@@ -368,8 +465,7 @@ mod tests {
     #[test]
     fn test_serde() {
         let instruction = Instruction::Register { o: OpCode::MOV, a: 2, b: 5, c: 1 };
-        let mut instructions = vec![];
-        instructions.push(instruction);
+        let instructions = vec![instruction];
         let serialized = Instruction::serialize_all(instructions);
         let deserialized = Instruction::deserialize_all(&serialized[..]);
         assert_eq!(Instruction::encode(&instruction), Instruction::encode(&(deserialized[0])));
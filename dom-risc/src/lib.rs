@@ -2,3 +2,4 @@
 pub mod computer;
 pub mod computer_test;
 pub mod instructions;
+pub mod object;
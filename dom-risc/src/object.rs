@@ -0,0 +1,323 @@
+// Relocatable object files, produced by the compiler and the assembler, and combined by the linker.
+//
+// An object is serialized as a bincode `Vec<u32>`, like images, with the sections following each other:
+//
+//   header      MAGIC VERSION name entry body
+//   code        count, one encoded instruction per word
+//   data        count, one word each
+//   variables   size of the static area, that is not initialized
+//   exports     count, then name section offset
+//   imports     count, then module name
+//   relocations count, then index kind target value
+//
+// Names are written as their length followed by one character per word, like strings in the data area.
+use crate::instructions::{Instruction, InstructionParseError};
+
+// "RSC0"
+pub const MAGIC: u32 = 0x5253_4330;
+pub const VERSION: u32 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Section {
+    Code = 0,
+    Data = 1,
+    Variables = 2,
+}
+
+// A symbol defined by the object, at some offset of one of its sections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    pub section: Section,
+    pub offset: usize,
+}
+
+// A symbol of another object, found by the linker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelocationKind {
+    // The immediate value of a `MOV` becomes the address of the target
+    Absolute = 0,
+    // The offset of a `BranchOff` becomes the distance to the target
+    Branch = 1,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelocationTarget {
+    Code(usize),
+    Data(usize),
+    Variables(usize),
+    // Index in the imports of the object
+    Import(usize),
+}
+
+// An instruction of the code section to fix once the final address of its target is known
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    pub index: usize,
+    pub kind: RelocationKind,
+    pub target: RelocationTarget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    pub name: String,
+    // Offset in the code of the first instruction to execute
+    pub entry: usize,
+    // The code at the entry is a module body, that returns to its caller. The linker runs the bodies of
+    // imported modules before the first object.
+    pub body: bool,
+    pub code: Vec<Instruction>,
+    pub data: Vec<i32>,
+    // Module variables, addressed through the static base
+    pub variables: usize,
+    pub exports: Vec<ObjectSymbol>,
+    pub imports: Vec<Import>,
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug)]
+pub enum ObjectError {
+    InvalidMagic(u32),
+    UnsupportedVersion(u32),
+    Truncated,
+    InvalidInstruction(InstructionParseError),
+    InvalidValue(u32),
+}
+
+impl Object {
+    pub fn new(name: &str) -> Object {
+        Object {
+            name: String::from(name),
+            entry: 0,
+            body: false,
+            code: vec![],
+            data: vec![],
+            variables: 0,
+            exports: vec![],
+            imports: vec![],
+            relocations: vec![],
+        }
+    }
+
+    // Index of the import, added if the object does not refer to the symbol yet
+    pub fn import(&mut self, module: &str, name: &str) -> usize {
+        match self.imports.iter().position(|import| import.module == module && import.name == name) {
+            Some(index) => index,
+            None => {
+                self.imports.push(Import {
+                    module: String::from(module),
+                    name: String::from(name),
+                });
+                self.imports.len() - 1
+            }
+        }
+    }
+
+    pub fn export(&self, name: &str) -> Option<&ObjectSymbol> {
+        self.exports.iter().find(|symbol| symbol.name == name)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut words = vec![MAGIC, VERSION];
+        push_name(&mut words, &self.name);
+        words.push(self.entry as u32);
+        words.push(self.body as u32);
+
+        words.push(self.code.len() as u32);
+        words.extend(self.code.iter().map(Instruction::encode));
+        words.push(self.data.len() as u32);
+        words.extend(self.data.iter().map(|word| *word as u32));
+        words.push(self.variables as u32);
+
+        words.push(self.exports.len() as u32);
+        for symbol in self.exports.iter() {
+            push_name(&mut words, &symbol.name);
+            words.push(symbol.section as u32);
+            words.push(symbol.offset as u32);
+        }
+
+        words.push(self.imports.len() as u32);
+        for import in self.imports.iter() {
+            push_name(&mut words, &import.module);
+            push_name(&mut words, &import.name);
+        }
+
+        words.push(self.relocations.len() as u32);
+        for relocation in self.relocations.iter() {
+            let (target, value) = match relocation.target {
+                RelocationTarget::Code(offset) => (0, offset),
+                RelocationTarget::Data(offset) => (1, offset),
+                RelocationTarget::Variables(offset) => (2, offset),
+                RelocationTarget::Import(index) => (3, index),
+            };
+            words.extend([relocation.index as u32, relocation.kind as u32, target, value as u32]);
+        }
+
+        bincode::serialize(&words).unwrap()
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Object, ObjectError> {
+        let words: Vec<u32> = bincode::deserialize(bytes).map_err(|_| ObjectError::Truncated)?;
+        let mut words = words.into_iter();
+
+        let magic = next_word(&mut words)?;
+        if magic != MAGIC {
+            return Err(ObjectError::InvalidMagic(magic));
+        }
+        let version = next_word(&mut words)?;
+        if version != VERSION {
+            return Err(ObjectError::UnsupportedVersion(version));
+        }
+        let mut object = Object::new(&next_name(&mut words)?);
+        object.entry = next_word(&mut words)? as usize;
+        object.body = match next_word(&mut words)? {
+            0 => false,
+            1 => true,
+            other => return Err(ObjectError::InvalidValue(other)),
+        };
+
+        for _ in 0..next_word(&mut words)? {
+            let instruction = Instruction::parse(next_word(&mut words)?).map_err(ObjectError::InvalidInstruction)?;
+            object.code.push(instruction);
+        }
+        for _ in 0..next_word(&mut words)? {
+            object.data.push(next_word(&mut words)? as i32);
+        }
+        object.variables = next_word(&mut words)? as usize;
+
+        for _ in 0..next_word(&mut words)? {
+            let name = next_name(&mut words)?;
+            let section = match next_word(&mut words)? {
+                0 => Section::Code,
+                1 => Section::Data,
+                2 => Section::Variables,
+                other => return Err(ObjectError::InvalidValue(other)),
+            };
+            let offset = next_word(&mut words)? as usize;
+            object.exports.push(ObjectSymbol { name, section, offset });
+        }
+
+        for _ in 0..next_word(&mut words)? {
+            let module = next_name(&mut words)?;
+            let name = next_name(&mut words)?;
+            object.imports.push(Import { module, name });
+        }
+
+        for _ in 0..next_word(&mut words)? {
+            let index = next_word(&mut words)? as usize;
+            let kind = match next_word(&mut words)? {
+                0 => RelocationKind::Absolute,
+                1 => RelocationKind::Branch,
+                other => return Err(ObjectError::InvalidValue(other)),
+            };
+            let target = match (next_word(&mut words)?, next_word(&mut words)? as usize) {
+                (0, offset) => RelocationTarget::Code(offset),
+                (1, offset) => RelocationTarget::Data(offset),
+                (2, offset) => RelocationTarget::Variables(offset),
+                (3, index) => RelocationTarget::Import(index),
+                (other, _) => return Err(ObjectError::InvalidValue(other)),
+            };
+            object.relocations.push(Relocation { index, kind, target });
+        }
+
+        Ok(object)
+    }
+}
+
+fn push_name(words: &mut Vec<u32>, name: &str) {
+    words.push(name.len() as u32);
+    words.extend(name.bytes().map(|c| c as u32));
+}
+
+fn next_word(words: &mut impl Iterator<Item = u32>) -> Result<u32, ObjectError> {
+    words.next().ok_or(ObjectError::Truncated)
+}
+
+fn next_name(words: &mut impl Iterator<Item = u32>) -> Result<String, ObjectError> {
+    let mut name = String::new();
+    for _ in 0..next_word(words)? {
+        let c = next_word(words)?;
+        name.push(char::from_u32(c).ok_or(ObjectError::InvalidValue(c))?);
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::instructions::*;
+
+    #[test]
+    fn can_read_serialized_objects() {
+        let mut object = Object::new("Counters");
+        object.body = true;
+        object.code = vec![
+            Instruction::RegisterIm {
+                o: OpCode::MOV,
+                a: 0,
+                b: 0,
+                im: 0,
+            },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
+                offset: 0,
+            },
+        ];
+        object.data = vec![72, -1, 0];
+        object.variables = 4;
+        object.exports.push(ObjectSymbol {
+            name: String::from("Add"),
+            section: Section::Code,
+            offset: 1,
+        });
+        object.exports.push(ObjectSymbol {
+            name: String::from("total"),
+            section: Section::Variables,
+            offset: 2,
+        });
+        let import = object.import("Out", "Int");
+        assert_eq!(object.import("Out", "Int"), import);
+        object.relocations = vec![
+            Relocation {
+                index: 0,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Data(1),
+            },
+            Relocation {
+                index: 0,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Variables(2),
+            },
+            Relocation {
+                index: 1,
+                kind: RelocationKind::Branch,
+                target: RelocationTarget::Import(import),
+            },
+        ];
+
+        let deserialized = Object::deserialize(&object.serialize()).unwrap();
+        assert_eq!(deserialized, object);
+        assert_eq!(deserialized.export("Add").map(|symbol| symbol.offset), Some(1));
+    }
+
+    #[test]
+    fn fails_on_invalid_objects() {
+        let image = Instruction::serialize_all(vec![Instruction::Register { o: OpCode::MOV, a: 2, b: 5, c: 1 }]);
+        assert_matches!(Object::deserialize(&image), Err(ObjectError::InvalidMagic(_)));
+
+        let newer = bincode::serialize(&vec![MAGIC, VERSION + 1]).unwrap();
+        assert_matches!(Object::deserialize(&newer), Err(ObjectError::UnsupportedVersion(version)) if version == VERSION + 1);
+
+        let serialized = Object::new("Empty").serialize();
+        assert_matches!(Object::deserialize(&serialized[..serialized.len() - 4]), Err(ObjectError::Truncated));
+    }
+}
@@ -47,6 +47,7 @@ pub struct Assembler {
     pub instructions: Vec<Instruction>,
     pub instruction_indexes: HashMap<String, i32>, // Map of symbols like @BAR to instruction indices
     pub symbols: HashMap<String, u32>,             // Map of symbols like #FOO to their values
+    pub externals: Vec<(usize, String, String)>,   // Branches to labels of other modules, like @Runtime.Double
     pub line_index: u32,
 }
 
@@ -81,6 +82,7 @@ impl Assembler {
             instructions: vec![],
             symbols,
             instruction_indexes: HashMap::new(),
+            externals: vec![],
             line_index: 0,
         }
     }
@@ -275,7 +277,7 @@ impl Assembler {
         s.parse::<i32>()
     }
 
-    fn parse_branch_offset(&self, instruction_index: u32, s: &str) -> Result<i32, std::num::ParseIntError> {
+    fn parse_branch_offset(&mut self, instruction_index: u32, s: &str) -> Result<i32, std::num::ParseIntError> {
         if let Some(param_instruction_index) = self.instruction_indexes.get(s) {
             let offset: i32 = param_instruction_index - instruction_index as i32;
             if offset < 0 {
@@ -287,6 +289,11 @@ impl Assembler {
         if let Some(&symbol) = self.symbols.get(s) {
             return Ok(symbol as i32);
         }
+        // The offset is left to 0 until the modules are linked
+        if let Some((module, name)) = s.strip_prefix('@').and_then(|label| label.split_once('.')) {
+            self.externals.push((instruction_index as usize, module.to_string(), name.to_string()));
+            return Ok(0);
+        }
         s.parse::<i32>()
     }
 
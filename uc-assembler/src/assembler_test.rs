@@ -176,4 +176,20 @@ mod tests {
             assert_eq!(*expected, parsed.unwrap(), "Invalid translation for {}", line);
         }
     }
+
+    #[test]
+    fn it_records_branches_to_other_modules() {
+        let mut a = Assembler::new();
+        a.parse_line(0, "MOV R0,1").unwrap();
+        let parsed = a.parse_instruction("BL @Runtime.Double");
+        assert_eq!(
+            Ok(BranchOff {
+                cond: BranchCondition::AW,
+                offset: 0,
+                link: true,
+            }),
+            parsed
+        );
+        assert_eq!(a.externals, vec![(1, "Runtime".to_string(), "Double".to_string())]);
+    }
 }
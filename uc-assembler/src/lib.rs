@@ -8,6 +8,7 @@ pub use crate::assembler::AssembleError;
 
 use crate::assembler::*;
 use risc::instructions::*;
+use risc::object::{Object, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, Section};
 
 pub fn assemble(input: &str) -> Result<Vec<Instruction>, AssembleError> {
     let mut assembler = Assembler::new();
@@ -17,3 +18,31 @@ pub fn assemble(input: &str) -> Result<Vec<Instruction>, AssembleError> {
         Err(err) => Err(err),
     }
 }
+
+// Every label is exported, and branches to labels like @Runtime.Double are relocated by the linker
+pub fn assemble_object(name: &str, input: &str) -> Result<Object, AssembleError> {
+    let mut assembler = Assembler::new();
+    assembler.assemble(input)?;
+
+    let mut object = Object::new(name);
+    object.code = assembler.instructions;
+    object.exports = assembler
+        .instruction_indexes
+        .iter()
+        .map(|(label, index)| ObjectSymbol {
+            name: label.trim_start_matches('@').to_string(),
+            section: Section::Code,
+            offset: *index as usize,
+        })
+        .collect();
+    object.exports.sort_by_key(|symbol| symbol.offset);
+    for (index, module, name) in assembler.externals.iter() {
+        let import = object.import(module, name);
+        object.relocations.push(Relocation {
+            index: *index,
+            kind: RelocationKind::Branch,
+            target: RelocationTarget::Import(import),
+        });
+    }
+    Ok(object)
+}
@@ -1,427 +1,1579 @@
-use ast::ast::{child, info, sibling, Ast};
-use ast::tree::{ExpressionOp, NodeInfo, SimpleExpressionOp, TermOp, Tree, TreeNode};
+use std::rc::Rc;
+
+use ast::scope::{StandardProcedure, Symbol, SymbolKind};
+use ast::syntax::{Case, Decl, Designator, Expr, Module, Selector, SetElement, Stmt};
+use ast::token::ScanContext;
+use ast::tree::{ExpressionOp, SimpleExpressionOp, TermOp};
+use risc::computer::{IO_CHAR, IO_INT, IO_WIDTH};
 use risc::instructions::OpCode::*;
 use risc::instructions::*;
+use risc::object::{Relocation, RelocationKind, RelocationTarget};
+
+use crate::error::{CodegenError, CompileError};
+
+// Calling convention:
+// - parameters are passed in registers, starting at R0 (addresses for VAR parameters)
+// - BL puts the return address in R15
+// - the callee allocates its frame below the caller's by decrementing R14,
+//   and saves the return address and the parameters in its frame
+// - module variables are in a static area after the data, reached through R13 (the static base),
+//   that is set by the module body, by exported procedures, and after calls to imported procedures
+// - the module body is called by the linked image, and keeps its return address in the first word
+//   of the static area
+const SB: usize = 13;
+const FP: usize = 14;
+const LNK: usize = 15;
+
+// CASE statements use a jump table if labels cover at least half of the range between
+// the lowest and highest label, and a chain of comparisons otherwise.
+const MIN_JUMP_TABLE_LABELS: i64 = 3;
+const MAX_JUMP_TABLE_SIZE: i64 = 256;
+
+// Wirth-style condition item. The code of a condition leaves the flags set, and `cond`
+// holds when the condition is true. The chains contain the branches already emitted
+// (by `&` and `OR`) that still have to be fixed up to the true or false destination.
+struct Condition {
+    cond: BranchCondition,
+    true_chain: Vec<usize>,
+    false_chain: Vec<usize>,
+}
+
+impl Condition {
+    fn new(cond: BranchCondition) -> Condition {
+        Condition {
+            cond,
+            true_chain: vec![],
+            false_chain: vec![],
+        }
+    }
+}
 
 pub struct Codegen {
     pub instructions: Vec<Instruction>,
     rh: usize,
-    pub last_expression_operator: Option<ExpressionOp>,
+    // 0 while generating the module body, level of the procedure being generated otherwise
+    level: usize,
+    // Address of the first instruction of each procedure
+    procedures: Vec<(Rc<Symbol>, usize)>,
+    // Index of the call instructions to fix once all procedures are generated
+    calls: Vec<(usize, Rc<Symbol>)>,
+    // Check at runtime that array indices are within bounds
+    pub bounds_checks: bool,
+    // Strings, one character per word followed by 0X, to be loaded right after the code
+    pub data: Vec<i32>,
+    // Index of the instructions loading the address of a string, with the offset of the string in the data
+    strings: Vec<(usize, usize)>,
+    // Index of the instructions referring to a symbol of an imported module, with the module and the
+    // name of the symbol. The address or offset is left to 0 until the modules are linked.
+    pub externals: Vec<(usize, String, String)>,
+    // Index of the instructions loading the static base
+    static_bases: Vec<usize>,
+    // Instructions holding the absolute address of some code or data, computed as if the code was loaded at 0
+    pub relocations: Vec<Relocation>,
+    // Context of the statement being generated, to locate errors
+    context: Option<ScanContext>,
 }
 
+type CodegenResult<T = ()> = Result<T, CodegenError>;
+
 impl Codegen {
     pub fn new() -> Codegen {
         Codegen {
             instructions: vec![],
             rh: 0,
-            last_expression_operator: None,
+            level: 0,
+            procedures: vec![],
+            calls: vec![],
+            bounds_checks: true,
+            data: vec![],
+            strings: vec![],
+            externals: vec![],
+            static_bases: vec![],
+            relocations: vec![],
+            context: None,
         }
     }
 
+    // Errors are located at the statement that was being generated when they were found
+    pub fn generate_code(&mut self, module: &Module) -> Result<(), CompileError> {
+        self.generate_module(module).map_err(|error| CompileError::codegen(error, self.context))
+    }
+
     // NOTE(pht) follow the CODE from the codegen at page 51/52, and
     // not the description (it is counter intuitive)
-    pub fn generate_code(&mut self, tree: &Ast) {
-        // println!("Generating code for ast");
-        // ast::ast::print(tree);
-        // println!("");
-
-        match tree.as_ref() {
-            Tree::Nil => {}
-            Tree::Node(node) => {
-                match &node.info {
-                    NodeInfo::Module => {
-                        self.generate_code(sibling(tree).unwrap());
-                    }
+    fn generate_module(&mut self, module: &Module) -> CodegenResult {
+        self.push_static_base();
+        // The body returns to its caller like a procedure, with the return address kept in the static area.
+        // A program starts with 0 in LNK, and branching to 0 ends it.
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: LNK,
+            b: SB,
+            offset: 0,
+        });
+        self.generate_statements(&module.body)?;
+
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Load,
+            a: LNK,
+            b: SB,
+            offset: 0,
+        });
+        self.instructions.push(Instruction::Branch {
+            cond: BranchCondition::AW,
+            c: LNK,
+            link: false,
+        });
+
+        // Procedures are put after the body, so that the program still starts at 0
+        self.generate_procedures(&module.declarations)?;
+        self.fixup_calls()?;
+        self.fixup_strings();
+        self.fixup_static_bases();
+        Ok(())
+    }
 
-                    NodeInfo::Declarations => {
-                        self.generate_code(sibling(tree).unwrap());
-                    }
+    fn generate_statements(&mut self, statements: &[Stmt]) -> CodegenResult {
+        for statement in statements.iter() {
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
 
-                    NodeInfo::Declaration => {
-                        //
-                    }
+    fn generate_statement(&mut self, statement: &Stmt) -> CodegenResult {
+        self.context = Some(statement.span().start);
+        match statement {
+            Stmt::Assignment { target, value, .. } => {
+                self.generate_assignment(target, value)?;
+            }
 
-                    NodeInfo::Var => {
-                        //
-                    }
+            Stmt::ProcedureCall { procedure, parameters, .. } => {
+                self.generate_procedure_call(procedure, parameters)?;
+            }
 
-                    NodeInfo::Type(_) => {
-                        //
-                    }
+            Stmt::StandardProcedureCall { procedure, parameters, span } => {
+                self.generate_standard_procedure_call(*procedure, span.start.line, parameters)?;
+            }
 
-                    NodeInfo::Expression(operator) => {
-                        self.generate_code(child(tree).unwrap());
-                        self.generate_code(sibling(tree).unwrap());
-                        // The "decr by 2" seems a bit too simple for what I do :D
-                        self.rh -= 2;
+            Stmt::If { branches, else_branch, .. } => {
+                // Branches that go to the END of the if, once a "then" part is executed
+                let mut exit_chain = vec![];
 
-                        println!("Generating expression with operator {:?}", operator);
-                        self.last_expression_operator = Some(*operator);
+                for (index, branch) in branches.iter().enumerate() {
+                    // This generate the code for the "test" part of the branch,
+                    // that has to branch either to the next branch, or not branch at all
+                    let false_chain = self.generate_false_jump(&branch.condition)?;
 
-                        self.instructions.push(Instruction::Register {
-                            o: OpCode::SUB,
-                            a: self.rh,
-                            b: self.rh,
-                            c: self.rh + 1,
-                        });
+                    // This generates the code for the "then" part of the branch
+                    self.generate_statements(&branch.body)?;
+
+                    // The next branch, or the else part, starts with a branch to the end, that has to be skipped too
+                    if index + 1 < branches.len() || else_branch.is_some() {
+                        let exit_jump = self.push_branch(BranchCondition::AW);
+                        exit_chain.push(exit_jump);
                     }
 
-                    NodeInfo::IfStatement => {
-                        println!("Generating if statement");
+                    // This fixes up the jump to the next branch
+                    self.fixup_chain(&false_chain, self.instructions.len());
+                }
 
-                        // This generate the code for the "test" part of the if
-                        self.generate_code(child(tree).unwrap());
+                // Generate the code for the "else" part, if applicable
+                if let Some(else_branch) = else_branch {
+                    self.generate_statements(else_branch)?;
+                }
 
-                        // This has to branch either to the end, or not branch at all
-                        let condition = self.last_expression_condition();
+                self.fixup_chain(&exit_chain, self.instructions.len());
+            }
 
-                        self.instructions.push(Instruction::BranchOff {
-                            cond: condition,
-                            link: false,
-                            offset: 0, // Offset will be fixedup later
-                        });
+            Stmt::While { condition, body, .. } => {
+                let test_index = self.instructions.len();
 
-                        let fixup_index = self.instructions.len() - 1;
+                // This generate the code for the "test" part of the while
+                let false_chain = self.generate_false_jump(condition)?;
 
-                        let then_branch = sibling(tree).unwrap();
+                // This generates the code for the "do" part of the while
+                self.generate_statements(body)?;
 
-                        // This generates the code for the "then" part of the if
-                        self.generate_code(then_branch);
+                // Go back to the top
+                let back_to_test_offset = (self.instructions.len() as i32) - (test_index as i32);
+                self.instructions.push(Instruction::BranchOff {
+                    cond: BranchCondition::AW,
+                    link: false,
+                    offset: -(back_to_test_offset + 1),
+                });
 
-                        // This fixes up the jump to the else part of the tree
-                        let destination_index = self.instructions.len();
+                // Exit the loop when the test fails
+                self.fixup_chain(&false_chain, self.instructions.len());
+            }
 
-                        let fixup = (destination_index - fixup_index) as i32;
+            Stmt::Repeat { body, condition, .. } => {
+                let body_index = self.instructions.len();
 
-                        self.instructions[fixup_index] = Instruction::BranchOff {
-                            cond: condition,
-                            link: false,
-                            offset: fixup,
-                        };
+                // This generates the code for the body of the loop, executed at least once
+                self.generate_statements(body)?;
 
-                        // Generate the code for the "else" part, if applicable
-                        let else_branch = sibling(then_branch).unwrap();
-                        self.generate_code(else_branch);
-                    }
+                // Go back to the body while the test fails
+                let false_chain = self.generate_false_jump(condition)?;
+                self.fixup_chain(&false_chain, body_index);
+            }
 
-                    NodeInfo::Then => {
-                        self.generate_code(child(tree).unwrap());
-                    }
+            Stmt::For {
+                control,
+                begin,
+                limit,
+                step,
+                body,
+                span,
+            } => {
+                // Initialize the control variable
+                self.generate_assignment(control, begin)?;
+
+                // NOTE(pht) the limit is evaluated before each iteration, like the WHILE test would be
+                let test_index = self.instructions.len();
+                self.generate_variable(control)?;
+                self.generate_expression(limit)?;
+                self.rh -= 2;
+                self.instructions.push(Instruction::Register {
+                    o: OpCode::SUB,
+                    a: self.rh,
+                    b: self.rh,
+                    c: self.rh + 1,
+                });
+
+                // Exit the loop once the control variable goes past the limit
+                let exit_condition = if *step > 0 { BranchCondition::GT } else { BranchCondition::LT };
+                let exit_jump = self.push_branch(exit_condition);
+
+                self.generate_statements(body)?;
+
+                // The control variable is incremented by the absolute value of the step, like an assignment would
+                self.context = Some(span.start);
+                let increment = Expr::Addition {
+                    operator: if *step > 0 { SimpleExpressionOp::Plus } else { SimpleExpressionOp::Minus },
+                    left: Box::new(Expr::Designator(control.clone())),
+                    right: Box::new(Expr::Constant { value: step.abs(), span: *span }),
+                    span: *span,
+                };
+                self.generate_assignment(control, &increment)?;
+
+                // Go back to the test
+                let back_to_test_offset = (self.instructions.len() as i32) - (test_index as i32);
+                self.instructions.push(Instruction::BranchOff {
+                    cond: BranchCondition::AW,
+                    link: false,
+                    offset: -(back_to_test_offset + 1),
+                });
 
-                    NodeInfo::Else => {
-                        // This wants to go to the last expression of the if / then else.
-                        self.instructions.push(Instruction::BranchOff {
-                            cond: BranchCondition::AW,
-                            link: false,
-                            offset: 0, // NOTE(pht) in case there is an else block, this offset will have to change
-                        });
+                self.fixup_chain(&[exit_jump], self.instructions.len());
+            }
 
-                        let aw_index = self.instructions.len() - 1;
+            Stmt::Case {
+                selector,
+                cases,
+                else_branch,
+                span,
+            } => {
+                // The selector stays in its register while the labels are tested
+                self.generate_expression(selector)?;
+                let selector = self.rh - 1;
+
+                let labels: Vec<&(i32, i32)> = cases.iter().flat_map(|case| case.labels.iter()).collect();
+                let low = labels.iter().map(|(low, _)| *low).min().unwrap_or(0);
+                let high = labels.iter().map(|(_, high)| *high).max().unwrap_or(0);
+                // Labels can span the whole range of INTEGER, so the sizes are counted in i64
+                let label_count: i64 = labels.iter().map(|(low, high)| *high as i64 - *low as i64 + 1).sum();
+                let size = high as i64 - low as i64 + 1;
+
+                let (case_chains, else_chain) = if label_count >= MIN_JUMP_TABLE_LABELS && size <= MAX_JUMP_TABLE_SIZE && 2 * label_count >= size {
+                    self.generate_jump_table(selector, low, high, cases)
+                } else {
+                    self.generate_case_comparisons(selector, cases)
+                };
+                // The bodies can reuse the register, the selector is only needed by the tests and the trap
+                self.rh -= 1;
+
+                // Branches that go to the END of the case, once a statement sequence is executed
+                let mut exit_chain = vec![];
+                for (case, case_chain) in cases.iter().zip(case_chains.iter()) {
+                    self.fixup_chain(case_chain, self.instructions.len());
+                    self.generate_statements(&case.body)?;
+                    let exit_jump = self.push_branch(BranchCondition::AW);
+                    exit_chain.push(exit_jump);
+                }
 
-                        self.generate_code(child(tree).unwrap());
+                self.fixup_chain(&else_chain, self.instructions.len());
+                match else_branch {
+                    Some(else_branch) => self.generate_statements(else_branch)?,
+                    // A selector that matches no label is an error when there is no ELSE
+                    None => self.instructions.push(Instruction::Trap {
+                        cond: BranchCondition::AW,
+                        c: selector,
+                        code: TrapCode::CaseNotMatched,
+                        line: span.start.line + 1,
+                    }),
+                }
 
-                        let aw_destination_index = self.instructions.len();
-                        let aw_fixup = (aw_destination_index - aw_index) as i32;
-                        self.instructions[aw_index] = Instruction::BranchOff {
-                            cond: BranchCondition::AW,
-                            link: false,
-                            offset: aw_fixup,
-                        };
-                    }
+                self.fixup_chain(&exit_chain, self.instructions.len());
+            }
+        }
+        Ok(())
+    }
 
-                    NodeInfo::WhileStatement => {
-                        let test_index = self.instructions.len();
+    // Puts the value of an expression in a new register
+    fn generate_expression(&mut self, expression: &Expr) -> CodegenResult {
+        match expression {
+            Expr::Relation { .. }
+            | Expr::Not { .. }
+            | Expr::Multiplication { operator: TermOp::And, .. }
+            | Expr::Addition {
+                operator: SimpleExpressionOp::Or, ..
+            } => {
+                self.load_condition(expression)?;
+            }
 
-                        // This generate the code for the "test" part of the if
-                        self.generate_code(child(tree).unwrap());
+            &Expr::Boolean { value, .. } => {
+                self.instructions.push(Instruction::RegisterIm {
+                    o: MOV,
+                    a: self.rh,
+                    b: 0,
+                    im: value as i32,
+                });
+                self.inc_rh()?;
+            }
 
-                        let condition = self.last_expression_condition();
-                        self.instructions.push(Instruction::BranchOff {
-                            cond: condition,
-                            link: false,
-                            offset: 0, // Offset will be fixedup later
-                        });
-                        let do_index = self.instructions.len() - 1;
-
-                        // This generates the code for the "do" part of the while
-                        let do_branch = sibling(tree).unwrap();
-                        self.generate_code(do_branch);
-
-                        // Fixup do index
-                        let do_offset = (self.instructions.len() as i32) - (do_index as i32);
-                        self.instructions[do_index] = Instruction::BranchOff {
-                            cond: condition,
-                            link: false,
-                            offset: do_offset,
-                        };
-
-                        // Go back to the top
-                        let back_to_test_offset = (self.instructions.len() as i32) - (test_index as i32);
-                        self.instructions.push(Instruction::BranchOff {
-                            cond: BranchCondition::AW,
-                            link: false,
-                            offset: -(back_to_test_offset + 1),
-                        });
-                    }
+            Expr::Designator(designator) => {
+                self.generate_variable(designator)?;
+            }
 
-                    NodeInfo::Do => {
-                        self.generate_code(child(tree).unwrap());
-                    }
+            &Expr::Set { value, .. } => {
+                self.load_constant(self.rh, value as i32);
+                self.inc_rh()?;
+            }
+
+            Expr::SetConstructor { elements, .. } => {
+                self.generate_set_constructor(elements)?;
+            }
+
+            &Expr::Constant { value, .. } => {
+                self.load_constant(self.rh, value);
+                self.inc_rh()?;
+            }
+
+            &Expr::Char { value, .. } => {
+                self.load_constant(self.rh, value as i32);
+                self.inc_rh()?;
+            }
 
-                    NodeInfo::Ident(ident_symbol) => {
-                        println!("Generating code for ident symbol {:?}", ident_symbol);
-
-                        let selector = child(tree).unwrap();
-
-                        let mut ident_offset = ident_symbol.adr as u32;
-                        match info(selector) {
-                            None => {
-                                self.instructions.push(Instruction::Memory {
-                                    u: MemoryMode::Load,
-                                    a: self.rh,
-                                    b: 14,
-                                    offset: ident_offset,
-                                });
-
-                                self.rh += 1;
-                            }
-
-                            Some(NodeInfo::Constant(selector_index)) => {
-                                ident_offset += selector_index;
-
-                                // R[A] <- M[SP + ident_offset]
-                                self.instructions.push(Instruction::Memory {
-                                    u: MemoryMode::Load,
-                                    a: self.rh,
-                                    b: 14,
-                                    offset: ident_offset,
-                                });
-
-                                self.rh += 1;
-                            }
-
-                            Some(NodeInfo::Ident(selector_symbol)) => {
-                                let selector_offset = selector_symbol.adr as u32;
-
-                                // General form is
-                                // R[A] <- M[R[B] + ident_offset]
-                                //
-                                // We want to write:
-                                // self.instructions.push(Instruction::Memory {
-                                //     u: MemoryMode::Load,
-                                //     a: self.rh,
-                                //     b: X,
-                                //     offset: Y
-                                // });
-                                //
-                                // So, that, in the end:
-                                // R[X] != RH
-                                // M[R[X] + Y] = M[SP + ident_offset + M[SP + selector_offset]]
-
-                                // It would be easy to load the content of M[SP + selector_offset] in a new register:
-                                self.instructions.push(Instruction::Memory {
-                                    u: MemoryMode::Load,
-                                    a: self.rh + 1,
-                                    b: 14,
-                                    offset: selector_offset,
-                                });
-
-                                // Know, we need to add the stack pointer at least once ?
-                                self.instructions.push(Instruction::Register {
-                                    o: ADD,
-                                    a: self.rh + 1,
-                                    b: self.rh + 1,
-                                    c: 14,
-                                });
-
-                                // Finally, we can load the value into the previous register
-                                self.instructions.push(Instruction::Memory {
-                                    u: MemoryMode::Load,
-                                    a: self.rh,
-                                    b: self.rh + 1,
-                                    offset: ident_offset,
-                                });
-
-                                // And we prepare next value
-                                self.rh += 1;
-                            }
-
-                            _ => {
-                                todo!("unsupported type of assignment");
-                            }
-                        }
+            Expr::String { value, .. } => {
+                // Single characters are values, longer strings are only copied by assignments
+                if value.len() != 1 {
+                    return Err(CodegenError::StringNotAValue(value.clone()));
+                }
+                self.load_constant(self.rh, value.as_bytes()[0] as i32);
+                self.inc_rh()?;
+            }
+
+            Expr::Negate { operand, .. } => {
+                // R[r] <- 0 - R[r], there is no instruction to subtract a register from an immediate
+                self.generate_expression(operand)?;
+                let r = self.rh - 1;
+                self.instructions.push(Instruction::RegisterIm { o: MOV, a: self.rh, b: 0, im: 0 });
+                self.instructions.push(Instruction::Register { o: SUB, a: r, b: self.rh, c: r });
+            }
+
+            Expr::Multiplication { operator, left, right, .. } => {
+                self.generate_expression(left)?;
+                self.generate_expression(right)?;
+                let opcode = match operator {
+                    TermOp::Times => MUL,
+                    TermOp::Div => DIV,
+                    TermOp::Mod => MOD,
+                    TermOp::Intersection => AND,
+                    TermOp::SymmetricDifference => XOR,
+                    // Conjunctions are generated as conditions
+                    TermOp::And => return Err(CodegenError::UnexpectedNode(format!("{:?}", operator))),
+                };
+                self.rh -= 1;
+                self.instructions.push(Instruction::Register {
+                    o: opcode,
+                    a: self.rh - 1,
+                    b: self.rh - 1,
+                    c: self.rh,
+                })
+            }
+
+            Expr::Addition { operator, left, right, .. } => {
+                self.generate_expression(left)?;
+                self.generate_expression(right)?;
+                let opcode = match operator {
+                    SimpleExpressionOp::Plus => ADD,
+                    SimpleExpressionOp::Minus => SUB,
+                    SimpleExpressionOp::Union => IOR,
+                    SimpleExpressionOp::Difference => ANN,
+                    // Disjunctions are generated as conditions
+                    SimpleExpressionOp::Or => return Err(CodegenError::UnexpectedNode(format!("{:?}", operator))),
+                };
+                self.rh -= 1;
+                self.instructions.push(Instruction::Register {
+                    o: opcode,
+                    a: self.rh - 1,
+                    b: self.rh - 1,
+                    c: self.rh,
+                })
+            }
+
+            Expr::StandardFunctionCall { procedure, parameters, span } => {
+                self.generate_standard_procedure_call(*procedure, span.start.line, parameters)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Puts the value of a variable in a new register
+    fn generate_variable(&mut self, designator: &Designator) -> CodegenResult {
+        if designator.selectors.is_empty() {
+            self.load_variable(&designator.symbol, self.rh);
+        } else {
+            // The address is computed in the registers after the one that receives the value
+            let target = self.rh;
+            self.inc_rh()?;
+            let (b, offset) = self.generate_designator(designator)?;
+            self.rh = target;
+
+            // R[A] <- M[R[B] + offset]
+            self.instructions.push(Instruction::Memory {
+                u: MemoryMode::Load,
+                a: self.rh,
+                b,
+                offset,
+            });
+        }
+        self.inc_rh()?;
+        Ok(())
+    }
+
+    fn generate_assignment(&mut self, target: &Designator, value: &Expr) -> CodegenResult {
+        if let Expr::String { value, .. } = value {
+            return self.generate_string_copy(target, value);
+        }
+
+        // Generate code for value to be assigned
+        self.generate_expression(value)?;
+
+        // NOTE(pht): it is not absolutely clear if the rh = rh - 1
+        // has to be done before or after the STW ; but it only
+        // makes sense for me to do it before.
+        self.rh -= 1;
+
+        // The value is in rh, the address is computed in the following registers
+        let value = self.rh;
+        self.inc_rh()?;
+        let (b, offset) = self.generate_designator(target)?;
+        self.rh = value;
+
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: value,
+            b,
+            offset,
+        });
+        Ok(())
+    }
+
+    fn generate_procedure_call(&mut self, procedure: &Rc<Symbol>, actual_parameters: &[Expr]) -> CodegenResult {
+        let parameters = match &procedure.kind {
+            SymbolKind::Procedure(parameters) => parameters,
+            _ => return Err(CodegenError::NotAProcedure(procedure.name.clone())),
+        };
+        // Procedures imported from a symbol file were not checked by the parser
+        if self.rh + parameters.len() >= SB {
+            return Err(CodegenError::TooManyParameters(procedure.name.clone()));
+        }
+
+        // Each parameter ends up in the next register
+        for (index, parameter) in parameters.iter().enumerate() {
+            let expression = actual_parameters.get(index).ok_or(CodegenError::MissingNode)?;
+            if is_by_reference(parameter) {
+                self.generate_address(as_designator(expression)?)?;
+            } else {
+                self.generate_expression(expression)?;
+            }
+        }
+
+        if let Some(module) = &procedure.module {
+            self.push_external(module, &procedure.name);
+        } else {
+            self.calls.push((self.instructions.len(), procedure.clone()));
+        }
+        self.instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::AW,
+            link: true,
+            offset: 0, // Offset will be fixedup once the procedure is generated
+        });
+        // The imported procedure left the static base on the variables of its own module
+        if procedure.module.is_some() {
+            self.push_static_base();
+        }
+
+        self.rh -= parameters.len();
+        Ok(())
+    }
+
+    fn generate_procedures(&mut self, declarations: &[Decl]) -> CodegenResult {
+        for declaration in declarations.iter() {
+            if let Decl::Procedure {
+                symbol,
+                frame_size,
+                declarations,
+                body,
+                ..
+            } = declaration
+            {
+                self.generate_procedure(symbol, *frame_size, declarations, body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_procedure(&mut self, procedure: &Rc<Symbol>, frame_size: usize, declarations: &[Decl], body: &[Stmt]) -> CodegenResult {
+        self.procedures.push((procedure.clone(), self.instructions.len()));
+
+        let outer_level = self.level;
+        self.level = procedure.level + 1;
+
+        // Exported procedures can be called from other modules, with the static base of the caller
+        if procedure.export.is_some() {
+            self.push_static_base();
+        }
+
+        // Prologue: allocate the frame, save the return address and the parameters
+        self.instructions.push(Instruction::RegisterIm {
+            o: SUB,
+            a: FP,
+            b: FP,
+            im: frame_size as i32,
+        });
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: LNK,
+            b: FP,
+            offset: 0,
+        });
+        if let SymbolKind::Procedure(parameters) = &procedure.kind {
+            for (register, parameter) in parameters.iter().enumerate() {
+                self.instructions.push(Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: register,
+                    b: FP,
+                    offset: parameter.adr as u32,
+                });
+            }
+        }
+
+        // Body
+        self.generate_statements(body)?;
+
+        // Epilogue: release the frame and go back to the caller
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Load,
+            a: LNK,
+            b: FP,
+            offset: 0,
+        });
+        self.instructions.push(Instruction::RegisterIm {
+            o: ADD,
+            a: FP,
+            b: FP,
+            im: frame_size as i32,
+        });
+        self.instructions.push(Instruction::Branch {
+            cond: BranchCondition::AW,
+            c: LNK,
+            link: false,
+        });
+
+        self.generate_procedures(declarations)?;
+
+        self.level = outer_level;
+        Ok(())
+    }
+
+    // Address of the first instruction of a procedure declared in the module
+    pub fn procedure_address(&self, name: &str) -> Option<usize> {
+        self.procedures
+            .iter()
+            .find(|(procedure, _)| procedure.level == 0 && procedure.name == name)
+            .map(|(_, address)| *address)
+    }
+
+    fn fixup_calls(&mut self) -> CodegenResult {
+        for (call_index, procedure) in self.calls.iter() {
+            let (_, entry_index) = self
+                .procedures
+                .iter()
+                .find(|(candidate, _)| Rc::ptr_eq(candidate, procedure))
+                .ok_or_else(|| CodegenError::UndefinedProcedure(procedure.name.clone()))?;
+
+            self.instructions[*call_index] = Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
+                offset: (*entry_index as i32) - (*call_index as i32) - 1,
+            };
+        }
+        Ok(())
+    }
+
+    // Adds each element, or range of elements, to an empty set
+    fn generate_set_constructor(&mut self, elements: &[SetElement]) -> CodegenResult {
+        let set = self.rh;
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: set, b: 0, im: 0 });
+        self.inc_rh()?;
+
+        for element in elements.iter() {
+            let bits = self.rh;
+            match element {
+                SetElement::Range { low, high, .. } => {
+                    // {low..high} is (-1 << low) without the bits of (-2 << high)
+                    self.generate_expression(low)?;
+                    self.generate_expression(high)?;
+                    let mask = bits + 2;
+                    self.instructions.push(Instruction::RegisterIm { o: MOV, a: mask, b: 0, im: -1 });
+                    self.instructions.push(Instruction::Register {
+                        o: LSL,
+                        a: bits,
+                        b: mask,
+                        c: bits,
+                    });
+                    self.instructions.push(Instruction::RegisterIm { o: MOV, a: mask, b: 0, im: -2 });
+                    self.instructions.push(Instruction::Register {
+                        o: LSL,
+                        a: bits + 1,
+                        b: mask,
+                        c: bits + 1,
+                    });
+                    self.instructions.push(Instruction::Register {
+                        o: ANN,
+                        a: bits,
+                        b: bits,
+                        c: bits + 1,
+                    });
+                }
+                SetElement::Single(value) => {
+                    self.generate_expression(value)?;
+                    let one = bits + 1;
+                    self.instructions.push(Instruction::RegisterIm { o: MOV, a: one, b: 0, im: 1 });
+                    self.instructions.push(Instruction::Register { o: LSL, a: bits, b: one, c: bits });
+                }
+            }
+            self.instructions.push(Instruction::Register { o: IOR, a: set, b: set, c: bits });
+            self.rh = set + 1;
+        }
+        Ok(())
+    }
+
+    // NOTE(pht) code is loaded at address 0, so the data area starts right after the last instruction
+    fn fixup_strings(&mut self) {
+        let data_address = self.instructions.len();
+        for &(load_index, data_offset) in self.strings.iter() {
+            self.relocations.push(Relocation {
+                index: load_index,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Data(data_offset),
+            });
+            if let Instruction::RegisterIm { o, a, b, .. } = self.instructions[load_index] {
+                self.instructions[load_index] = Instruction::RegisterIm {
+                    o,
+                    a,
+                    b,
+                    im: (data_address + data_offset) as i32,
+                };
+            }
+        }
+    }
+
+    // NOTE(pht) code is loaded at address 0, so the static area starts right after the data area
+    fn fixup_static_bases(&mut self) {
+        let static_base = self.instructions.len() + self.data.len();
+        for &load_index in self.static_bases.iter() {
+            self.relocations.push(Relocation {
+                index: load_index,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Variables(0),
+            });
+            self.instructions[load_index] = Instruction::RegisterIm {
+                o: MOV,
+                a: SB,
+                b: 0,
+                im: static_base as i32,
+            };
+        }
+    }
+
+    // SB <- address of the static area, fixed up once the code and data are complete
+    fn push_static_base(&mut self) {
+        self.static_bases.push(self.instructions.len());
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: SB, b: 0, im: 0 });
+    }
+
+    // The next instruction holds the address of the target
+    fn push_relocation(&mut self, target: RelocationTarget) {
+        self.relocations.push(Relocation {
+            index: self.instructions.len(),
+            kind: RelocationKind::Absolute,
+            target,
+        });
+    }
+
+    // R[register] <- address of a new string in the data area, fixed up once the code is complete
+    fn push_string_address(&mut self, register: usize, value: &str) {
+        self.strings.push((self.instructions.len(), self.data.len()));
+        self.data.extend(value.bytes().map(|c| c as i32));
+        self.data.push(0);
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: register, b: 0, im: 0 });
+    }
+
+    // Copies a string from the data area, including its terminating 0X, one word at a time
+    fn generate_string_copy(&mut self, designator: &Designator, value: &str) -> CodegenResult {
+        let source = self.rh;
+        self.push_string_address(source, value);
+        self.inc_rh()?;
+
+        let destination = self.rh;
+        self.generate_address(designator)?;
+        let count = destination + 1;
+        let word = destination + 2;
+        self.load_constant(count, value.len() as i32 + 1);
+
+        let loop_index = self.instructions.len();
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Load,
+            a: word,
+            b: source,
+            offset: 0,
+        });
+        self.instructions.push(Instruction::RegisterIm {
+            o: ADD,
+            a: source,
+            b: source,
+            im: 1,
+        });
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: word,
+            b: destination,
+            offset: 0,
+        });
+        self.instructions.push(Instruction::RegisterIm {
+            o: ADD,
+            a: destination,
+            b: destination,
+            im: 1,
+        });
+        self.instructions.push(Instruction::RegisterIm {
+            o: SUB,
+            a: count,
+            b: count,
+            im: 1,
+        });
+        self.instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::NE,
+            link: false,
+            offset: (loop_index as i32) - (self.instructions.len() as i32) - 1,
+        });
+
+        self.rh = source;
+        Ok(())
+    }
+
+    // Module variables are addressed from the static base, locals and parameters from the frame
+    fn base_register(&self, symbol: &Symbol) -> usize {
+        if symbol.level == 0 {
+            SB
+        } else {
+            FP
+        }
+    }
+
+    // R[register] <- value of a variable without selector
+    fn load_variable(&mut self, symbol: &Symbol, register: usize) {
+        if let Some(module) = &symbol.module {
+            self.push_external_address(register, module, &symbol.name);
+            self.instructions.push(Instruction::Memory {
+                u: MemoryMode::Load,
+                a: register,
+                b: register,
+                offset: 0,
+            });
+            return;
+        }
+
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Load,
+            a: register,
+            b: self.base_register(symbol),
+            offset: symbol.adr as u32,
+        });
+
+        if is_by_reference(symbol) {
+            self.instructions.push(Instruction::Memory {
+                u: MemoryMode::Load,
+                a: register,
+                b: register,
+                offset: 0,
+            });
+        }
+    }
+
+    // R[register] <- address of a variable of an imported module
+    fn push_external_address(&mut self, register: usize, module: &str, name: &str) {
+        self.push_external(module, name);
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: register, b: 0, im: 0 });
+    }
+
+    // The next instruction refers to a symbol of an imported module
+    fn push_external(&mut self, module: &str, name: &str) {
+        self.externals.push((self.instructions.len(), String::from(module), String::from(name)));
+    }
+
+    // Puts the address of a variable in a new register, to pass it as a VAR parameter
+    fn generate_address(&mut self, designator: &Designator) -> CodegenResult {
+        let address = self.rh;
+        let (b, offset) = self.generate_designator(designator)?;
+        // The address might already be in the right register, when the designator is a VAR parameter
+        if b != address || offset != 0 {
+            self.instructions.push(Instruction::RegisterIm {
+                o: ADD,
+                a: address,
+                b,
+                im: offset as i32,
+            });
+        }
+        self.rh = address + 1;
+        Ok(())
+    }
+
+    // Computes the location of a designator as a register and an offset, so that it can be used by
+    // a single load or store. Registers from rh onwards are used when part of the address is
+    // only known at runtime.
+    fn generate_designator(&mut self, designator: &Designator) -> CodegenResult<(usize, u32)> {
+        let symbol = &designator.symbol;
+        let mut b = self.base_register(symbol);
+        let mut offset = symbol.adr as u32;
+
+        if let Some(module) = &symbol.module {
+            self.push_external_address(self.rh, module, &symbol.name);
+            b = self.rh;
+            offset = 0;
+            self.inc_rh()?;
+        }
+
+        if is_by_reference(symbol) {
+            // The slot of a VAR parameter holds the address of the variable
+            self.instructions.push(Instruction::Memory {
+                u: MemoryMode::Load,
+                a: self.rh,
+                b,
+                offset,
+            });
+            b = self.rh;
+            offset = 0;
+            self.inc_rh()?;
+        }
+
+        for selector in designator.selectors.iter() {
+            match selector {
+                Selector::Field { offset: field_offset, .. } => {
+                    offset += *field_offset as u32;
+                }
+
+                Selector::Index {
+                    index,
+                    element_size,
+                    capacity,
+                    span,
+                } => {
+                    if let Expr::Constant { value: index, .. } = index {
+                        offset += *index as u32 * *element_size as u32;
+                        continue;
                     }
 
-                    // TODO(pht) Constant should be allowed to be negative...
-                    &NodeInfo::Constant(value) => {
+                    // R[rh] <- R[b] + index * element_size, the constant part stays in the offset
+                    self.generate_expression(index)?;
+                    self.rh -= 1;
+                    if let (Some(capacity), true) = (capacity, self.bounds_checks) {
+                        self.generate_bounds_check(self.rh, *capacity, span.start.line);
+                    }
+                    if *element_size > 1 {
                         self.instructions.push(Instruction::RegisterIm {
-                            o: MOV,
+                            o: MUL,
                             a: self.rh,
-                            b: 0,
-                            im: value as i32,
+                            b: self.rh,
+                            im: *element_size as i32,
                         });
-                        self.rh += 1;
-                    }
-
-                    NodeInfo::Assignement => {
-                        if let Tree::Node(TreeNode {
-                            info: NodeInfo::Ident(lhs_symbol),
-                            child: selector,
-                            ..
-                        }) = node.child.as_ref()
-                        {
-                            // Generate code for value to be assigned
-                            self.generate_code(&node.sibling);
-
-                            // NOTE(pht): it is not absolutely clear if the rh = rh - 1
-                            // has to be done before or after the STW ; but it only
-                            // makes sense for me to do it before.
-                            self.rh -= 1;
-
-                            let mut offset = lhs_symbol.adr as u32;
-                            match info(selector) {
-                                None => {
-                                    self.instructions.push(Instruction::Memory {
-                                        u: MemoryMode::Store,
-                                        a: self.rh,
-                                        b: 14,
-                                        offset,
-                                    });
-                                }
-
-                                Some(NodeInfo::Constant(selector_index)) => {
-                                    // NOTE(pht) this only works for constant selector
-                                    offset += selector_index;
-
-                                    self.instructions.push(Instruction::Memory {
-                                        u: MemoryMode::Store,
-                                        a: self.rh,
-                                        b: 14,
-                                        offset,
-                                    });
-                                }
-
-                                // First part
-                                // Handle _storing_ at the current value of indent
-                                // This is the a[i] := 1 part
-                                Some(NodeInfo::Ident(selector_symbol)) => {
-                                    // So, what does not work is that the value of the index
-                                    // is ignored ; somehow there is nothing that properly loads the value of rh in the thingy :/
-
-                                    self.rh += 1;
-
-                                    // Load the index value into a new register
-                                    self.instructions.push(Instruction::Memory {
-                                        a: self.rh,
-                                        b: 14,
-                                        offset: selector_symbol.adr as u32,
-                                        u: MemoryMode::Load,
-                                    });
-
-                                    // Add the location of the base pointer
-                                    // self.rh <- self.rh + offset
-                                    self.instructions.push(Instruction::Register {
-                                        o: ADD,
-                                        a: self.rh,
-                                        b: self.rh,
-                                        c: 14,
-                                    });
-
-                                    // Store the value of the "previous" register at the new location
-                                    self.instructions.push(Instruction::Memory {
-                                        u: MemoryMode::Store,
-                                        a: self.rh - 1,
-                                        b: self.rh,
-                                        offset,
-                                    });
-
-                                    // Then store are the right location
-
-                                    self.rh -= 1;
-                                }
-
-                                // You'll have to generate the code for it
-                                // The offset cannot stay a constant...
-                                _ => {
-                                    todo!("Assignement with selector is only implemented for constants and identifiers")
-                                }
-                            }
-                        }
                     }
+                    self.instructions.push(Instruction::Register {
+                        o: ADD,
+                        a: self.rh,
+                        b: self.rh,
+                        c: b,
+                    });
+                    b = self.rh;
+                    self.inc_rh()?;
+                }
+            }
+        }
 
-                    NodeInfo::StatementSequence => {
-                        self.generate_code(&node.child);
-                        self.generate_code(&node.sibling);
-                    }
+        Ok((b, offset))
+    }
 
-                    NodeInfo::Term(operator) => {
-                        self.generate_code(&node.child);
-                        self.generate_code(&node.sibling);
-                        let opcode = match operator {
-                            TermOp::Times => MUL,
-                            TermOp::Div => DIV,
-                        };
+    // Predeclared procedures are generated inline. Functions leave their result in a new register, like expressions.
+    fn generate_standard_procedure_call(&mut self, procedure: StandardProcedure, line: u32, parameters: &[Expr]) -> CodegenResult {
+        // NOTE(pht) Out.Ln is the only procedure without parameters
+        let first = parameters.get(0);
+        let second = parameters.get(1);
+
+        match procedure {
+            StandardProcedure::Inc | StandardProcedure::Dec => {
+                let designator = as_designator(first.ok_or(CodegenError::MissingNode)?)?;
+                let opcode = if procedure == StandardProcedure::Inc { ADD } else { SUB };
+
+                // The address is computed once, for both the load and the store
+                let base = self.rh;
+                let (b, offset) = self.generate_designator(designator)?;
+                let value = self.rh;
+                self.instructions.push(Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: value,
+                    b,
+                    offset,
+                });
+                self.inc_rh()?;
+                match second {
+                    None => self.push_register_im(opcode, value, value, 1),
+                    Some(&Expr::Constant { value: amount, .. }) => self.push_register_im(opcode, value, value, amount),
+                    Some(amount) => {
+                        self.generate_expression(amount)?;
                         self.rh -= 1;
                         self.instructions.push(Instruction::Register {
                             o: opcode,
-                            a: self.rh - 1,
-                            b: self.rh - 1,
+                            a: value,
+                            b: value,
                             c: self.rh,
-                        })
+                        });
                     }
+                }
+                self.instructions.push(Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: value,
+                    b,
+                    offset,
+                });
+                self.rh = base;
+            }
 
-                    NodeInfo::SimpleExpression(operator) => {
-                        self.generate_code(&node.child);
-                        self.generate_code(&node.sibling);
-                        let opcode = match operator {
-                            SimpleExpressionOp::Plus => ADD,
-                            SimpleExpressionOp::Minus => SUB,
-                        };
-                        self.rh -= 1;
-                        self.instructions.push(Instruction::Register {
-                            o: opcode,
-                            a: self.rh - 1,
-                            b: self.rh - 1,
-                            c: self.rh,
-                        })
-                    }
+            StandardProcedure::Assert => {
+                let condition = self.generate_condition(first.ok_or(CodegenError::MissingNode)?)?;
+                let mut true_chain = condition.true_chain;
+                let trap_cond = if condition.false_chain.is_empty() {
+                    negated(condition.cond)
+                } else {
+                    true_chain.push(self.push_branch(condition.cond));
+                    self.fixup_chain(&condition.false_chain, self.instructions.len());
+                    BranchCondition::AW
+                };
+                self.instructions.push(Instruction::Trap {
+                    cond: trap_cond,
+                    c: 0,
+                    code: TrapCode::AssertionFailed,
+                    line: line + 1,
+                });
+                self.fixup_chain(&true_chain, self.instructions.len());
+            }
+
+            StandardProcedure::Halt => {
+                let code = match first.ok_or(CodegenError::MissingNode)? {
+                    &Expr::Constant { value: code, .. } => code,
+                    // The parser evaluates the code of HALT
+                    other => return Err(CodegenError::UnexpectedNode(format!("{:?}", other))),
+                };
+                self.load_constant(self.rh, code);
+                self.instructions.push(Instruction::Trap {
+                    cond: BranchCondition::AW,
+                    c: self.rh,
+                    code: TrapCode::Halt,
+                    line: line + 1,
+                });
+            }
+
+            StandardProcedure::Odd => {
+                self.generate_expression(first.ok_or(CodegenError::MissingNode)?)?;
+                let r = self.rh - 1;
+                self.instructions.push(Instruction::RegisterIm { o: AND, a: r, b: r, im: 1 });
+            }
+
+            StandardProcedure::Abs => {
+                // Moving the value to itself sets the flags, negative values are subtracted from 0
+                self.generate_expression(first.ok_or(CodegenError::MissingNode)?)?;
+                let r = self.rh - 1;
+                self.instructions.push(Instruction::Register { o: MOV, a: r, b: 0, c: r });
+                self.instructions.push(Instruction::BranchOff {
+                    cond: BranchCondition::GE,
+                    link: false,
+                    offset: 2,
+                });
+                self.instructions.push(Instruction::RegisterIm { o: MOV, a: self.rh, b: 0, im: 0 });
+                self.instructions.push(Instruction::Register { o: SUB, a: r, b: self.rh, c: r });
+            }
+
+            StandardProcedure::Lsl | StandardProcedure::Asr | StandardProcedure::Ror => {
+                let opcode = match procedure {
+                    StandardProcedure::Lsl => LSL,
+                    StandardProcedure::Asr => ASR,
+                    _ => ROR,
+                };
+                self.generate_expression(first.ok_or(CodegenError::MissingNode)?)?;
+                let r = self.rh - 1;
+                let amount = second.ok_or(CodegenError::MissingNode)?;
+                if let &Expr::Constant { value: amount, .. } = amount {
+                    self.instructions.push(Instruction::RegisterIm {
+                        o: opcode,
+                        a: r,
+                        b: r,
+                        im: amount,
+                    });
+                } else {
+                    self.generate_expression(amount)?;
+                    self.rh -= 1;
+                    self.instructions.push(Instruction::Register {
+                        o: opcode,
+                        a: r,
+                        b: r,
+                        c: self.rh,
+                    });
+                }
+            }
+
+            StandardProcedure::OutInt | StandardProcedure::OutChar | StandardProcedure::OutString | StandardProcedure::OutLn | StandardProcedure::InInt => {
+                self.generate_console_call(procedure, first, second)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Console procedures load from and store to the devices mapped at negative addresses
+    fn generate_console_call(&mut self, procedure: StandardProcedure, first: Option<&Expr>, second: Option<&Expr>) -> CodegenResult {
+        let base = self.rh;
+
+        match procedure {
+            StandardProcedure::OutInt => {
+                if let Some(width) = second {
+                    self.generate_expression(width)?;
+                    self.push_device_store(self.rh - 1, IO_WIDTH);
+                    self.rh = base;
+                }
+                self.generate_expression(first.ok_or(CodegenError::MissingNode)?)?;
+                self.push_device_store(self.rh - 1, IO_INT);
+            }
+            StandardProcedure::OutChar => {
+                self.generate_expression(first.ok_or(CodegenError::MissingNode)?)?;
+                self.push_device_store(self.rh - 1, IO_CHAR);
+            }
+            StandardProcedure::OutLn => {
+                self.load_constant(base, '\n' as i32);
+                self.push_device_store(base, IO_CHAR);
+            }
+            StandardProcedure::OutString => {
+                let capacity = match second {
+                    Some(&Expr::Constant { value: capacity, .. }) => capacity,
+                    // The parser passes the capacity along with the printed string
+                    other => return Err(CodegenError::UnexpectedNode(format!("{:?}", other))),
+                };
+                self.generate_string_output(first.ok_or(CodegenError::MissingNode)?, capacity)?;
+            }
+            StandardProcedure::InInt => {
+                self.generate_address(as_designator(first.ok_or(CodegenError::MissingNode)?)?)?;
+                let value = self.rh;
+                self.load_constant(value, IO_INT);
+                self.instructions.push(Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: value,
+                    b: value,
+                    offset: 0,
+                });
+                self.instructions.push(Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: value,
+                    b: base,
+                    offset: 0,
+                });
+            }
+            _ => return Err(CodegenError::UnexpectedNode(String::from(procedure.name()))),
+        }
+        self.rh = base;
+        Ok(())
+    }
+
+    // M[device] <- R[value], using the next register for the address of the device
+    fn push_device_store(&mut self, value: usize, device: i32) {
+        self.load_constant(value + 1, device);
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: value,
+            b: value + 1,
+            offset: 0,
+        });
+    }
+
+    // Prints characters until the terminating 0X, or until capacity characters have been printed
+    fn generate_string_output(&mut self, string: &Expr, capacity: i32) -> CodegenResult {
+        let address = self.rh;
+        if let Expr::String { value, .. } = string {
+            self.push_string_address(address, value);
+        } else {
+            self.generate_address(as_designator(string)?)?;
+        }
+        let count = address + 1;
+        let device = address + 2;
+        let character = address + 3;
+        self.load_constant(count, capacity);
+        self.load_constant(device, IO_CHAR);
+
+        let loop_index = self.instructions.len();
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Load,
+            a: character,
+            b: address,
+            offset: 0,
+        });
+        self.instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::EQ,
+            link: false,
+            offset: 4,
+        });
+        self.instructions.push(Instruction::Memory {
+            u: MemoryMode::Store,
+            a: character,
+            b: device,
+            offset: 0,
+        });
+        self.push_register_im(ADD, address, address, 1);
+        self.push_register_im(SUB, count, count, 1);
+        self.instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::NE,
+            link: false,
+            offset: (loop_index as i32) - (self.instructions.len() as i32) - 1,
+        });
+        Ok(())
+    }
+
+    // Traps unless 0 <= R[index] < capacity, using the next register as a scratch one
+    fn generate_bounds_check(&mut self, index: usize, capacity: u32, line: u32) {
+        // Traps report lines counting from 1, like editors do
+        let trap = |cond| Instruction::Trap {
+            cond,
+            c: index,
+            code: TrapCode::ArrayIndexOutOfBounds,
+            line: line + 1,
+        };
+
+        self.instructions.push(Instruction::Register {
+            o: MOV,
+            a: index + 1,
+            b: 0,
+            c: index,
+        });
+        self.instructions.push(trap(BranchCondition::LT));
+        self.instructions.push(Instruction::RegisterIm {
+            o: SUB,
+            a: index + 1,
+            b: index,
+            im: capacity as i32,
+        });
+        self.instructions.push(trap(BranchCondition::GE));
+    }
+
+    // Generates the code of a condition, without branching on its final value
+    fn generate_condition(&mut self, expression: &Expr) -> CodegenResult<Condition> {
+        match expression {
+            Expr::Relation { operator, left, right, .. } => {
+                self.generate_expression(left)?;
+                self.generate_expression(right)?;
+                // The "decr by 2" seems a bit too simple for what I do :D
+                self.rh -= 2;
+
+                if *operator == ExpressionOp::In {
+                    // Rotating the set by x + 1 brings bit x to the sign bit
+                    self.instructions.push(Instruction::RegisterIm {
+                        o: ADD,
+                        a: self.rh,
+                        b: self.rh,
+                        im: 1,
+                    });
+                    self.instructions.push(Instruction::Register {
+                        o: ROR,
+                        a: self.rh + 1,
+                        b: self.rh + 1,
+                        c: self.rh,
+                    });
+                } else {
+                    self.instructions.push(Instruction::Register {
+                        o: OpCode::SUB,
+                        a: self.rh,
+                        b: self.rh,
+                        c: self.rh + 1,
+                    });
+                }
+
+                Ok(Condition::new(relation_condition(*operator)))
+            }
+
+            Expr::Multiplication {
+                operator: TermOp::And,
+                left,
+                right,
+                ..
+            } => {
+                // If the left part is false, the whole condition is false
+                let mut left = self.generate_condition(left)?;
+                let false_jump = self.push_branch(negated(left.cond));
+                left.false_chain.push(false_jump);
+                self.fixup_chain(&left.true_chain, self.instructions.len());
+
+                let mut right = self.generate_condition(right)?;
+                left.false_chain.append(&mut right.false_chain);
+                Ok(Condition {
+                    cond: right.cond,
+                    true_chain: right.true_chain,
+                    false_chain: left.false_chain,
+                })
+            }
+
+            Expr::Addition {
+                operator: SimpleExpressionOp::Or,
+                left,
+                right,
+                ..
+            } => {
+                // If the left part is true, the whole condition is true
+                let mut left = self.generate_condition(left)?;
+                let true_jump = self.push_branch(left.cond);
+                left.true_chain.push(true_jump);
+                self.fixup_chain(&left.false_chain, self.instructions.len());
+
+                let mut right = self.generate_condition(right)?;
+                left.true_chain.append(&mut right.true_chain);
+                Ok(Condition {
+                    cond: right.cond,
+                    true_chain: left.true_chain,
+                    false_chain: right.false_chain,
+                })
+            }
+
+            Expr::Not { operand, .. } => {
+                let condition = self.generate_condition(operand)?;
+                Ok(Condition {
+                    cond: negated(condition.cond),
+                    true_chain: condition.false_chain,
+                    false_chain: condition.true_chain,
+                })
+            }
+
+            &Expr::Boolean { value, .. } => Ok(Condition::new(if value { BranchCondition::AW } else { BranchCondition::NV })),
+
+            _ => {
+                // Boolean variables: loading the value sets the flags
+                self.generate_expression(expression)?;
+                self.rh -= 1;
+                Ok(Condition::new(BranchCondition::NE))
+            }
+        }
+    }
+
+    // Wirth's "CFJump": branches when the condition is false, and returns the chain
+    // of branches to fix once the destination is known.
+    fn generate_false_jump(&mut self, expression: &Expr) -> CodegenResult<Vec<usize>> {
+        let condition = self.generate_condition(expression)?;
+
+        let mut false_chain = condition.false_chain;
+        let false_jump = self.push_branch(negated(condition.cond));
+        false_chain.push(false_jump);
+
+        self.fixup_chain(&condition.true_chain, self.instructions.len());
+        Ok(false_chain)
+    }
+
+    // Puts the value of a condition (1 or 0) in a new register
+    fn load_condition(&mut self, expression: &Expr) -> CodegenResult {
+        let false_chain = self.generate_false_jump(expression)?;
+
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: self.rh, b: 0, im: 1 });
+        self.instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::AW,
+            link: false,
+            offset: 1,
+        });
+
+        self.fixup_chain(&false_chain, self.instructions.len());
+        self.instructions.push(Instruction::RegisterIm { o: MOV, a: self.rh, b: 0, im: 0 });
+
+        self.inc_rh()?;
+        Ok(())
+    }
+
+    // Branches to the entry of a table of branches, one for each value between low and high.
+    // Returns the chains of branches to each case, and the chain of branches to the else part.
+    fn generate_jump_table(&mut self, selector: usize, low: i32, high: i32, cases: &[Case]) -> (Vec<Vec<usize>>, Vec<usize>) {
+        let mut else_chain = vec![];
+
+        // Bounds check: the selector is rebased to 0 in the next register, and must be lower than the size of the table ;
+        // the selector itself is kept for the trap of a CASE without ELSE
+        let index = self.rh;
+        self.push_register_im(SUB, index, selector, low);
+        else_chain.push(self.push_branch(BranchCondition::LT));
+        self.instructions.push(Instruction::RegisterIm {
+            o: SUB,
+            a: index + 1,
+            b: index,
+            im: high - low,
+        });
+        else_chain.push(self.push_branch(BranchCondition::GT));
+
+        // NOTE(pht) code is loaded at address 0, so the index of the table is also its address ;
+        // the linker relocates it otherwise
+        let table_index = self.instructions.len() + 2;
+        self.push_relocation(RelocationTarget::Code(table_index));
+        self.instructions.push(Instruction::RegisterIm {
+            o: ADD,
+            a: index,
+            b: index,
+            im: table_index as i32,
+        });
+        self.instructions.push(Instruction::Branch {
+            cond: BranchCondition::AW,
+            c: index,
+            link: false,
+        });
+
+        let mut case_chains = vec![vec![]; cases.len()];
+        for value in low..=high {
+            let entry = self.push_branch(BranchCondition::AW);
+            match cases.iter().position(|case| case.labels.iter().any(|(low, high)| *low <= value && value <= *high)) {
+                Some(case_index) => case_chains[case_index].push(entry),
+                None => else_chain.push(entry),
+            }
+        }
+
+        (case_chains, else_chain)
+    }
+
+    // Compares the selector with each label in turn.
+    // Returns the chains of branches to each case, and the chain of branches to the else part.
+    fn generate_case_comparisons(&mut self, selector: usize, cases: &[Case]) -> (Vec<Vec<usize>>, Vec<usize>) {
+        let mut case_chains = vec![];
+        for case in cases.iter() {
+            let mut case_chain = vec![];
+            for &(low, high) in case.labels.iter() {
+                self.push_register_im(SUB, self.rh, selector, low);
+                if low == high {
+                    case_chain.push(self.push_branch(BranchCondition::EQ));
+                } else {
+                    // Skip the comparison with the upper bound if the selector is too low
+                    let skip = self.push_branch(BranchCondition::LT);
+                    self.push_register_im(SUB, self.rh, selector, high);
+                    case_chain.push(self.push_branch(BranchCondition::LE));
+                    self.fixup_chain(&[skip], self.instructions.len());
                 }
             }
+            case_chains.push(case_chain);
+        }
+
+        let else_chain = vec![self.push_branch(BranchCondition::AW)];
+        (case_chains, else_chain)
+    }
+
+    // R[register] <- value, in two instructions (MOV' and IOR) if the value does not fit in an immediate
+    fn load_constant(&mut self, register: usize, value: i32) {
+        if fits_in_immediate(value) {
+            self.instructions.push(Instruction::RegisterIm {
+                o: MOV,
+                a: register,
+                b: 0,
+                im: value,
+            });
+            return;
+        }
+
+        self.instructions.push(Instruction::MovHigh {
+            a: register,
+            im: ((value as u32) >> 16) as u16,
+        });
+        let low = value & 0xFFFF;
+        if low != 0 {
+            self.instructions.push(Instruction::RegisterIm {
+                o: IOR,
+                a: register,
+                b: register,
+                im: low,
+            });
+        }
+    }
+
+    // R[a] <- R[b] op value ; values that do not fit in an immediate go through the register after a and b
+    fn push_register_im(&mut self, o: OpCode, a: usize, b: usize, value: i32) {
+        if fits_in_immediate(value) {
+            self.instructions.push(Instruction::RegisterIm { o, a, b, im: value });
+        } else {
+            let scratch = a.max(b) + 1;
+            self.load_constant(scratch, value);
+            self.instructions.push(Instruction::Register { o, a, b, c: scratch });
         }
     }
 
-    fn last_expression_condition(&mut self) -> BranchCondition {
-        match self.last_expression_operator.unwrap() {
-            ExpressionOp::Eql => BranchCondition::NE,
-            ExpressionOp::Neq => BranchCondition::EQ,
-            ExpressionOp::Lss => BranchCondition::GE,
-            ExpressionOp::Leq => BranchCondition::GT,
-            ExpressionOp::Gtr => BranchCondition::LE,
-            ExpressionOp::Geq => BranchCondition::LT,
+    // NOTE(pht) values are kept in R0 to R11, so that R12 is left for the scratch computations
+    // of the top value, and the static base is never overwritten.
+    fn inc_rh(&mut self) -> CodegenResult {
+        self.rh += 1;
+        if self.rh >= SB {
+            return Err(CodegenError::ExpressionTooComplex);
         }
+        Ok(())
+    }
+
+    fn push_branch(&mut self, cond: BranchCondition) -> usize {
+        self.instructions.push(Instruction::BranchOff {
+            cond,
+            link: false,
+            offset: 0, // Offset will be fixedup later
+        });
+        self.instructions.len() - 1
+    }
+
+    fn fixup_chain(&mut self, chain: &[usize], destination_index: usize) {
+        for &branch_index in chain.iter() {
+            if let Instruction::BranchOff { cond, link, .. } = self.instructions[branch_index] {
+                self.instructions[branch_index] = Instruction::BranchOff {
+                    cond,
+                    link,
+                    offset: (destination_index as i32) - (branch_index as i32) - 1,
+                };
+            }
+        }
+    }
+}
+
+// Immediates are 16 bits, extended with ones when the 'v' modifier is set
+fn fits_in_immediate(value: i32) -> bool {
+    (-0x10000..=0xFFFF).contains(&value)
+}
+
+// Condition that holds when the relation is true
+fn relation_condition(operator: ExpressionOp) -> BranchCondition {
+    match operator {
+        ExpressionOp::Eql => BranchCondition::EQ,
+        ExpressionOp::Neq => BranchCondition::NE,
+        ExpressionOp::Lss => BranchCondition::LT,
+        ExpressionOp::Leq => BranchCondition::LE,
+        ExpressionOp::Gtr => BranchCondition::GT,
+        ExpressionOp::Geq => BranchCondition::GE,
+        ExpressionOp::In => BranchCondition::MI,
+    }
+}
+
+fn negated(cond: BranchCondition) -> BranchCondition {
+    match cond {
+        BranchCondition::MI => BranchCondition::PL,
+        BranchCondition::PL => BranchCondition::MI,
+        BranchCondition::EQ => BranchCondition::NE,
+        BranchCondition::NE => BranchCondition::EQ,
+        BranchCondition::LT => BranchCondition::GE,
+        BranchCondition::GE => BranchCondition::LT,
+        BranchCondition::LE => BranchCondition::GT,
+        BranchCondition::GT => BranchCondition::LE,
+        BranchCondition::AW => BranchCondition::NV,
+        BranchCondition::NV => BranchCondition::AW,
+    }
+}
+
+fn is_by_reference(symbol: &Symbol) -> bool {
+    matches!(symbol.kind, SymbolKind::Parameter { by_reference: true })
+}
+
+// Only variables can be passed by reference, or changed by predeclared procedures
+fn as_designator(expression: &Expr) -> CodegenResult<&Designator> {
+    match expression {
+        Expr::Designator(designator) => Ok(designator),
+        _ => Err(CodegenError::NotAVariable),
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::assert_matches::assert_matches;
+
     use super::*;
-    use ast::ast::{empty, leaf};
+    use crate::error::ErrorKind;
+    use ast::ast::{empty, info, leaf, node_at, Ast};
     use ast::parser;
     use ast::scanner::*;
     use ast::scope::Scope;
+    use ast::syntax;
+    use ast::token::Span;
+    use ast::tree::NodeInfo;
 
     #[cfg(test)]
     use pretty_assertions::assert_eq;
 
+    // Statements and expressions built by the parser are generated on their own, outside of a module
+    fn generate(codegen: &mut Codegen, tree: &Ast) -> Result<(), CompileError> {
+        let result = match info(tree) {
+            None => Ok(()),
+            Some(NodeInfo::StatementSequence(_)) => {
+                let statements = syntax::statements(tree)?;
+                codegen.generate_statements(&statements)
+            }
+            Some(NodeInfo::IfStatement) | Some(NodeInfo::WhileStatement) => {
+                let statement = syntax::statement(tree)?;
+                codegen.generate_statement(&statement)
+            }
+            Some(_) => {
+                let expression = syntax::expression(tree)?;
+                codegen.generate_expression(&expression)
+            }
+        };
+        result.map_err(|error| CompileError::codegen(error, codegen.context))
+    }
+
     #[test]
     fn generate_no_instruction_for_empty_tree() {
         let mut codegen = Codegen::new();
         let tree = empty();
-        codegen.generate_code(&tree);
+        generate(&mut codegen, &tree).unwrap();
         assert_eq!(codegen.instructions, vec![]);
     }
 
+    #[test]
+    fn fails_on_malformed_tree() {
+        let mut codegen = Codegen::new();
+        let error = generate(&mut codegen, &leaf(NodeInfo::Elsif)).unwrap_err();
+        assert_eq!(error.code(), "E402");
+
+        let context = ScanContext { line: 3, column: 2 };
+        let span = Span {
+            file: 0,
+            start: context,
+            end: context,
+        };
+        let statement = node_at(NodeInfo::Assignement, empty(), leaf(NodeInfo::String(String::from("abc"))), span);
+        let tree = node_at(NodeInfo::StatementSequence(context), statement, empty(), span);
+        let error = generate(&mut codegen, &tree).unwrap_err();
+        assert_matches!(error.kind, ErrorKind::Codegen(CodegenError::NotAVariable));
+        assert_eq!(error.context, Some(ScanContext { line: 3, column: 2 }));
+
+        let call = ast::ast::node(NodeInfo::StandardProcedureCall(StandardProcedure::Odd, 0), empty(), empty());
+        assert_matches!(generate(&mut codegen, &call).unwrap_err().kind, ErrorKind::Codegen(CodegenError::MissingNode));
+    }
+
     #[test]
     fn generate_load_instruction_for_single_ident() {
         let mut codegen = Codegen::new();
@@ -431,14 +1583,14 @@ mod tests {
 
         let tree = leaf(NodeInfo::Ident(symbol));
 
-        codegen.generate_code(&tree);
+        generate(&mut codegen, &tree).unwrap();
 
         assert_eq!(
             codegen.instructions,
             vec![Instruction::Memory {
                 u: MemoryMode::Load,
                 a: 0,
-                b: 14,
+                b: 13,
                 offset: 0
             }]
         )
@@ -455,7 +1607,7 @@ mod tests {
         let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -464,13 +1616,209 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 1
                 }
             ]
         )
     }
 
+    #[test]
+    fn generate_two_instructions_for_large_constants() {
+        let scope = Scope::new();
+        scope.add("x");
+        let mut scanner = Scanner::new("x:=-100000;x:=65536;x:=-65536");
+        parser::scan_next(&mut scanner).unwrap();
+        let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &assignement).unwrap();
+
+        let store = Instruction::Memory {
+            u: MemoryMode::Store,
+            a: 0,
+            b: 13,
+            offset: 0,
+        };
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                // -100000 is 0xFFFE7960
+                Instruction::MovHigh { a: 0, im: 0xFFFE },
+                Instruction::RegisterIm { o: IOR, a: 0, b: 0, im: 0x7960 },
+                store,
+                // No need for the IOR when the lower half is empty
+                Instruction::MovHigh { a: 0, im: 1 },
+                store,
+                // Fits in an immediate, with the 'v' modifier
+                Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: -65536 },
+                store,
+            ]
+        )
+    }
+
+    #[test]
+    fn generate_copy_loop_for_string_assignment() {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new("VAR s: ARRAY 4 OF CHAR; c: CHAR;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+
+        let mut scanner = Scanner::new("s := \"ab\"; c := \"z\"");
+        parser::scan_next(&mut scanner).unwrap();
+        let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &assignement).unwrap();
+
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                // Address of the string, fixed up at the end of the module
+                Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 0 },
+                Instruction::RegisterIm { o: ADD, a: 1, b: 13, im: 0 },
+                Instruction::RegisterIm { o: MOV, a: 2, b: 0, im: 3 },
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 3,
+                    b: 0,
+                    offset: 0
+                },
+                Instruction::RegisterIm { o: ADD, a: 0, b: 0, im: 1 },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 3,
+                    b: 1,
+                    offset: 0
+                },
+                Instruction::RegisterIm { o: ADD, a: 1, b: 1, im: 1 },
+                Instruction::RegisterIm { o: SUB, a: 2, b: 2, im: 1 },
+                Instruction::BranchOff {
+                    cond: BranchCondition::NE,
+                    link: false,
+                    offset: -6
+                },
+                // A single character is a value
+                Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 122 },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 13,
+                    offset: 4
+                },
+            ]
+        );
+        assert_eq!(codegen.data, vec![97, 98, 0]);
+    }
+
+    #[test]
+    fn generate_bitwise_instructions_for_sets() {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new("VAR s: SET; i: INTEGER;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+
+        let mut scanner = Scanner::new("s := {i} + s");
+        parser::scan_next(&mut scanner).unwrap();
+        let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &assignement).unwrap();
+
+        let load = |a, offset| Instruction::Memory {
+            u: MemoryMode::Load,
+            a,
+            b: 13,
+            offset,
+        };
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 0 },
+                load(1, 1),
+                Instruction::RegisterIm { o: MOV, a: 2, b: 0, im: 1 },
+                Instruction::Register { o: LSL, a: 1, b: 2, c: 1 },
+                Instruction::Register { o: IOR, a: 0, b: 0, c: 1 },
+                load(1, 0),
+                Instruction::Register { o: IOR, a: 0, b: 0, c: 1 },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 13,
+                    offset: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn generate_inline_instructions_for_inc() {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new("VAR x: INTEGER;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+
+        let mut scanner = Scanner::new("INC(x, 2)");
+        parser::scan_next(&mut scanner).unwrap();
+        let call = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &call).unwrap();
+
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 0,
+                    b: 13,
+                    offset: 0
+                },
+                Instruction::RegisterIm { o: ADD, a: 0, b: 0, im: 2 },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 13,
+                    offset: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn generate_store_to_console_device_for_out_char() {
+        let scope = Scope::new();
+        let mut scanner = Scanner::new("VAR c: CHAR;");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+
+        let mut scanner = Scanner::new("Out.Char(c)");
+        parser::scan_next(&mut scanner).unwrap();
+        let call = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &call).unwrap();
+
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 0,
+                    b: 13,
+                    offset: 0
+                },
+                Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: IO_CHAR },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 1,
+                    offset: 0
+                },
+            ]
+        );
+    }
+
     #[test]
     fn generate_load_instruction_for_array_assignment_at_constant() {
         let scope = Scope::new();
@@ -481,7 +1829,7 @@ mod tests {
         let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -490,7 +1838,7 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 2
                 }
             ]
@@ -508,7 +1856,7 @@ mod tests {
         let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -521,7 +1869,7 @@ mod tests {
                 // Move content of R0 to address 0
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
@@ -533,11 +1881,11 @@ mod tests {
                 // Put the content of i (address 0) in R1
                 Instruction::Memory {
                     a: 1,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Load,
                 },
-                Instruction::Register { a: 1, b: 1, o: ADD, c: 14 },
+                Instruction::Register { a: 1, b: 1, o: ADD, c: 13 },
                 // Put the content of R0 in address R1 + offset
                 Instruction::Memory {
                     a: 0,
@@ -549,6 +1897,98 @@ mod tests {
         )
     }
 
+    #[test]
+    fn generate_load_instruction_for_field_of_array_element_at_variable() {
+        let scope = Scope::new();
+        scope.add("i"); // At address 0
+        let mut scanner = Scanner::new("TYPE Point = RECORD x, y: INTEGER END; VAR p: ARRAY 3 OF Point; BEGIN i := p[i].y");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+        // Skip the BEGIN
+        parser::scan_next(&mut scanner).unwrap();
+        let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        generate(&mut codegen, &assignement).unwrap();
+
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                // Load i in R1, check that it is in [0, 3), and turn it into the address of p[i]
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 1,
+                    b: 13,
+                    offset: 0
+                },
+                Instruction::Register { o: MOV, a: 2, b: 0, c: 1 },
+                Instruction::Trap {
+                    cond: BranchCondition::LT,
+                    c: 1,
+                    code: TrapCode::ArrayIndexOutOfBounds,
+                    line: 1
+                },
+                Instruction::RegisterIm { o: SUB, a: 2, b: 1, im: 3 },
+                Instruction::Trap {
+                    cond: BranchCondition::GE,
+                    c: 1,
+                    code: TrapCode::ArrayIndexOutOfBounds,
+                    line: 1
+                },
+                Instruction::RegisterIm { o: MUL, a: 1, b: 1, im: 2 },
+                Instruction::Register { o: ADD, a: 1, b: 1, c: 13 },
+                // p is at address 1, y at offset 1 in the record
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 0,
+                    b: 1,
+                    offset: 2
+                },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 13,
+                    offset: 0
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn generate_no_bounds_checks_when_disabled() {
+        let scope = Scope::new();
+        scope.add("i"); // At address 0
+        let mut scanner = Scanner::new("VAR a: ARRAY 3 OF INTEGER; BEGIN a[i] := 1");
+        parser::scan_next(&mut scanner).unwrap();
+        parser::parse_declarations(&mut scanner, &scope, &mut |_, _| Ok(empty())).unwrap();
+        parser::scan_next(&mut scanner).unwrap();
+        let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
+
+        let mut codegen = Codegen::new();
+        codegen.bounds_checks = false;
+        generate(&mut codegen, &assignement).unwrap();
+
+        assert_eq!(
+            codegen.instructions,
+            vec![
+                Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 1 },
+                Instruction::Memory {
+                    u: MemoryMode::Load,
+                    a: 1,
+                    b: 13,
+                    offset: 0
+                },
+                Instruction::Register { o: ADD, a: 1, b: 1, c: 13 },
+                Instruction::Memory {
+                    u: MemoryMode::Store,
+                    a: 0,
+                    b: 1,
+                    offset: 1
+                },
+            ]
+        )
+    }
+
     #[test]
     fn generate_load_instruction_for_multiple_assignments() {
         let scope = Scope::new();
@@ -560,7 +2000,7 @@ mod tests {
         let assignement = parser::parse_statement_sequence(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -569,19 +2009,19 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 1
                 },
                 Instruction::Memory {
                     u: MemoryMode::Load,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 1
                 },
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
             ]
@@ -599,7 +2039,7 @@ mod tests {
         let assignement = parser::parse_term(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -608,14 +2048,14 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Load,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
                 // Load ident Y
                 Instruction::Memory {
                     u: MemoryMode::Load,
                     a: 1,
-                    b: 14,
+                    b: 13,
                     offset: 1
                 },
                 // Multiply RH,RH,RH+1
@@ -635,7 +2075,7 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -649,7 +2089,7 @@ mod tests {
                 // Branch if not equals to fixed-up location
                 Instruction::BranchOff {
                     cond: BranchCondition::NE,
-                    offset: 3,
+                    offset: 2,
                     link: false
                 },
                 // Load 1
@@ -658,7 +2098,7 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
             ]
@@ -676,7 +2116,7 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -700,13 +2140,13 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
                 // Branch to the avoid the 'else' part
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 3,
+                    offset: 2,
                     link: false
                 },
                 // (Else part)
@@ -716,7 +2156,7 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 }
             ]
@@ -745,7 +2185,7 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -777,12 +2217,12 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 3,
+                    offset: 2,
                     link: false
                 },
                 //  ELSE
@@ -791,12 +2231,12 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 3,
+                    offset: 2,
                     link: false
                 },
                 //  END
@@ -807,7 +2247,7 @@ mod tests {
                 Instruction::Memory {
                     u: MemoryMode::Store,
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0
                 }
             ]
@@ -836,7 +2276,7 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
@@ -852,13 +2292,13 @@ mod tests {
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 1 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 10,
+                    offset: 9,
                     link: false,
                 },
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 0 },
@@ -872,19 +2312,19 @@ mod tests {
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 2 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 3,
+                    offset: 2,
                     link: false,
                 },
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 3 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
@@ -918,11 +2358,9 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
         println!("{:#?}", codegen.instructions);
 
-        // TODO(pht) some of the offset in this code seem to be are wrong ; or maybe some AW instructions must be added.
-        // Try to find the culprit and fix it.
         assert_eq!(
             codegen.instructions,
             [
@@ -940,13 +2378,13 @@ mod tests {
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 1 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 16,
+                    offset: 15,
                     link: false,
                 },
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 0 },
@@ -960,7 +2398,7 @@ mod tests {
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 2 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store
                 },
@@ -969,25 +2407,25 @@ mod tests {
                 Instruction::Register { a: 0, b: 0, o: SUB, c: 1 },
                 Instruction::BranchOff {
                     cond: BranchCondition::NE,
-                    offset: 3,
+                    offset: 2,
                     link: false,
                 },
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 3 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
                 Instruction::BranchOff {
                     cond: BranchCondition::AW,
-                    offset: 3,
+                    offset: 2,
                     link: false,
                 },
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 4 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
@@ -1007,7 +2445,7 @@ mod tests {
         let assignement = parser::parse_if_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         println!("{:#?}", codegen.instructions);
         assert!(false);
@@ -1031,14 +2469,14 @@ mod tests {
         let assignement = parser::parse_while_statement(&mut scanner, &scope).unwrap();
 
         let mut codegen = Codegen::new();
-        codegen.generate_code(&assignement);
+        generate(&mut codegen, &assignement).unwrap();
 
         assert_eq!(
             codegen.instructions,
             [
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Load
                 },
@@ -1052,7 +2490,7 @@ mod tests {
                 Instruction::RegisterIm { a: 0, b: 0, o: MOV, im: 1 },
                 Instruction::Memory {
                     a: 0,
-                    b: 14,
+                    b: 13,
                     offset: 0,
                     u: MemoryMode::Store,
                 },
@@ -0,0 +1,420 @@
+// Compile errors, as displayed to users.
+//
+// Plain and ANSI outputs look like the messages of other compilers :
+//
+//   error[E301]: mismatched types
+//    --> Test.mod:3:8
+//     |
+//   3 |   x := TRUE
+//     |        ^^^^
+//     = expected INTEGER, found BOOLEAN
+//
+// The JSON output puts each error on a single line, for editors to read.
+use std::str::FromStr;
+
+use ast::parser::ParseError;
+use ast::token::{ScanErrorType, Token};
+use ast::tree::VarType;
+
+use crate::error::{CodegenError, CompileError, ErrorKind};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Plain,
+    Ansi,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "ansi" => Ok(Format::Ansi),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown error format {:?}, expected plain, ansi or json", s)),
+        }
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    // Counting from 1, like editors do
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    // Number of characters under the caret
+    pub width: usize,
+    pub expected: Option<String>,
+    pub found: Option<String>,
+    pub hint: Option<&'static str>,
+}
+
+pub fn render(error: &CompileError, file: &str, source: &str, format: Format) -> String {
+    Diagnostic::new(error, source).render(file, source, format)
+}
+
+// Errors are separated by an empty line, except in JSON where there is one per line
+pub fn render_all(errors: &[CompileError], file: &str, source: &str, format: Format) -> String {
+    let separator = if format == Format::Json { "" } else { "\n" };
+    errors.iter().map(|error| render(error, file, source, format)).collect::<Vec<_>>().join(separator)
+}
+
+impl Diagnostic {
+    pub fn new(error: &CompileError, source: &str) -> Diagnostic {
+        let (message, expected, found) = describe(&error.kind);
+        let location = error.context.map(|context| (context.line as usize, context.column as usize));
+        let word = location.and_then(|(line, column)| word_at(source, line, column));
+
+        // Tokens are shown as they were written
+        let found = match (&error.kind, &word) {
+            (ErrorKind::Parse(ParseError::UnexpectedToken { .. }), Some(word)) => Some(format!("`{}`", word)),
+            _ => found,
+        };
+
+        Diagnostic {
+            code: error.code(),
+            message,
+            line: location.map(|(line, _)| line + 1),
+            column: location.map(|(_, column)| column + 1),
+            width: word.map_or(1, |word| word.chars().count().max(1)),
+            expected,
+            found,
+            hint: hint(&error.kind),
+        }
+    }
+
+    pub fn render(&self, file: &str, source: &str, format: Format) -> String {
+        match format {
+            Format::Plain => self.render_text(file, source, ["", "", "", ""]),
+            Format::Ansi => self.render_text(file, source, [RED, BLUE, BOLD, RESET]),
+            Format::Json => self.render_json(file),
+        }
+    }
+
+    fn render_text(&self, file: &str, source: &str, [red, blue, bold, reset]: [&str; 4]) -> String {
+        let mut text = format!("{red}error[{}]{reset}{bold}: {}{reset}\n", self.code, self.message);
+
+        let source_line = self.line.and_then(|line| source.lines().nth(line - 1));
+        let gutter = " ".repeat(self.line.map_or(0, |line| line.to_string().len()));
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => text.push_str(&format!("{gutter}{blue}-->{reset} {file}:{line}:{column}\n")),
+            _ => text.push_str(&format!("{gutter}{blue}-->{reset} {file}\n")),
+        }
+
+        if let (Some(line), Some(column), Some(source_line)) = (self.line, self.column, source_line) {
+            let caret = format!("{}{red}{}{reset}", " ".repeat(column - 1), "^".repeat(self.width));
+            text.push_str(&format!("{gutter} {blue}|{reset}\n"));
+            text.push_str(&format!("{blue}{line} |{reset} {source_line}\n"));
+            text.push_str(&format!("{gutter} {blue}|{reset} {caret}\n"));
+        }
+
+        match (&self.expected, &self.found) {
+            (Some(expected), Some(found)) => text.push_str(&format!("{gutter} {blue}={reset} expected {expected}, found {found}\n")),
+            (Some(expected), None) => text.push_str(&format!("{gutter} {blue}={reset} expected {expected}\n")),
+            (None, Some(found)) => text.push_str(&format!("{gutter} {blue}={reset} found {found}\n")),
+            (None, None) => {}
+        }
+        if let Some(hint) = self.hint {
+            text.push_str(&format!("{gutter} {blue}={reset} {bold}hint{reset}: {hint}\n"));
+        }
+        text
+    }
+
+    fn render_json(&self, file: &str) -> String {
+        let number = |value: Option<usize>| value.map_or(String::from("null"), |value| value.to_string());
+        let string = |value: Option<&str>| value.map_or(String::from("null"), json_string);
+        format!(
+            "{{\"file\":{},\"line\":{},\"column\":{},\"code\":{},\"message\":{},\"expected\":{},\"found\":{},\"hint\":{}}}\n",
+            json_string(file),
+            number(self.line),
+            number(self.column),
+            json_string(self.code),
+            json_string(&self.message),
+            string(self.expected.as_deref()),
+            string(self.found.as_deref()),
+            string(self.hint),
+        )
+    }
+}
+
+// Message, and what was expected and found instead when it is known
+fn describe(kind: &ErrorKind) -> (String, Option<String>, Option<String>) {
+    let message = |message: String| (message, None, None);
+    match kind {
+        ErrorKind::Scan(error_type) => match error_type {
+            ScanErrorType::InvalidChar(c) => message(format!("invalid character {:?}", c)),
+            ScanErrorType::UnexpectedNewLine => message(String::from("unexpected new line")),
+            ScanErrorType::UnterminatedComment => message(String::from("unterminated comment")),
+            ScanErrorType::IntegerOverflow => message(String::from("integer literal is too large")),
+            ScanErrorType::InvalidCharacterCode => message(String::from("invalid character code")),
+            ScanErrorType::UnterminatedString => message(String::from("unterminated string")),
+        },
+        ErrorKind::Parse(error) | ErrorKind::Semantic(error) => match error {
+            ParseError::ScanError(scan_error) => message(format!("invalid input {:?}", scan_error.error_type)),
+            ParseError::UndefinedSymbol(name) => message(format!("undefined symbol `{}`", name)),
+            ParseError::PrematureEof => (String::from("unexpected end of file"), None, Some(String::from("end of file"))),
+            ParseError::UnexpectedToken { expected, found } => (String::from("unexpected token"), token_names(expected), Some(token_name(&found.token))),
+            ParseError::SymbolAlreadyDeclared(name, _) => message(format!("`{}` is already declared", name)),
+            ParseError::UnexpectedBlockEnding { expected, found } => (String::from("block ends with another name"), Some(format!("`{}`", expected)), Some(format!("`{}`", found))),
+            ParseError::InaccessibleSymbol(name) => message(format!("`{}` belongs to an enclosing procedure", name)),
+            ParseError::ParameterCountMismatch { procedure, expected, found } => (
+                format!("wrong number of parameters for `{}`", procedure),
+                Some(format!("{} parameters", expected)),
+                Some(found.to_string()),
+            ),
+            ParseError::TooManyParameters(name, _) => message(format!("`{}` has too many parameters", name)),
+            ParseError::NotAType(name) => message(format!("`{}` is not a type", name)),
+            ParseError::DuplicateCaseLabel(label) => message(format!("duplicate case label {}", label)),
+            ParseError::NotAConstant(name) => message(format!("`{}` is not a constant", name)),
+            ParseError::NotARecord(field) => message(format!("field `{}` of a variable that is not a record", field)),
+            ParseError::IndexOutOfBounds(index) => message(format!("index {} is out of the bounds of the array", index)),
+            ParseError::UndefinedField(field) => message(format!("undefined field `{}`", field)),
+            ParseError::StringNotAssignable(value, _) => message(format!("string {:?} can not be assigned here", value)),
+            ParseError::SetElementOutOfRange(element) => message(format!("set element {} is out of range", element)),
+            ParseError::InvalidConstantExpression => message(String::from("invalid constant expression")),
+            ParseError::UndefinedModule(module) => message(format!("no symbol file for module `{}`", module)),
+            ParseError::InvalidSymbolFile(line) => message(format!("invalid symbol file line {:?}", line)),
+            ParseError::ReadOnlyVariable(name) => message(format!("`{}` is read-only here", name)),
+            ParseError::TypeMismatch { expected, found, .. } => (String::from("mismatched types"), Some(type_name(expected)), Some(type_name(found))),
+            ParseError::NotAnArray(name, _) => message(format!("`{}` is not an array", name)),
+            ParseError::NotAssignable(name, _) => message(format!("`{}` can not be assigned as a whole", name)),
+        },
+        ErrorKind::Codegen(error) => match error {
+            CodegenError::MissingNode => message(String::from("malformed syntax tree")),
+            CodegenError::UnexpectedNode(node) => message(format!("unexpected node {}", node)),
+            CodegenError::NotAVariable => message(String::from("expected a variable")),
+            CodegenError::NotAProcedure(name) => message(format!("`{}` is not a procedure", name)),
+            CodegenError::UndefinedProcedure(name) => message(format!("no code for procedure `{}`", name)),
+            CodegenError::StringNotAValue(value) => message(format!("string {:?} can not be used as a value", value)),
+            CodegenError::ExpressionTooComplex => message(String::from("expression is too complex")),
+            CodegenError::TooManyParameters(name) => message(format!("`{}` has too many parameters", name)),
+        },
+    }
+}
+
+fn hint(kind: &ErrorKind) -> Option<&'static str> {
+    match kind {
+        ErrorKind::Scan(ScanErrorType::UnterminatedComment) => Some("comments end with `*)`"),
+        ErrorKind::Scan(ScanErrorType::InvalidCharacterCode) => Some("character codes are hexadecimal, below 100X"),
+        ErrorKind::Scan(ScanErrorType::UnterminatedString) => Some("strings can not span lines"),
+        ErrorKind::Parse(error) | ErrorKind::Semantic(error) => match error {
+            ParseError::InaccessibleSymbol(_) => Some("pass it to the procedure as a parameter"),
+            ParseError::StringNotAssignable(_, _) => Some("strings need an array of characters with room for the terminating 0X"),
+            ParseError::SetElementOutOfRange(_) => Some("set elements go from 0 to 31"),
+            ParseError::UndefinedModule(_) => Some("compile the imported module first, or add its directory with -I"),
+            ParseError::ReadOnlyVariable(_) => Some("variables exported with `-` can only be changed by their own module"),
+            ParseError::TooManyParameters(_, _) => Some("parameters are passed in registers R0 to R11, so procedures take at most 12 of them"),
+            ParseError::NotAssignable(_, _) => Some("assign the elements or fields one by one"),
+            _ => None,
+        },
+        ErrorKind::Codegen(CodegenError::ExpressionTooComplex) => Some("split the expression with intermediate variables"),
+        ErrorKind::Codegen(CodegenError::TooManyParameters(_)) => Some("parameters are passed in registers R0 to R11, so procedures take at most 12 of them"),
+        ErrorKind::Codegen(_) => Some("the program should have been rejected before code generation, this is a bug of the compiler"),
+        _ => None,
+    }
+}
+
+fn type_name(var_type: &VarType) -> String {
+    match var_type {
+        VarType::Integer => String::from("INTEGER"),
+        VarType::Boolean => String::from("BOOLEAN"),
+        VarType::Char => String::from("CHAR"),
+        VarType::Set => String::from("SET"),
+        VarType::Array(capacity, element_type) => format!("ARRAY {} OF {}", capacity, type_name(element_type)),
+        VarType::Record(_) => String::from("RECORD"),
+    }
+}
+
+// `A`, `A or B`, `A, B or C`, none when nothing in particular was expected
+fn token_names(tokens: &[Token]) -> Option<String> {
+    let names: Vec<String> = tokens.iter().map(token_name).collect();
+    match names.split_last() {
+        None => None,
+        Some((last, [])) => Some(last.clone()),
+        Some((last, others)) => Some(format!("{} or {}", others.join(", "), last)),
+    }
+}
+
+// Tokens as they are written, identifiers and literals by their kind
+fn token_name(token: &Token) -> String {
+    let name = match token {
+        Token::Ident(_) => return String::from("identifier"),
+        Token::Int(_) => return String::from("number"),
+        Token::Char(_) => return String::from("character"),
+        Token::String(_) => return String::from("string"),
+        Token::Times => "*",
+        Token::Rdiv => "/",
+        Token::Div => "DIV",
+        Token::Mod => "MOD",
+        Token::And => "&",
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Or => "OR",
+        Token::Eql => "=",
+        Token::Neq => "#",
+        Token::Lss => "<",
+        Token::Geq => ">=",
+        Token::Leq => "<=",
+        Token::Gtr => ">",
+        Token::In => "IN",
+        Token::Period => ".",
+        Token::Upto => "..",
+        Token::Bar => "|",
+        Token::Comma => ",",
+        Token::Colon => ":",
+        Token::Rparen => ")",
+        Token::Rbrak => "]",
+        Token::Rbrace => "}",
+        Token::Of => "OF",
+        Token::Then => "THEN",
+        Token::Do => "DO",
+        Token::Lparen => "(",
+        Token::Lbrak => "[",
+        Token::Lbrace => "{",
+        Token::Not => "~",
+        Token::Becomes => ":=",
+        Token::Semicolon => ";",
+        Token::End => "END",
+        Token::Else => "ELSE",
+        Token::Elsif => "ELSIF",
+        Token::If => "IF",
+        Token::While => "WHILE",
+        Token::Case => "CASE",
+        Token::Array => "ARRAY",
+        Token::Record => "RECORD",
+        Token::Const => "CONST",
+        Token::Type => "TYPE",
+        Token::Var => "VAR",
+        Token::Procedure => "PROCEDURE",
+        Token::Begin => "BEGIN",
+        Token::Module => "MODULE",
+        Token::Import => "IMPORT",
+        Token::False => "FALSE",
+        Token::True => "TRUE",
+        Token::Repeat => "REPEAT",
+        Token::Until => "UNTIL",
+        Token::For => "FOR",
+        Token::To => "TO",
+        Token::By => "BY",
+    };
+    format!("`{}`", name)
+}
+
+// Identifier, keyword, number or string starting at a location (counting from 0), or the single character found there
+fn word_at(source: &str, line: usize, column: usize) -> Option<String> {
+    let mut chars = source.lines().nth(line)?.chars().skip(column).peekable();
+    let first = *chars.peek()?;
+    if first == '"' {
+        let content: String = chars.skip(1).take_while(|c| *c != '"').collect();
+        Some(format!("\"{}\"", content))
+    } else if first.is_ascii_alphanumeric() {
+        Some(chars.take_while(char::is_ascii_alphanumeric).collect())
+    } else {
+        Some(first.to_string())
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_render_plain_errors() {
+        let source = "MODULE Test; VAR x: INTEGER;\nBEGIN x := 1;\n  x := TRUE\nEND Test.";
+        let error = crate::compile(source).unwrap_err();
+        assert_eq!(
+            render(&error, "Test.mod", source, Format::Plain),
+            "error[E301]: mismatched types
+ --> Test.mod:3:8
+  |
+3 |   x := TRUE
+  |        ^^^^
+  = expected INTEGER, found BOOLEAN
+"
+        );
+
+        let source = "MODULE Test; VAR x: INTEGER; BEGIN IF x = 0 THEN x := 1 ELSE IF END Test.";
+        let error = crate::compile(source).unwrap_err();
+        let rendered = render(&error, "Test.mod", source, Format::Plain);
+        assert!(rendered.contains(" --> Test.mod:1:65\n"), "{}", rendered);
+        assert!(
+            rendered.ends_with(&format!(
+                "  | {}^^^\n  = expected identifier, number, character, string, `(`, `{{`, `~`, `TRUE` or `FALSE`, found `END`\n",
+                " ".repeat(64)
+            )),
+            "{}",
+            rendered
+        );
+
+        let source = "MODULE Test; VAR s: ARRAY 3 OF CHAR;\nBEGIN s := \"abcd\" END Test.";
+        let error = crate::compile(source).unwrap_err();
+        let rendered = render(&error, "Test.mod", source, Format::Plain);
+        assert!(rendered.contains(" --> Test.mod:2:12\n"), "{}", rendered);
+        assert!(rendered.contains("\n  |            ^^^^^^\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn can_render_hints_without_location() {
+        let source = "MODULE Test; IMPORT Shapes; END Test.";
+        let error = crate::compile(source).unwrap_err();
+        let rendered = render(&error, "Test.mod", source, Format::Plain);
+        assert!(rendered.starts_with("error[E217]: no symbol file for module `Shapes`\n"), "{}", rendered);
+        assert!(
+            rendered.ends_with("= hint: compile the imported module first, or add its directory with -I\n"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn can_render_ansi_errors() {
+        let source = "MODULE Test; VAR s: ARRAY 3 OF INTEGER; BEGIN s := 0 END Test.";
+        let error = crate::compile(source).unwrap_err();
+        let rendered = render(&error, "Test.mod", source, Format::Ansi);
+        assert!(rendered.starts_with("\x1b[1;31merror[E303]\x1b[0m\x1b[1m: `s` can not be assigned as a whole\x1b[0m\n"));
+        assert!(rendered.contains("\x1b[1;34m1 |\x1b[0m MODULE Test;"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m\n"));
+    }
+
+    #[test]
+    fn can_render_json_errors() {
+        let source = "MODULE Test; VAR c: CHAR; BEGIN c := \"ab\" END Test.";
+        let error = crate::compile(source).unwrap_err();
+        assert_eq!(
+            render(&error, "src/Test.mod", source, Format::Json),
+            "{\"file\":\"src/Test.mod\",\"line\":1,\"column\":38,\"code\":\"E214\",\"message\":\"string \\\"ab\\\" can not be assigned here\",\
+             \"expected\":null,\"found\":null,\"hint\":\"strings need an array of characters with room for the terminating 0X\"}\n"
+        );
+
+        let source = "MODULE Test; VAR c CHAR; END Test.";
+        let error = crate::compile(source).unwrap_err();
+        assert_eq!(
+            render(&error, "src/Test.mod", source, Format::Json),
+            "{\"file\":\"src/Test.mod\",\"line\":1,\"column\":20,\"code\":\"E203\",\"message\":\"unexpected token\",\
+             \"expected\":\"`:`\",\"found\":\"`CHAR`\",\"hint\":null}\n"
+        );
+    }
+}
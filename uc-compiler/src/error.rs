@@ -0,0 +1,135 @@
+// Errors of the compiler, whatever the phase that finds them.
+//
+// Each error has a stable code, that tools and documentation can refer to, and the location of
+// the token it was found at. Errors that do not carry their own location are located at the token
+// the parser stopped at, or at the statement being generated.
+use ast::parser::ParseError;
+use ast::syntax::TreeError;
+use ast::token::{ScanContext, ScanError, ScanErrorType};
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub kind: ErrorKind,
+    pub context: Option<ScanContext>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Scan(ScanErrorType),
+    Parse(ParseError),
+    // Found by the checker, once the module is parsed
+    Semantic(ParseError),
+    Codegen(CodegenError),
+}
+
+// NOTE(pht) the parser and the checker should not let those happen, they are reported
+// instead of crashing the compiler when they do
+#[derive(Debug, PartialEq)]
+pub enum CodegenError {
+    // A part of a node that the parser always builds is missing
+    MissingNode,
+    // A node that is generated along with its parent, like a selector or a CASE label, found on its own
+    UnexpectedNode(String),
+    // Only variables can be changed or passed by reference
+    NotAVariable,
+    NotAProcedure(String),
+    // A procedure that is called, but whose code was never generated
+    UndefinedProcedure(String),
+    // Strings longer than one character are only copied by assignments, or printed
+    StringNotAValue(String),
+    // Values of an expression are kept in registers, and there are not enough of them
+    ExpressionTooComplex,
+    // Parameters are passed in registers, and there are not enough of them
+    TooManyParameters(String),
+}
+
+impl CompileError {
+    pub fn codegen(error: CodegenError, context: Option<ScanContext>) -> CompileError {
+        CompileError {
+            kind: ErrorKind::Codegen(error),
+            context,
+        }
+    }
+
+    // Codes are never reused: new errors get a new code, even when an old one is removed
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            ErrorKind::Scan(error_type) => match error_type {
+                ScanErrorType::InvalidChar(_) => "E101",
+                ScanErrorType::UnexpectedNewLine => "E102",
+                ScanErrorType::UnterminatedComment => "E103",
+                ScanErrorType::IntegerOverflow => "E104",
+                ScanErrorType::InvalidCharacterCode => "E105",
+                ScanErrorType::UnterminatedString => "E106",
+            },
+            ErrorKind::Parse(error) | ErrorKind::Semantic(error) => match error {
+                // Scan errors are taken out of parse errors when the compile error is built
+                ParseError::ScanError(_) => "E100",
+                ParseError::UndefinedSymbol(_) => "E201",
+                ParseError::PrematureEof => "E202",
+                ParseError::UnexpectedToken { .. } => "E203",
+                ParseError::SymbolAlreadyDeclared(_, _) => "E204",
+                ParseError::UnexpectedBlockEnding { .. } => "E205",
+                ParseError::InaccessibleSymbol(_) => "E206",
+                ParseError::ParameterCountMismatch { .. } => "E207",
+                ParseError::NotAType(_) => "E208",
+                ParseError::DuplicateCaseLabel(_) => "E209",
+                ParseError::NotAConstant(_) => "E210",
+                ParseError::NotARecord(_) => "E211",
+                ParseError::IndexOutOfBounds(_) => "E212",
+                ParseError::UndefinedField(_) => "E213",
+                ParseError::StringNotAssignable(_, _) => "E214",
+                ParseError::SetElementOutOfRange(_) => "E215",
+                ParseError::InvalidConstantExpression => "E216",
+                ParseError::UndefinedModule(_) => "E217",
+                ParseError::InvalidSymbolFile(_) => "E218",
+                ParseError::ReadOnlyVariable(_) => "E219",
+                ParseError::TooManyParameters(_, _) => "E220",
+                ParseError::TypeMismatch { .. } => "E301",
+                ParseError::NotAnArray(_, _) => "E302",
+                ParseError::NotAssignable(_, _) => "E303",
+            },
+            ErrorKind::Codegen(error) => match error {
+                CodegenError::MissingNode => "E401",
+                CodegenError::UnexpectedNode(_) => "E402",
+                CodegenError::NotAVariable => "E403",
+                CodegenError::NotAProcedure(_) => "E404",
+                CodegenError::UndefinedProcedure(_) => "E405",
+                CodegenError::StringNotAValue(_) => "E406",
+                CodegenError::ExpressionTooComplex => "E407",
+                CodegenError::TooManyParameters(_) => "E408",
+            },
+        }
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(error: ParseError) -> CompileError {
+        let context = match &error {
+            ParseError::ScanError(scan_error) => Some(scan_error.context),
+            ParseError::UnexpectedToken { found, .. } => Some(found.context),
+            ParseError::SymbolAlreadyDeclared(_, context) | ParseError::TooManyParameters(_, context) | ParseError::StringNotAssignable(_, context) => Some(*context),
+            ParseError::TypeMismatch { context, .. } => Some(*context),
+            ParseError::NotAnArray(_, context) | ParseError::NotAssignable(_, context) => Some(*context),
+            _ => None,
+        };
+        let kind = match error {
+            ParseError::ScanError(ScanError { error_type, .. }) => ErrorKind::Scan(error_type),
+            ParseError::TypeMismatch { .. } | ParseError::NotAnArray(_, _) | ParseError::NotAssignable(_, _) => ErrorKind::Semantic(error),
+            _ => ErrorKind::Parse(error),
+        };
+        CompileError { kind, context }
+    }
+}
+
+// Trees that can not be converted to a syntax tree are reported like the code generator would
+impl From<TreeError> for CompileError {
+    fn from(error: TreeError) -> CompileError {
+        let (error, span) = match error {
+            TreeError::MissingNode(span) => (CodegenError::MissingNode, span),
+            TreeError::UnexpectedNode(node, span) => (CodegenError::UnexpectedNode(node), span),
+            TreeError::NotAVariable(span) => (CodegenError::NotAVariable, span),
+        };
+        CompileError::codegen(error, Some(span.start))
+    }
+}
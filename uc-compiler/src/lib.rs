@@ -1,47 +1,174 @@
+#![feature(assert_matches)]
+use ast::ast::Ast;
 use ast::parser;
-use ast::parser::ParseResult;
+use ast::parser::{Importer, ParseErrors};
 use ast::scanner::*;
 use ast::scope::*;
+use ast::symbol_file;
+use ast::syntax;
+use ast::syntax::Module;
 use risc::instructions::*;
+use risc::object::{Object, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, Section};
 
 mod codegen;
+pub mod diagnostics;
+mod error;
 
-// NOTE(pht) alternatively, I might want a "CompileError", when there will be other possible errors...
-// or just rename the thing...
 pub use ast::parser::ParseError;
+pub use error::{CodegenError, CompileError, ErrorKind};
 
-pub fn compile(input: &str) -> std::result::Result<Vec<Instruction>, ParseError> {
-    let ast = build_ast(input)?;
+#[derive(Debug, Copy, Clone)]
+pub struct CompileOptions {
+    // Trap when an array is accessed out of its bounds, at the cost of a few instructions per access
+    pub bounds_checks: bool,
+}
 
-    let mut codegen = codegen::Codegen::new();
-    codegen.generate_code(&ast);
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions { bounds_checks: true }
+    }
+}
 
-    let mut instructions = codegen.instructions;
+// Code to load at address 0, followed by the data area and the static area
+#[derive(Debug)]
+pub struct Program {
+    pub module: String,
+    pub instructions: Vec<Instruction>,
+    pub data: Vec<i32>,
+    // Size of the static area holding the module variables
+    pub variables: usize,
+    // Instructions referring to symbols of imported modules, with the module and the name of the symbol
+    pub externals: Vec<(usize, String, String)>,
+    // Instructions holding the address of some code or data of the module
+    pub relocations: Vec<Relocation>,
+    // Exported procedures and variables, with their offset in the code or in the static area
+    pub exports: Vec<ObjectSymbol>,
+    // Exports of the module, to be read when compiling its importers
+    pub symbol_file: String,
+}
+
+impl Program {
+    pub fn data_address(&self) -> usize {
+        self.instructions.len()
+    }
 
-    // NOTE(pht) add the two instructions to return, in any case, at the end
-    // of the generated code.
-    // Or this could be done by codegen ?
-    instructions.push(Instruction::RegisterIm {
-        o: OpCode::MOV,
-        a: 15,
-        b: 0,
-        im: 0,
-    });
-    instructions.push(Instruction::Branch {
-        cond: BranchCondition::AW,
-        c: 15,
-        link: false,
-    });
+    pub fn static_base(&self) -> usize {
+        self.instructions.len() + self.data.len()
+    }
 
-    Ok(instructions)
+    // Relocatable object, to be linked with the objects of the imported modules
+    pub fn object(&self) -> Object {
+        let mut object = Object::new(&self.module);
+        object.body = true;
+        object.code = self.instructions.clone();
+        object.data = self.data.clone();
+        object.variables = self.variables;
+        object.exports = self.exports.clone();
+        object.relocations = self.relocations.clone();
+        for (index, module, name) in self.externals.iter() {
+            let import = object.import(module, name);
+            // Imported procedures are called with a branch, imported variables are reached through their address
+            let kind = match self.instructions[*index] {
+                Instruction::BranchOff { .. } => RelocationKind::Branch,
+                _ => RelocationKind::Absolute,
+            };
+            object.relocations.push(Relocation {
+                index: *index,
+                kind,
+                target: RelocationTarget::Import(import),
+            });
+        }
+        object
+    }
 }
 
+pub fn compile(input: &str) -> std::result::Result<Program, CompileError> {
+    compile_with_options(input, CompileOptions::default())
+}
 
-pub fn build_ast(input: &str) -> ParseResult {
+pub fn compile_with_options(input: &str, options: CompileOptions) -> std::result::Result<Program, CompileError> {
+    compile_with_imports(input, options, &mut |module| Err(ParseError::UndefinedModule(String::from(module))))
+}
+
+pub fn compile_with_imports(input: &str, options: CompileOptions, import: &mut Importer) -> std::result::Result<Program, CompileError> {
+    // There is always at least one error
+    compile_with_recovery(input, options, import).map_err(|mut errors| errors.remove(0))
+}
+
+// All the syntax errors of the module are reported, instead of the first one
+pub fn compile_with_recovery(input: &str, options: CompileOptions, import: &mut Importer) -> std::result::Result<Program, Vec<CompileError>> {
     let mut scanner = Scanner::new(input);
+    let scope = Scope::new();
+    let ast = parse(&mut scanner, |scanner| parser::parse_module_with_recovery(scanner, &scope, import))?;
+    generate(&ast, &scope, options).map_err(|error| vec![error])
+}
+
+fn generate(ast: &Ast, scope: &Scope, options: CompileOptions) -> std::result::Result<Program, CompileError> {
+    let syntax = syntax::module(ast)?;
+    let module = syntax.name.name.clone();
+
+    let mut codegen = codegen::Codegen::new();
+    codegen.bounds_checks = options.bounds_checks;
+    // NOTE(pht) codegen adds the instructions to return at the end of the module body
+    codegen.generate_code(&syntax)?;
+
+    let exports = scope
+        .symbols()
+        .iter()
+        .filter(|symbol| symbol.export.is_some())
+        .filter_map(|symbol| {
+            let (section, offset) = match symbol.kind {
+                SymbolKind::Procedure(_) => (Section::Code, codegen.procedure_address(&symbol.name)?),
+                SymbolKind::Variable => (Section::Variables, symbol.adr),
+                _ => return None,
+            };
+            Some(ObjectSymbol {
+                name: symbol.name.clone(),
+                section,
+                offset,
+            })
+        })
+        .collect();
 
+    Ok(Program {
+        symbol_file: symbol_file::write(&module, &scope.symbols()),
+        module,
+        instructions: codegen.instructions,
+        data: codegen.data,
+        variables: scope.size(),
+        externals: codegen.externals,
+        relocations: codegen.relocations,
+        exports,
+    })
+}
+
+pub fn build_module(input: &str) -> std::result::Result<Module, CompileError> {
+    let mut scanner = Scanner::new(input);
     let scope = Scope::new();
-    // Still needed because `parsemodule` is not yet `compile`
-    parser::scan_next(&mut scanner)?;
-    parser::parse_module(&mut scanner, &scope)
+    let import = &mut |module: &str| Err(ParseError::UndefinedModule(String::from(module)));
+    let ast = parse(&mut scanner, |scanner| parser::parse_module_with_recovery(scanner, &scope, import)).map_err(|mut errors| errors.remove(0))?;
+    Ok(syntax::module(&ast)?)
+}
+
+// Errors without a location of their own are located at the token the parser found them at
+fn parse(scanner: &mut Scanner, parse_module: impl FnOnce(&mut Scanner) -> (Option<Ast>, ParseErrors)) -> std::result::Result<Ast, Vec<CompileError>> {
+    let (module, errors) = match parser::scan_next(scanner) {
+        Ok(()) => parse_module(scanner),
+        Err(error) => (None, vec![(error, None)]),
+    };
+    let errors: Vec<CompileError> = errors
+        .into_iter()
+        .map(|(error, context)| {
+            let mut error = CompileError::from(error);
+            if error.context.is_none() {
+                error.context = context;
+            }
+            error
+        })
+        .collect();
+
+    match module {
+        Some(module) if errors.is_empty() => Ok(module),
+        _ => Err(errors),
+    }
 }
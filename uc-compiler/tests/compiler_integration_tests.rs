@@ -1,5 +1,11 @@
+#![feature(assert_matches)]
+use std::assert_matches::assert_matches;
+
+use ast::token::{ScanContext, ScanErrorType};
+use compiler::{CodegenError, ErrorKind, ParseError};
 use risc::instructions::OpCode::*;
 use risc::instructions::*;
+use risc::object::*;
 
 #[test]
 fn compile_assignement_program() {
@@ -14,37 +20,138 @@ fn compile_assignement_program() {
     END Test.
   ",
     );
-    let instructions = compiler::compile(&content).unwrap();
+    let instructions = compiler::compile(&content).unwrap().instructions;
     assert_eq!(
         instructions,
         vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 8 },
+            // The return address is kept in the static area
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
             // Instructions for the program
             Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 42 },
             Instruction::Memory {
                 u: MemoryMode::Store,
                 a: 0,
-                b: 14,
+                b: 13,
                 offset: 2
             },
             Instruction::Memory {
                 u: MemoryMode::Load,
                 a: 0,
-                b: 14,
+                b: 13,
+                offset: 2
+            },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 15,
+                link: false
+            }
+        ]
+    )
+}
+
+#[test]
+fn compile_procedure_call() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+      PROCEDURE Set(VAR v: INTEGER);
+      BEGIN
+        v := 1
+      END Set;
+      BEGIN
+        Set(x)
+    END Test.
+  ",
+    );
+    let instructions = compiler::compile(&content).unwrap().instructions;
+    assert_eq!(
+        instructions,
+        vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 15 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            // Pass the address of x
+            Instruction::RegisterIm { o: ADD, a: 0, b: 13, im: 1 },
+            // Call to the procedure, after the footer
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
                 offset: 2
             },
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 15,
+                link: false
+            },
+            // Prologue
+            Instruction::RegisterIm { o: SUB, a: 14, b: 14, im: 2 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 14,
+                offset: 0
+            },
             Instruction::Memory {
                 u: MemoryMode::Store,
                 a: 0,
                 b: 14,
                 offset: 1
             },
-            // Footer to exit
-            Instruction::RegisterIm {
-                o: OpCode::MOV,
+            // v := 1
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 1 },
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 1,
+                b: 14,
+                offset: 1
+            },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 1,
+                offset: 0
+            },
+            // Epilogue
+            Instruction::Memory {
+                u: MemoryMode::Load,
                 a: 15,
-                b: 0,
-                im: 0
+                b: 14,
+                offset: 0
             },
+            Instruction::RegisterIm { o: ADD, a: 14, b: 14, im: 2 },
             Instruction::Branch {
                 cond: BranchCondition::AW,
                 c: 15,
@@ -53,3 +160,252 @@ fn compile_assignement_program() {
         ]
     )
 }
+
+#[test]
+fn compile_importer_from_symbol_file() {
+    let exporter = String::from(
+        "
+  MODULE Counters;
+      CONST Step* = 2;
+      VAR total-, hidden: INTEGER;
+      PROCEDURE Add*(n: INTEGER);
+      BEGIN
+        total := total + n
+      END Add;
+    END Counters.
+  ",
+    );
+    let exporter = compiler::compile(&exporter).unwrap();
+    assert_eq!(exporter.module, "Counters");
+    assert_eq!(exporter.symbol_file, "MODULE Counters\nCONST Step * 2 INTEGER\nVAR total - 1 INTEGER\nPROCEDURE Add * VALUE n INTEGER\n");
+
+    let importer = String::from(
+        "
+  MODULE Main;
+      IMPORT C := Counters;
+      VAR x: INTEGER;
+      BEGIN
+        C.Add(C.Step);
+        x := C.total
+    END Main.
+  ",
+    );
+    let program = compiler::compile_with_imports(&importer, compiler::CompileOptions::default(), &mut |module| {
+        assert_eq!(module, "Counters");
+        Ok(ast::symbol_file::read(&exporter.symbol_file)?.1)
+    })
+    .unwrap();
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 10 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 2 },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
+                offset: 0
+            },
+            // The imported procedure changed the static base
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 10 },
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 0 },
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 0,
+                offset: 0
+            },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 15,
+                link: false
+            },
+        ]
+    );
+    assert_eq!(
+        program.externals,
+        vec![(3, String::from("Counters"), String::from("Add")), (5, String::from("Counters"), String::from("total"))]
+    );
+
+    let object = program.object();
+    assert_eq!(
+        object.imports,
+        vec![
+            Import {
+                module: String::from("Counters"),
+                name: String::from("Add")
+            },
+            Import {
+                module: String::from("Counters"),
+                name: String::from("total")
+            }
+        ]
+    );
+    assert_eq!(
+        object.relocations,
+        vec![
+            Relocation {
+                index: 0,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Variables(0)
+            },
+            Relocation {
+                index: 4,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Variables(0)
+            },
+            Relocation {
+                index: 3,
+                kind: RelocationKind::Branch,
+                target: RelocationTarget::Import(0)
+            },
+            Relocation {
+                index: 5,
+                kind: RelocationKind::Absolute,
+                target: RelocationTarget::Import(1)
+            }
+        ]
+    );
+}
+
+#[test]
+fn compile_relocatable_object() {
+    let content = String::from(
+        "
+  MODULE Greetings;
+      VAR i: INTEGER;
+      PROCEDURE Hello*;
+      BEGIN
+        Out.String(\"Hi\")
+      END Hello;
+      PROCEDURE Pick(n: INTEGER);
+      BEGIN
+        CASE n OF 1: i := 1 | 2: i := 2 | 3: i := 3 END
+      END Pick;
+    BEGIN
+      Hello;
+      Pick(2)
+    END Greetings.
+  ",
+    );
+    let program = compiler::compile(&content).unwrap();
+    let object = program.object();
+    assert_eq!(object.name, "Greetings");
+    assert_eq!(object.entry, 0);
+    assert!(object.body);
+    assert_eq!(object.code, program.instructions);
+    assert_eq!(object.data, vec![72, 105, 0]);
+    assert_eq!(
+        object.exports,
+        vec![ObjectSymbol {
+            name: String::from("Hello"),
+            section: Section::Code,
+            offset: 7
+        }]
+    );
+    assert!(object.imports.is_empty());
+    assert_eq!(object.variables, 2);
+
+    // The address of the string, the address of the jump table, and the static base loaded
+    // by the module body and by the exported procedure
+    assert_eq!(object.relocations.len(), 4);
+    for relocation in object.relocations.iter() {
+        let address = match object.code[relocation.index] {
+            Instruction::RegisterIm { im, .. } => im as usize,
+            other => panic!("Unexpected relocated instruction {:?}", other),
+        };
+        match relocation.target {
+            RelocationTarget::Data(offset) => assert_eq!(address, program.data_address() + offset),
+            RelocationTarget::Code(offset) => assert_eq!(address, offset),
+            RelocationTarget::Variables(offset) => assert_eq!(address, program.static_base() + offset),
+            RelocationTarget::Import(_) => panic!("Unexpected import"),
+        }
+    }
+}
+
+#[test]
+fn compile_errors_have_a_code_and_a_location() {
+    let error = compiler::compile("MODULE Test; VAR x: INTEGER; BEGIN x := 99999999999 END Test.").unwrap_err();
+    assert_matches!(error.kind, ErrorKind::Scan(ScanErrorType::IntegerOverflow));
+    assert_eq!(error.code(), "E104");
+    assert_eq!(error.context, Some(ScanContext { line: 0, column: 40 }));
+
+    // The symbol does not know where it is used, the parser stopped on it
+    let error = compiler::compile("MODULE Test; VAR x: INTEGER;\nBEGIN x := y END Test.").unwrap_err();
+    assert_matches!(error.kind, ErrorKind::Parse(ParseError::UndefinedSymbol(_)));
+    assert_eq!(error.code(), "E201");
+    assert_eq!(error.context, Some(ScanContext { line: 1, column: 11 }));
+
+    let error = compiler::compile("MODULE Test; VAR x: INTEGER;\nBEGIN x := 1;\n  x := TRUE END Test.").unwrap_err();
+    assert_matches!(error.kind, ErrorKind::Semantic(ParseError::TypeMismatch { .. }));
+    assert_eq!(error.code(), "E301");
+    assert_eq!(error.context, Some(ScanContext { line: 2, column: 7 }));
+}
+
+#[test]
+fn compile_fails_when_registers_run_out() {
+    // Each level of parentheses keeps the value of its left operand in a register
+    let nested = |levels: usize| {
+        let expression = (0..levels).fold(String::from("x"), |expression, _| format!("x + ({})", expression));
+        format!("MODULE Test; VAR x: INTEGER;\nBEGIN\n  x := 1;\n  x := {} END Test.", expression)
+    };
+    assert!(compiler::compile(&nested(11)).is_ok());
+
+    let error = compiler::compile(&nested(12)).unwrap_err();
+    assert_matches!(error.kind, ErrorKind::Codegen(CodegenError::ExpressionTooComplex));
+    assert_eq!(error.code(), "E407");
+    assert_eq!(error.context, Some(ScanContext { line: 3, column: 2 }));
+
+    // The parser limits the parameters of the procedures it reads, but not the ones of symbol files
+    let parameters: String = (0..13).map(|index| format!(" VALUE p{} INTEGER", index)).collect();
+    let symbol_file = format!("MODULE Lib\nPROCEDURE P *{}\n", parameters);
+    let content = "MODULE Test; IMPORT Lib;\nBEGIN\n  Lib.P(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12) END Test.";
+    let errors = compiler::compile_with_recovery(content, compiler::CompileOptions::default(), &mut |_| {
+        Ok(ast::symbol_file::read(&symbol_file)?.1)
+    })
+    .unwrap_err();
+    assert_matches!(&errors[0].kind, ErrorKind::Codegen(CodegenError::TooManyParameters(name)) if name == "P");
+    assert_eq!(errors[0].context, Some(ScanContext { line: 2, column: 2 }));
+}
+
+#[test]
+fn compile_reports_every_syntax_error() {
+    let content = "MODULE Test; VAR x: INTEGER;\nBEGIN\n  x := 1 +;\n  x := y;\n  x := 2 x := 3\nEND Test.";
+    let errors = compiler::compile_with_recovery(content, compiler::CompileOptions::default(), &mut |module| {
+        Err(ParseError::UndefinedModule(String::from(module)))
+    })
+    .unwrap_err();
+    let located: Vec<_> = errors.iter().map(|error| (error.code(), error.context)).collect();
+    assert_eq!(
+        located,
+        vec![
+            ("E203", Some(ScanContext { line: 2, column: 10 })),
+            ("E201", Some(ScanContext { line: 3, column: 7 })),
+            ("E203", Some(ScanContext { line: 4, column: 9 })),
+        ]
+    );
+
+    // Only the first error is reported by the other functions
+    let error = compiler::compile(content).unwrap_err();
+    assert_eq!(error.context, Some(ScanContext { line: 2, column: 10 }));
+
+    let rendered = compiler::diagnostics::render_all(&errors, "Test.mod", content, compiler::diagnostics::Format::Json);
+    assert_eq!(rendered.lines().count(), 3);
+}
@@ -17,10 +17,18 @@ fn compile_false_condition_test() {
     END Test.
   ",
     );
-    let instructions = compiler::compile(&content).unwrap();
+    let instructions = compiler::compile(&content).unwrap().instructions;
     assert_eq!(
         instructions,
         vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 10 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
             // Instructions for the program
             Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 0 },
             Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 1 },
@@ -28,7 +36,7 @@ fn compile_false_condition_test() {
             Instruction::Register { o: SUB, a: 0, b: 0, c: 1 },
             Instruction::BranchOff {
                 cond: BranchCondition::NE,
-                offset: 3,
+                offset: 2,
                 link: false
             },
             // Instruction for the then branch, will be ignored
@@ -36,15 +44,15 @@ fn compile_false_condition_test() {
             Instruction::Memory {
                 u: MemoryMode::Store,
                 a: 0,
-                b: 14,
+                b: 13,
                 offset: 1
             },
-            // Footer to exit
-            Instruction::RegisterIm {
-                o: OpCode::MOV,
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
                 a: 15,
-                b: 0,
-                im: 0
+                b: 13,
+                offset: 0
             },
             Instruction::Branch {
                 cond: BranchCondition::AW,
@@ -70,10 +78,18 @@ fn compile_else_condition_test() {
     END Test.
   ",
     );
-    let instructions = compiler::compile(&content).unwrap();
+    let instructions = compiler::compile(&content).unwrap().instructions;
     assert_eq!(
         instructions,
         vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 13 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
             // Instructions for the program
             Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 0 },
             Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 1 },
@@ -89,12 +105,12 @@ fn compile_else_condition_test() {
             Instruction::Memory {
                 u: MemoryMode::Store,
                 a: 0,
-                b: 14,
+                b: 13,
                 offset: 1
             },
             Instruction::BranchOff {
                 cond: BranchCondition::AW,
-                offset: 3,
+                offset: 2,
                 link: false
             },
             // Instruction for the else branch, should be taken
@@ -102,15 +118,93 @@ fn compile_else_condition_test() {
             Instruction::Memory {
                 u: MemoryMode::Store,
                 a: 0,
-                b: 14,
+                b: 13,
+                offset: 1
+            },
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 15,
+                link: false
+            }
+        ]
+    )
+}
+
+#[test]
+fn compile_conjunction_condition_test() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+      BEGIN
+        IF (x = 0) & (x = 1) THEN
+          x:= 2
+        END
+    END Test.
+  ",
+    );
+    let instructions = compiler::compile(&content).unwrap().instructions;
+    assert_eq!(
+        instructions,
+        vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 14 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            // x = 0
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 0 },
+            Instruction::Register { o: SUB, a: 0, b: 0, c: 1 },
+            // Skip the rest of the condition, and the then branch
+            Instruction::BranchOff {
+                cond: BranchCondition::NE,
+                offset: 6,
+                link: false
+            },
+            // x = 1
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 1 },
+            Instruction::Register { o: SUB, a: 0, b: 0, c: 1 },
+            Instruction::BranchOff {
+                cond: BranchCondition::NE,
+                offset: 2,
+                link: false
+            },
+            // Then branch
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 2 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 13,
                 offset: 1
             },
-            // Footer to exit
-            Instruction::RegisterIm {
-                o: OpCode::MOV,
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
                 a: 15,
-                b: 0,
-                im: 0
+                b: 13,
+                offset: 0
             },
             Instruction::Branch {
                 cond: BranchCondition::AW,
@@ -120,3 +214,169 @@ fn compile_else_condition_test() {
         ]
     )
 }
+
+#[test]
+fn compile_elsif_condition_test() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+      BEGIN
+        IF x = 0 THEN
+          x:= 1
+        ELSIF x = 1 THEN
+          x:= 2
+        END
+    END Test.
+  ",
+    );
+    let instructions = compiler::compile(&content).unwrap().instructions;
+    assert_eq!(
+        instructions,
+        vec![
+            // Static base, right after the code
+            Instruction::RegisterIm { o: MOV, a: 13, b: 0, im: 17 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            // IF x = 0
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 0 },
+            Instruction::Register { o: SUB, a: 0, b: 0, c: 1 },
+            Instruction::BranchOff {
+                cond: BranchCondition::NE,
+                offset: 3,
+                link: false
+            },
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 1 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            // Branch to the common END
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                offset: 6,
+                link: false
+            },
+            // ELSIF x = 1
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            Instruction::RegisterIm { o: MOV, a: 1, b: 0, im: 1 },
+            Instruction::Register { o: SUB, a: 0, b: 0, c: 1 },
+            Instruction::BranchOff {
+                cond: BranchCondition::NE,
+                offset: 2,
+                link: false
+            },
+            Instruction::RegisterIm { o: MOV, a: 0, b: 0, im: 2 },
+            Instruction::Memory {
+                u: MemoryMode::Store,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            // Footer to return
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 15,
+                b: 13,
+                offset: 0
+            },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 15,
+                link: false
+            }
+        ]
+    )
+}
+
+#[test]
+fn compile_dense_case_to_jump_table_test() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+      BEGIN
+        CASE x OF
+          1: x := 10
+        | 2..3: x := 20
+        END
+    END Test.
+  ",
+    );
+    let instructions = compiler::compile(&content).unwrap().instructions;
+    assert_eq!(
+        // After the static base, and the return address
+        instructions[2..12],
+        vec![
+            Instruction::Memory {
+                u: MemoryMode::Load,
+                a: 0,
+                b: 13,
+                offset: 1
+            },
+            // Bounds check
+            Instruction::RegisterIm { o: SUB, a: 1, b: 0, im: 1 },
+            Instruction::BranchOff {
+                cond: BranchCondition::LT,
+                offset: 13,
+                link: false
+            },
+            Instruction::RegisterIm { o: SUB, a: 2, b: 1, im: 2 },
+            Instruction::BranchOff {
+                cond: BranchCondition::GT,
+                offset: 11,
+                link: false
+            },
+            // Branch into the table
+            Instruction::RegisterIm { o: ADD, a: 1, b: 1, im: 9 },
+            Instruction::Branch {
+                cond: BranchCondition::AW,
+                c: 1,
+                link: false
+            },
+            // Table
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                offset: 2,
+                link: false
+            },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                offset: 4,
+                link: false
+            },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                offset: 3,
+                link: false
+            },
+        ]
+    );
+    // Selectors out of the table end up on the trap of the missing ELSE
+    assert_eq!(
+        instructions[18],
+        Instruction::Trap {
+            cond: BranchCondition::AW,
+            c: 0,
+            code: TrapCode::CaseNotMatched,
+            line: 5
+        }
+    );
+}
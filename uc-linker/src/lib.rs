@@ -0,0 +1,197 @@
+// Combines objects, compiled from oberon-0 or assembly, into a single image loaded at address 0.
+//
+// The code of every object comes first, in the given order, followed by their data, and then
+// by their static areas. Execution starts at the entry of the first object, once the bodies of the
+// modules it imports have run, the modules they import first.
+use risc::instructions::*;
+use risc::object::{Import, Object, RelocationKind, RelocationTarget, Section};
+
+const LNK: usize = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    NoObjects,
+    DuplicateModule(String),
+    // Qualified name of the missing symbol, like `Runtime.Double`
+    UndefinedSymbol(String),
+    // Module and index of an instruction that can not be relocated
+    InvalidRelocation(String, usize),
+}
+
+#[derive(Debug)]
+pub struct Image {
+    pub instructions: Vec<Instruction>,
+    // Data of the objects, followed by their static areas, filled with 0
+    pub data: Vec<i32>,
+}
+
+impl Image {
+    pub fn data_address(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        Instruction::serialize_image(self.instructions.clone(), &self.data)
+    }
+}
+
+// Where each object ends up in the image
+struct Layout {
+    code_bases: Vec<usize>,
+    data_bases: Vec<usize>,
+    variables_bases: Vec<usize>,
+}
+
+impl Layout {
+    fn address(&self, object_index: usize, section: Section, offset: usize) -> usize {
+        match section {
+            Section::Code => self.code_bases[object_index] + offset,
+            Section::Data => self.data_bases[object_index] + offset,
+            Section::Variables => self.variables_bases[object_index] + offset,
+        }
+    }
+}
+
+pub fn link(objects: &[Object]) -> Result<Image, LinkError> {
+    let main = objects.first().ok_or(LinkError::NoObjects)?;
+    for (index, object) in objects.iter().enumerate() {
+        if objects[..index].iter().any(|other| other.name == object.name) {
+            return Err(LinkError::DuplicateModule(object.name.clone()));
+        }
+    }
+
+    // Execution starts at 0, so the image starts with calls to the bodies of the imported modules, and with
+    // a branch if the entry of the first object is elsewhere. Their offsets are known once the code is laid out.
+    let initialized = initialization_order(objects);
+    let mut instructions = vec![];
+    // Index of the branches of the startup code, with the object they go to
+    let mut startup = vec![];
+    for &object_index in initialized.iter() {
+        startup.push((instructions.len(), object_index));
+        instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::AW,
+            link: true,
+            offset: 0,
+        });
+    }
+    if !initialized.is_empty() {
+        // The first object returns to 0 like a program that was not linked, which ends it
+        instructions.push(Instruction::RegisterIm {
+            o: OpCode::MOV,
+            a: LNK,
+            b: 0,
+            im: 0,
+        });
+    }
+    if !initialized.is_empty() || main.entry != 0 {
+        startup.push((instructions.len(), 0));
+        instructions.push(Instruction::BranchOff {
+            cond: BranchCondition::AW,
+            link: false,
+            offset: 0,
+        });
+    }
+
+    let mut code_bases = vec![];
+    for object in objects.iter() {
+        code_bases.push(instructions.len());
+        instructions.extend(object.code.iter().copied());
+    }
+    let mut data = vec![];
+    let mut data_bases = vec![];
+    for object in objects.iter() {
+        data_bases.push(instructions.len() + data.len());
+        data.extend(object.data.iter().copied());
+    }
+    let mut variables_bases = vec![];
+    for object in objects.iter() {
+        variables_bases.push(instructions.len() + data.len());
+        data.resize(data.len() + object.variables, 0);
+    }
+    let layout = Layout {
+        code_bases,
+        data_bases,
+        variables_bases,
+    };
+
+    for (index, object_index) in startup {
+        let target = layout.address(object_index, Section::Code, objects[object_index].entry);
+        if let Instruction::BranchOff { offset, .. } = &mut instructions[index] {
+            *offset = (target as i32) - (index as i32) - 1;
+        }
+    }
+
+    for (object_index, object) in objects.iter().enumerate() {
+        for relocation in object.relocations.iter() {
+            let invalid = || LinkError::InvalidRelocation(object.name.clone(), relocation.index);
+
+            let target = match relocation.target {
+                RelocationTarget::Code(offset) => layout.address(object_index, Section::Code, offset),
+                RelocationTarget::Data(offset) => layout.address(object_index, Section::Data, offset),
+                RelocationTarget::Variables(offset) => layout.address(object_index, Section::Variables, offset),
+                RelocationTarget::Import(import_index) => {
+                    let import = object.imports.get(import_index).ok_or_else(invalid)?;
+                    resolve(objects, &layout, import)?
+                }
+            };
+
+            if relocation.index >= object.code.len() {
+                return Err(invalid());
+            }
+            let index = layout.address(object_index, Section::Code, relocation.index);
+            instructions[index] = match (relocation.kind, instructions[index]) {
+                (RelocationKind::Absolute, Instruction::RegisterIm { o, a, b, .. }) => Instruction::RegisterIm { o, a, b, im: target as i32 },
+                (RelocationKind::Branch, Instruction::BranchOff { cond, link, .. }) => Instruction::BranchOff {
+                    cond,
+                    link,
+                    offset: (target as i32) - (index as i32) - 1,
+                },
+                _ => return Err(invalid()),
+            };
+        }
+    }
+
+    Ok(Image { instructions, data })
+}
+
+// Index of the objects with a body to run before the first one, each after the modules it imports.
+// Modules are taken in the order the first object imports them, then in the order they are given.
+fn initialization_order(objects: &[Object]) -> Vec<usize> {
+    let mut visited = vec![false; objects.len()];
+    visited[0] = true;
+    let mut order = vec![];
+    let imported = objects[0].imports.iter().filter_map(|import| module_index(objects, &import.module));
+    for object_index in imported.chain(1..objects.len()) {
+        visit(objects, object_index, &mut visited, &mut order);
+    }
+    order
+}
+
+fn visit(objects: &[Object], object_index: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+    if visited[object_index] {
+        return;
+    }
+    visited[object_index] = true;
+    for import in objects[object_index].imports.iter() {
+        if let Some(imported) = module_index(objects, &import.module) {
+            visit(objects, imported, visited, order);
+        }
+    }
+    if objects[object_index].body {
+        order.push(object_index);
+    }
+}
+
+fn module_index(objects: &[Object], module: &str) -> Option<usize> {
+    objects.iter().position(|object| object.name == module)
+}
+
+// Address of a symbol exported by one of the objects
+fn resolve(objects: &[Object], layout: &Layout, import: &Import) -> Result<usize, LinkError> {
+    objects
+        .iter()
+        .enumerate()
+        .filter(|(_, object)| object.name == import.module)
+        .find_map(|(object_index, object)| object.export(&import.name).map(|symbol| layout.address(object_index, symbol.section, symbol.offset)))
+        .ok_or_else(|| LinkError::UndefinedSymbol(format!("{}.{}", import.module, import.name)))
+}
@@ -0,0 +1,197 @@
+use linker::LinkError;
+use risc::instructions::*;
+use risc::object::*;
+
+fn runtime() -> Object {
+    assembler::assemble_object(
+        "Runtime",
+        "
+    * Doubles the integer at the address held by R0
+    @Double  LDW  R1,R0,0
+             ADD  R1,R1,R1
+             STW  R1,R0,0
+             B    R15
+    ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn links_calls_to_other_objects() {
+    let main = assembler::assemble_object(
+        "Main",
+        "
+             MOV  R0,100
+             BL   @Runtime.Double
+             MOV  R15,0
+             B    R15
+    ",
+    )
+    .unwrap();
+    assert_eq!(main.imports[0].name, "Double");
+
+    let image = linker::link(&[main, runtime()]).unwrap();
+    assert_eq!(image.instructions.len(), 8);
+    assert_eq!(
+        image.instructions[1],
+        Instruction::BranchOff {
+            cond: BranchCondition::AW,
+            link: true,
+            offset: 2
+        }
+    );
+}
+
+#[test]
+fn relocates_addresses_of_code_and_data() {
+    let mut first = Object::new("First");
+    first.code = vec![Instruction::RegisterIm {
+        o: OpCode::MOV,
+        a: 0,
+        b: 0,
+        im: 0,
+    }];
+    first.data = vec![1, 2];
+    first.variables = 2;
+    first.relocations.push(Relocation {
+        index: 0,
+        kind: RelocationKind::Absolute,
+        target: RelocationTarget::Variables(1),
+    });
+
+    let mut second = Object::new("Second");
+    second.entry = 1;
+    second.code = vec![
+        Instruction::RegisterIm {
+            o: OpCode::MOV,
+            a: 0,
+            b: 0,
+            im: 0,
+        },
+        Instruction::RegisterIm {
+            o: OpCode::MOV,
+            a: 1,
+            b: 0,
+            im: 0,
+        },
+    ];
+    second.data = vec![3];
+    second.variables = 1;
+    second.relocations = vec![
+        Relocation {
+            index: 0,
+            kind: RelocationKind::Absolute,
+            target: RelocationTarget::Data(0),
+        },
+        Relocation {
+            index: 1,
+            kind: RelocationKind::Absolute,
+            target: RelocationTarget::Code(1),
+        },
+    ];
+
+    // The entry of the first object is not at 0, so the image starts with a branch to it
+    let image = linker::link(&[second, first]).unwrap();
+    assert_eq!(
+        image.instructions,
+        vec![
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: false,
+                offset: 1
+            },
+            Instruction::RegisterIm {
+                o: OpCode::MOV,
+                a: 0,
+                b: 0,
+                im: 4
+            },
+            Instruction::RegisterIm {
+                o: OpCode::MOV,
+                a: 1,
+                b: 0,
+                im: 2
+            },
+            Instruction::RegisterIm {
+                o: OpCode::MOV,
+                a: 0,
+                b: 0,
+                im: 9
+            },
+        ]
+    );
+    // Static areas follow the data of every object
+    assert_eq!(image.data, vec![3, 1, 2, 0, 0, 0]);
+    assert_eq!(image.data_address(), 4);
+}
+
+#[test]
+fn calls_the_bodies_of_imported_modules_first() {
+    let body = |name: &str, imports: &[&str]| {
+        let mut object = Object::new(name);
+        object.body = true;
+        object.code = vec![Instruction::Branch {
+            cond: BranchCondition::AW,
+            c: 15,
+            link: false,
+        }];
+        for module in imports {
+            object.import(module, "x");
+        }
+        object
+    };
+
+    // Modules run after the ones they import, and objects without a body are not called
+    let main = body("Main", &["Lists"]);
+    let lists = body("Lists", &["Strings", "Runtime"]);
+    let strings = body("Strings", &[]);
+    let image = linker::link(&[main, runtime(), strings, lists]).unwrap();
+    assert_eq!(
+        image.instructions[..4],
+        vec![
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
+                offset: 8
+            },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: true,
+                offset: 8
+            },
+            Instruction::RegisterIm {
+                o: OpCode::MOV,
+                a: 15,
+                b: 0,
+                im: 0
+            },
+            Instruction::BranchOff {
+                cond: BranchCondition::AW,
+                link: false,
+                offset: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn fails_on_unresolved_objects() {
+    assert_eq!(linker::link(&[]).unwrap_err(), LinkError::NoObjects);
+    assert_eq!(linker::link(&[runtime(), runtime()]).unwrap_err(), LinkError::DuplicateModule(String::from("Runtime")));
+
+    let main = assembler::assemble_object("Main", "BL @Runtime.Triple").unwrap();
+    assert_eq!(linker::link(&[main, runtime()]).unwrap_err(), LinkError::UndefinedSymbol(String::from("Runtime.Triple")));
+
+    let mut invalid = Object::new("Invalid");
+    invalid.code = vec![Instruction::Branch {
+        cond: BranchCondition::AW,
+        c: 15,
+        link: false,
+    }];
+    invalid.relocations.push(Relocation {
+        index: 0,
+        kind: RelocationKind::Absolute,
+        target: RelocationTarget::Code(0),
+    });
+    assert_eq!(linker::link(&[invalid]).unwrap_err(), LinkError::InvalidRelocation(String::from("Invalid"), 0));
+}
@@ -1,16 +1,29 @@
 #![feature(assert_matches)]
 use assembler::AssembleError;
-use compiler::ParseError;
+use compiler::CompileError;
+use compiler::CompileOptions;
 use risc::computer::Computer;
+use risc::computer::Trap;
+use risc::instructions::TrapCode;
 
 #[derive(Debug)]
 pub struct Simulator {
     computer: Computer,
+    // Address of the module variables of a compiled program
+    static_base: usize,
 }
 
 #[derive(Debug)]
 pub enum ExecutionError {
     MaxCycleReached,
+    // Line of the Oberon source, counting from 1
+    ArrayIndexOutOfBounds { line: u32, index: i32 },
+    DivisionByZero { dividend: i32 },
+    AssertionFailed { line: u32 },
+    Halted { line: u32, code: i32 },
+    CaseNotMatched { line: u32, selector: i32 },
+    // Load or store out of memory, where there is no device either
+    InvalidAddress { address: i32 },
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -24,14 +37,31 @@ impl Simulator {
         let instructions = assembler::assemble(s)?;
         let mut computer = Computer::new();
         computer.load_instructions(instructions);
-        Ok(Simulator { computer })
+        Ok(Simulator { computer, static_base: 0 })
     }
 
-    pub fn from_oberon(s: &str) -> Result<Simulator, ParseError> {
-        let instructions = compiler::compile(s)?;
+    pub fn from_oberon(s: &str) -> Result<Simulator, CompileError> {
+        Simulator::from_oberon_with_options(s, CompileOptions::default())
+    }
+
+    pub fn from_oberon_with_options(s: &str, options: CompileOptions) -> Result<Simulator, CompileError> {
+        let program = compiler::compile_with_options(s, options)?;
         let mut computer = Computer::new();
-        computer.load_instructions(instructions);
-        Ok(Simulator { computer })
+        computer.load_data(program.data_address(), &program.data);
+        let static_base = program.static_base();
+        computer.load_instructions(program.instructions);
+        Ok(Simulator { computer, static_base })
+    }
+
+    // Image produced by the linker, with the instructions followed by the data
+    pub fn from_image(words: &[i32]) -> Simulator {
+        let mut computer = Computer::new();
+        computer.load_data(0, words);
+        Simulator { computer, static_base: 0 }
+    }
+
+    pub fn static_base(&self) -> usize {
+        self.static_base
     }
 
     pub fn registers(&self) -> &[i32] {
@@ -47,11 +77,32 @@ impl Simulator {
         }
     }
 
+    // Everything printed by the program, through the Out module or the console device
+    pub fn output(&self) -> &str {
+        &self.computer.console.output
+    }
+
+    // Integers read by In.Int, once they are consumed the program reads 0
+    pub fn set_input(&mut self, input: impl Iterator<Item = i32> + 'static) {
+        self.computer.console.set_input(Box::new(input));
+    }
+
     pub fn execute(&mut self, execution: Execution) -> Result<(), ExecutionError> {
         self.start(execution.stack_base as i32);
 
         self.computer.execute(execution.max_cycles);
 
+        if let Some(Trap { code, line, value }) = self.computer.trap {
+            return match code {
+                TrapCode::ArrayIndexOutOfBounds => Err(ExecutionError::ArrayIndexOutOfBounds { line, index: value }),
+                TrapCode::DivisionByZero => Err(ExecutionError::DivisionByZero { dividend: value }),
+                TrapCode::AssertionFailed => Err(ExecutionError::AssertionFailed { line }),
+                TrapCode::Halt => Err(ExecutionError::Halted { line, code: value }),
+                TrapCode::CaseNotMatched => Err(ExecutionError::CaseNotMatched { line, selector: value }),
+                TrapCode::InvalidAddress => Err(ExecutionError::InvalidAddress { address: value }),
+            };
+        }
+
         if self.computer.pc == 0 {
             Ok(())
         } else {
@@ -60,6 +111,7 @@ impl Simulator {
     }
 
     pub fn start(&mut self, stack_base: i32) {
+        // NOTE(pht) R13, the static base, is set by the program itself
         self.computer.regs[14] = stack_base;
     }
 
@@ -0,0 +1,161 @@
+use compiler::CompileOptions;
+use risc::instructions::Instruction;
+use risc::object::Object;
+use simulator::*;
+
+#[test]
+fn compiled_and_assembled_objects_are_linked() {
+    let greeter = compiler::compile(
+        "
+  MODULE Greeter;
+    VAR count-: INTEGER;
+    PROCEDURE Greet*(n: INTEGER);
+    BEGIN
+      INC(count);
+      Out.String(\"Hello \");
+      CASE n OF 1: Out.String(\"one \") | 2: Out.String(\"two \") | 3: Out.String(\"three \") END
+    END Greet;
+  END Greeter.",
+    )
+    .unwrap();
+
+    let runtime = assembler::assemble_object(
+        "Runtime",
+        "
+    * Doubles the integer at the address held by R0
+    @Double  LDW  R1,R0,0
+             ADD  R1,R1,R1
+             STW  R1,R0,0
+             B    R15
+    ",
+    )
+    .unwrap();
+    let runtime_symbols = "MODULE Runtime\nPROCEDURE Double * VAR x INTEGER\n";
+
+    let main = compiler::compile_with_imports(
+        "
+  MODULE Main;
+    IMPORT Greeter, Runtime;
+    VAR x: INTEGER;
+  BEGIN
+    Greeter.Greet(2);
+    Greeter.Greet(3);
+    x := 21;
+    Runtime.Double(x);
+    Out.Int(x);
+    Out.Int(Greeter.count, 2)
+  END Main.",
+        CompileOptions::default(),
+        &mut |module| match module {
+            "Greeter" => Ok(ast::symbol_file::read(&greeter.symbol_file)?.1),
+            _ => Ok(ast::symbol_file::read(runtime_symbols)?.1),
+        },
+    )
+    .unwrap();
+
+    let image = linker::link(&[main.object(), greeter.object(), runtime]).unwrap();
+    let mut s = Simulator::from_image(&Instruction::deserialize_image(&image.serialize()));
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "Hello two Hello three 42 2");
+}
+
+#[test]
+fn bodies_of_imported_modules_run_before_the_importer() {
+    let config = compiler::compile(
+        "
+  MODULE Config;
+    VAR size*: INTEGER;
+  BEGIN
+    size := 42
+  END Config.",
+    )
+    .unwrap();
+
+    let main = compiler::compile_with_imports(
+        "
+  MODULE Main;
+    IMPORT Config;
+  BEGIN
+    Out.Int(Config.size)
+  END Main.",
+        CompileOptions::default(),
+        &mut |_| Ok(ast::symbol_file::read(&config.symbol_file)?.1),
+    )
+    .unwrap();
+
+    let image = linker::link(&[main.object(), config.object()]).unwrap();
+    let mut s = Simulator::from_image(&Instruction::deserialize_image(&image.serialize()));
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "42");
+}
+
+#[test]
+fn bodies_with_side_effects_survive_object_files() {
+    let log = compiler::compile(
+        "
+  MODULE Log;
+    VAR lines-: INTEGER;
+    PROCEDURE Write*(n: INTEGER);
+    BEGIN
+      INC(lines);
+      Out.Int(n, 3)
+    END Write;
+  BEGIN
+    Out.String(\"Log\")
+  END Log.",
+    )
+    .unwrap();
+
+    let counter = compiler::compile_with_imports(
+        "
+  MODULE Counter;
+    IMPORT Log;
+    VAR count*: INTEGER;
+  BEGIN
+    count := 10;
+    Log.Write(count)
+  END Counter.",
+        CompileOptions::default(),
+        &mut |_| Ok(ast::symbol_file::read(&log.symbol_file)?.1),
+    )
+    .unwrap();
+
+    let main = compiler::compile_with_imports(
+        "
+  MODULE Main;
+    IMPORT Counter, Log;
+  BEGIN
+    INC(Counter.count);
+    Log.Write(Counter.count);
+    Out.Int(Log.lines, 2)
+  END Main.",
+        CompileOptions::default(),
+        &mut |module| match module {
+            "Log" => Ok(ast::symbol_file::read(&log.symbol_file)?.1),
+            _ => Ok(ast::symbol_file::read(&counter.symbol_file)?.1),
+        },
+    )
+    .unwrap();
+
+    // Log runs first, as Counter imports it, and only once
+    let objects: Vec<Object> = [main.object(), log.object(), counter.object()]
+        .iter()
+        .map(|object| Object::deserialize(&object.serialize()).unwrap())
+        .collect();
+    let image = linker::link(&objects).unwrap();
+    let mut s = Simulator::from_image(&Instruction::deserialize_image(&image.serialize()));
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "Log 10 11 2");
+}
@@ -0,0 +1,117 @@
+#![feature(assert_matches)]
+use std::assert_matches::assert_matches;
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn integers_can_be_negated() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y, z: INTEGER;
+    BEGIN
+        x := -5;
+        y := -x * 3;
+        z := -(x + y) - (-2)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 4), [0, -5, 15, -8]);
+}
+
+#[test]
+fn large_constants_are_not_truncated() {
+    let content = String::from(
+        "
+  MODULE Test;
+      CONST Big = 2147483647;
+      VAR x, y, z, c: INTEGER;
+    BEGIN
+        x := 100000;
+        y := -Big - 1;
+        z := x * 3 - 70000;
+        CASE z OF
+          230000: c := 1
+        | 100000..200000: c := 2
+        ELSE c := 3
+        END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 5), [0, 100000, i32::MIN, 230000, 1]);
+}
+
+#[test]
+fn arithmetic_wraps_around_and_comparisons_hold_at_the_extremes() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y, z, greater, less: INTEGER;
+    BEGIN
+        x := 2147483647;
+        IF x > -1 THEN greater := 1 END;
+        y := -x - 1;
+        IF y < 1 THEN less := 1 END;
+        x := x + 1;
+        z := 100000;
+        z := z * z
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 100 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 6), [0, i32::MIN, i32::MIN, 1410065408, 1, 1]);
+}
+
+#[test]
+fn div_and_mod_floor_the_quotient() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR a, b, c, d, e: INTEGER;
+    BEGIN
+        a := -7;
+        b := a DIV 2;
+        c := a MOD 2;
+        d := 7 DIV 2;
+        e := 7 MOD 2
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 6), [0, -7, -4, 1, 3, 1]);
+}
+
+#[test]
+fn division_by_zero_traps() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y: INTEGER;
+    BEGIN
+        x := 0;
+        y := 42 DIV x
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    let result = s.execute(execution);
+    assert_matches!(result, Err(ExecutionError::DivisionByZero { dividend: 42 }));
+}
@@ -1,4 +1,5 @@
 #![feature(assert_matches)]
+use std::assert_matches::assert_matches;
 use simulator::Simulator;
 use simulator::*;
 
@@ -22,7 +23,7 @@ fn arrays_values_can_be_set_by_constant_index() {
         max_cycles: 50,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 5), [0, 0, 1, 2, 3]);
+    assert_eq!(s.memory(s.static_base(), 5), [0, 0, 1, 2, 3]);
 }
 
 #[test]
@@ -45,7 +46,7 @@ fn arrays_values_can_be_accessed_by_constant_index() {
         max_cycles: 50,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 5), [0, 3, 1, 2, 3]);
+    assert_eq!(s.memory(s.static_base(), 5), [0, 3, 1, 2, 3]);
 }
 
 #[test]
@@ -70,5 +71,117 @@ fn arrays_values_can_be_accessed_by_variable_index() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 50 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 6), [0, 6, 1, 5, 6, 7]);
+    assert_eq!(s.memory(s.static_base(), 6), [0, 6, 1, 5, 6, 7]);
+}
+
+#[test]
+fn arrays_can_be_accessed_by_expressions() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR i, j, swap: INTEGER;
+          a: ARRAY 5 OF INTEGER;
+          p: ARRAY 2 OF INTEGER;
+    BEGIN
+     a[0] := 5; a[1] := 3; a[2] := 4; a[3] := 1; a[4] := 2;
+     i := 1;
+     WHILE i < 5 DO
+       j := i;
+       WHILE (j > 0) & (a[j - 1] > a[j]) DO
+         swap := a[j];
+         a[j] := a[j - 1];
+         a[j - 1] := swap;
+         j := j - 1
+       END;
+       i := i + 1
+     END;
+     p[0] := 4;
+     p[1] := a[a[p[0]] - 3]
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 1000,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base() + 4, 7), [1, 2, 3, 4, 5, 4, 3]);
+}
+
+#[test]
+fn arrays_can_have_multiple_dimensions() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR i, j: INTEGER;
+          m: ARRAY 2, 3 OF INTEGER;
+    BEGIN
+     FOR i := 0 TO 1 DO
+       FOR j := 0 TO 2 DO
+         m[i, j] := i * 10 + j
+       END
+     END;
+     i := 1;
+     j := 1;
+     m[0][0] := m[1, 2] + m[i - 1][j + 1]
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base() + 3, 6), [14, 1, 2, 10, 11, 12]);
+}
+
+#[test]
+fn arrays_accesses_out_of_bounds_trap() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR i, x: INTEGER;
+          a: ARRAY 3 OF INTEGER;
+    BEGIN
+     i := 3;
+     a[i - 1] := 1;
+     x := a[i]
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    let error = s.execute(execution).unwrap_err();
+    assert_matches!(error, ExecutionError::ArrayIndexOutOfBounds { line: 8, index: 3 });
+    assert_eq!(s.memory(s.static_base(), 6), [0, 3, 0, 0, 0, 1]);
+
+    let mut s = Simulator::from_oberon(&content.replace("a[i - 1]", "a[i - 4]")).unwrap();
+    let error = s.execute(execution).unwrap_err();
+    assert_matches!(error, ExecutionError::ArrayIndexOutOfBounds { line: 7, index: -1 });
+}
+
+#[test]
+fn arrays_bounds_checks_can_be_disabled() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR a: ARRAY 2 OF INTEGER;
+          x: INTEGER;
+          i: INTEGER;
+    BEGIN
+     i := 2;
+     a[i] := 42
+  END Test.",
+    );
+    let options = compiler::CompileOptions { bounds_checks: false };
+    let mut s = Simulator::from_oberon_with_options(&content, options).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    s.execute(execution).unwrap();
+    // The write lands in the variable after the array
+    assert_eq!(s.memory(s.static_base(), 5), [0, 0, 0, 42, 2]);
 }
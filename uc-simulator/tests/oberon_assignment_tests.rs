@@ -20,5 +20,5 @@ fn multiple_assignments_can_be_chained() {
         max_cycles: 20,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 3, 2]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 3, 2]);
 }
@@ -0,0 +1,77 @@
+#![feature(assert_matches)]
+use std::assert_matches::assert_matches;
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn standard_procedures_are_compiled_inline() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR a: ARRAY 3 OF INTEGER;
+        i, x, y, z, o, s: INTEGER;
+    BEGIN
+     i := 1;
+     a[i] := 10;
+     INC(a[i]);
+     INC(a[i], i + 4);
+     DEC(a[2], 3);
+     x := ABS(a[2]) + ABS(7);
+     y := LSL(1, 4) + ASR(-16, 2);
+     z := ROR(i, 1);
+     o := 0;
+     IF ODD(a[1] + 1) THEN o := 1 END;
+     IF ~ODD(a[1]) THEN INC(o, 2) END;
+     s := LSL(3, i);
+     ASSERT((x = 10) OR (y = 0))
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 300,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 11), [0, 0, 16, -3, 1, 10, 12, i32::MIN, 3, 6, 0]);
+}
+
+#[test]
+fn failed_assertions_trap_with_their_line() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR x: INTEGER;
+    BEGIN
+     x := 3;
+     ASSERT(x > 0);
+     ASSERT((x > 5) & (x < 10));
+     x := 4
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    let result = s.execute(execution);
+    assert_matches!(result, Err(ExecutionError::AssertionFailed { line: 7 }));
+    assert_eq!(s.memory(s.static_base(), 2), [0, 3]);
+}
+
+#[test]
+fn halt_stops_the_program_with_its_code() {
+    let content = String::from(
+        "
+  MODULE Test;
+    BEGIN
+     HALT(42)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    let result = s.execute(execution);
+    assert_matches!(result, Err(ExecutionError::Halted { line: 4, code: 42 }));
+}
@@ -0,0 +1,56 @@
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn characters_and_strings_can_be_assigned() {
+    let content = String::from(
+        "
+  MODULE Test;
+    CONST Bang = 21X;
+    VAR c, d: CHAR;
+        s: ARRAY 6 OF CHAR;
+        t: ARRAY 2, 4 OF CHAR;
+        n: INTEGER;
+    BEGIN
+     c := \"A\";
+     d := Bang;
+     s := \"Hello\";
+     t[1] := \"ok\";
+     IF (c < \"B\") & (s[1] = \"e\") THEN n := 1 ELSE n := 2 END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 200,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(
+        s.memory(s.static_base(), 18),
+        [0, 65, 33, 72, 101, 108, 108, 111, 0, 0, 0, 0, 0, 111, 107, 0, 0, 1]
+    );
+}
+
+#[test]
+fn strings_can_be_copied_through_var_parameters() {
+    let content = String::from(
+        "
+  MODULE Test;
+    TYPE Name = ARRAY 4 OF CHAR;
+    VAR s: Name;
+    PROCEDURE Greet(VAR g: Name);
+    BEGIN
+      g := \"Hi!\"
+    END Greet;
+    BEGIN
+     Greet(s)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 5), [0, 72, 105, 33, 0]);
+}
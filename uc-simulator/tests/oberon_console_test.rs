@@ -0,0 +1,81 @@
+#![feature(assert_matches)]
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn out_module_prints_to_the_console() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR i: INTEGER;
+        name: ARRAY 8 OF CHAR;
+    BEGIN
+     name := \"Oberon\";
+     Out.String(\"Hello, \");
+     Out.String(name);
+     Out.Char(21X);
+     Out.Ln;
+     i := 1;
+     WHILE i <= 3 DO
+       Out.Int(i * i, 3);
+       i := i + 1
+     END;
+     Out.Ln();
+     Out.Int(-42)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "Hello, Oberon!\n  1  4  9\n-42");
+}
+
+#[test]
+fn out_string_stops_at_the_end_of_the_array() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR name: ARRAY 3 OF CHAR;
+    BEGIN
+     name[0] := \"a\";
+     name[1] := \"b\";
+     name[2] := \"c\";
+     Out.String(name)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "abc");
+}
+
+#[test]
+fn in_int_reads_from_the_input() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR a: ARRAY 2 OF INTEGER;
+        x: INTEGER;
+    BEGIN
+     In.Int(a[1]);
+     In.Int(x);
+     Out.Int(a[1] + x);
+     In.Int(x)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    s.set_input(vec![40, 2].into_iter());
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.output(), "42");
+    assert_eq!(s.memory(s.static_base(), 4), [0, 0, 40, 0]);
+}
@@ -0,0 +1,33 @@
+#![feature(assert_matches)]
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn constants_can_be_used_in_expressions_and_array_sizes() {
+    let content = String::from(
+        "
+  MODULE Test;
+    CONST N = 3;
+          Last = N - 1;
+          Debug = FALSE;
+    TYPE Vec = ARRAY N OF INTEGER;
+    VAR x: INTEGER;
+        v: Vec;
+    BEGIN
+     v[0] := N;
+     v[Last] := N * 10;
+     IF Debug THEN
+       x := 1
+     ELSE
+       x := v[0] + v[Last]
+     END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 5), [0, 33, 3, 0, 30]);
+}
@@ -1,4 +1,5 @@
 #![feature(assert_matches)]
+use std::assert_matches::assert_matches;
 use simulator::Simulator;
 use simulator::*;
 
@@ -20,7 +21,32 @@ fn statement_is_not_executed_if_condition_is_false() {
         max_cycles: 20,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 2), [0, 0]);
+    assert_eq!(s.memory(s.static_base(), 2), [0, 0]);
+}
+
+#[test]
+fn statement_following_an_if_is_executed() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y, z: INTEGER;
+    BEGIN
+      IF 0 = 1 THEN
+        x:= 1
+      END;
+      y := 2;
+      IF 0 = 1 THEN
+        x:= 3
+      ELSE
+        x:= 4
+      END;
+      z := 5
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 40 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 4), [0, 4, 2, 5]);
 }
 
 #[test]
@@ -38,7 +64,7 @@ fn statement_is_executed_if_condition_is_true() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 20 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 2), [0, 1]);
+    assert_eq!(s.memory(s.static_base(), 2), [0, 1]);
 }
 
 #[test]
@@ -62,7 +88,7 @@ fn variable_statement_is_executed_if_condition_is_true() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 20 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 2), [0, 4]);
+    assert_eq!(s.memory(s.static_base(), 2), [0, 4]);
 }
 
 #[test]
@@ -81,7 +107,7 @@ fn all_statements_are_executed_if_condition_is_true() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 20 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 1, 2]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 1, 2]);
 }
 
 #[test]
@@ -102,7 +128,7 @@ fn else_statement_is_executed_if_condition_is_false() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 20 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 2, 3]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 2, 3]);
 }
 
 #[test]
@@ -129,7 +155,7 @@ fn more_nested_else_if() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 20 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 3, 0]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 3, 0]);
 }
 
 #[test]
@@ -155,7 +181,7 @@ fn more_nested_else_if_but_the_other_way_around() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 50 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 2), [0, 1]);
+    assert_eq!(s.memory(s.static_base(), 2), [0, 1]);
 }
 
 #[test]
@@ -190,7 +216,7 @@ fn nested_else_statement_is_executed_if_condition_is_false() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution { stack_base: 100, max_cycles: 50 };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 5, 4]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 5, 4]);
 }
 
 /*
@@ -213,7 +239,7 @@ fn elseif_statement_is_executed_if_other_condition_is_true() {
     max_cycles: 20
   };
   s.execute(execution).unwrap();
-  assert_eq!(s.memory(execution.stack_base, 2), [0, 2]);
+  assert_eq!(s.memory(s.static_base(), 2), [0, 2]);
 }
 
 #[test]
@@ -237,7 +263,7 @@ fn elseif_statement_is_not_executed_if_other_condition_is_false() {
     max_cycles: 20
   };
   s.execute(execution).unwrap();
-  assert_eq!(s.memory(execution.stack_base, 2), [0, 3]);
+  assert_eq!(s.memory(s.static_base(), 2), [0, 3]);
 }
 
 
@@ -264,7 +290,7 @@ fn elseif_statements_are_executed_if_some_conditions_is_true() {
     max_cycles: 20
   };
   s.execute(execution).unwrap();
-  assert_eq!(s.memory(execution.stack_base, 2), [0, 3]);
+  assert_eq!(s.memory(s.static_base(), 2), [0, 3]);
 }
 
 
@@ -291,6 +317,231 @@ fn elseif_statements_are_not_executed_if_all_other_conditions_are_false() {
     max_cycles: 20
   };
   s.execute(execution).unwrap();
-  assert_eq!(s.memory(execution.stack_base, 2), [0, 4]);
+  assert_eq!(s.memory(s.static_base(), 2), [0, 4]);
 }
 */
+
+#[test]
+fn conjunction_is_short_circuited() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y: INTEGER;
+    BEGIN
+      x := 0;
+      y := 1;
+      IF (x # 0) & (10 / x > 1) THEN
+        y := 2
+      END;
+      IF (y = 1) & (x = 0) THEN
+        x := 3
+      END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 50 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 3, 1]);
+}
+
+#[test]
+fn disjunction_is_short_circuited() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y: INTEGER;
+    BEGIN
+      x := 0;
+      IF (x = 0) OR (10 / x > 1) THEN
+        y := 1
+      ELSE
+        y := 2
+      END;
+      IF (x = 1) OR (x = 2) OR ~(x < 1) THEN
+        x := 3
+      END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 50 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 0, 1]);
+}
+
+#[test]
+fn boolean_variables_can_be_assigned_and_tested() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR b, c: BOOLEAN;
+          x, i: INTEGER;
+    BEGIN
+      b := TRUE;
+      c := (x = 0) & ~b;
+      i := 0;
+      WHILE b & (i < 5) DO
+        i := i + 1;
+        b := (i # 3) OR c
+      END;
+      IF ~c THEN
+        x := i
+      END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 200 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 5), [0, 0, 0, 3, 3]);
+}
+
+#[test]
+fn only_the_first_matching_elsif_branch_is_executed() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x, y, z: INTEGER;
+    BEGIN
+      x := 2;
+      IF x = 1 THEN
+        y := 10
+      ELSIF x = 2 THEN
+        y := 20
+      ELSIF x > 1 THEN
+        y := 30
+      ELSE
+        y := 40
+      END;
+      IF x = 1 THEN
+        z := 10
+      ELSIF x = 3 THEN
+        z := 30
+      ELSE
+        z := 40
+      END;
+      IF x = 1 THEN
+        x := 10
+      ELSIF x = 3 THEN
+        x := 30
+      END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 100 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 4), [0, 2, 20, 40]);
+}
+
+fn case_program(x: i32) -> String {
+    format!(
+        "
+  MODULE Test;
+      VAR x, dense, sparse: INTEGER;
+    BEGIN
+      x := {x};
+      CASE x OF
+        1, 3..5: dense := 1
+      | 2: dense := 2
+      | 7: dense := 7
+      ELSE dense := 0
+      END;
+      CASE x OF
+        2: sparse := 2
+      | 100..110: sparse := 100
+      ELSE sparse := 0
+      END
+  END Test."
+    )
+}
+
+#[test]
+fn case_statement_executes_the_matching_case() {
+    let expected = [(0, 0, 0), (1, 1, 0), (2, 2, 2), (4, 1, 0), (5, 1, 0), (6, 0, 0), (7, 7, 0), (8, 0, 0), (105, 0, 100)];
+    for (x, dense, sparse) in expected {
+        let mut s = Simulator::from_oberon(&case_program(x)).unwrap();
+        let execution = Execution { stack_base: 100, max_cycles: 100 };
+        s.execute(execution).unwrap();
+        assert_eq!(s.memory(s.static_base(), 4), [0, x, dense, sparse], "CASE with x = {}", x);
+    }
+}
+
+#[test]
+fn case_statement_without_else_traps_when_no_label_matches() {
+    // The first CASE is compiled to a jump table, the second one to comparisons
+    let content = |x: i32| {
+        format!(
+            "
+  MODULE Test;
+      VAR x, y: INTEGER;
+    BEGIN
+      x := {x};
+      CASE x OF 1: y := 1 | 2..3: y := 2 | 6: y := 6 END;
+      CASE x OF
+        2: y := 3
+      END
+  END Test."
+        )
+    };
+    let execution = Execution { stack_base: 100, max_cycles: 100 };
+
+    let mut s = Simulator::from_oberon(&content(4)).unwrap();
+    assert_matches!(s.execute(execution), Err(ExecutionError::CaseNotMatched { line: 6, selector: 4 }));
+
+    let mut s = Simulator::from_oberon(&content(-7)).unwrap();
+    assert_matches!(s.execute(execution), Err(ExecutionError::CaseNotMatched { line: 6, selector: -7 }));
+
+    let mut s = Simulator::from_oberon(&content(1)).unwrap();
+    assert_matches!(s.execute(execution), Err(ExecutionError::CaseNotMatched { line: 7, selector: 1 }));
+
+    let mut s = Simulator::from_oberon(&content(2)).unwrap();
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 2, 3]);
+}
+
+#[test]
+fn case_statement_with_labels_far_apart_uses_comparisons() {
+    let content = |x: i32| {
+        format!(
+            "
+  MODULE Test;
+      VAR x, y: INTEGER;
+    BEGIN
+      x := {x};
+      CASE x OF
+        -2000000000: y := 1
+      | 2000000000: y := 2
+      ELSE y := 3
+      END
+  END Test."
+        )
+    };
+    for (x, y) in [(-2000000000, 1), (2000000000, 2), (0, 3), (-2147483647, 3)] {
+        let mut s = Simulator::from_oberon(&content(x)).unwrap();
+        let execution = Execution { stack_base: 100, max_cycles: 100 };
+        s.execute(execution).unwrap();
+        assert_eq!(s.memory(s.static_base(), 3), [0, x, y], "CASE with x = {}", x);
+    }
+}
+
+#[test]
+fn case_statement_with_constant_and_character_labels() {
+    let content = |op: i32, c: char| {
+        format!(
+            "
+  MODULE Test;
+      CONST Add = 1; Sub = Add + 1;
+      VAR op, y: INTEGER; c: CHAR;
+    BEGIN
+      op := {op};
+      c := \"{c}\";
+      CASE op OF Add: y := 10 | Sub: y := 20 ELSE y := 0 END;
+      CASE c OF \"a\"..\"c\": y := y + 1 | \"x\", 7AX: y := y + 2 END
+  END Test."
+        )
+    };
+    for (op, c, y) in [(1, 'a', 11), (2, 'c', 21), (3, 'x', 2), (1, 'z', 12)] {
+        let mut s = Simulator::from_oberon(&content(op, c)).unwrap();
+        let execution = Execution { stack_base: 100, max_cycles: 100 };
+        s.execute(execution).unwrap();
+        assert_eq!(s.memory(s.static_base(), 3), [0, op, y], "CASE with op = {} and c = {}", op, c);
+    }
+}
@@ -21,7 +21,7 @@ fn while_statement_is_executed_until_condition_is_false() {
         max_cycles: 50,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 2), [0, 2]);
+    assert_eq!(s.memory(s.static_base(), 2), [0, 2]);
 }
 
 #[test]
@@ -45,5 +45,54 @@ fn while_statement_is_executed_until_condition_is_false_with_nested_body() {
         max_cycles: 60,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 3, 4]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 3, 4]);
+}
+
+#[test]
+fn repeat_statement_is_executed_at_least_once() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x,y: INTEGER;
+    BEGIN
+      x := 0;
+      REPEAT
+        x := x + 1
+      UNTIL x >= 3;
+      y := 10;
+      REPEAT
+        y := y + 1
+      UNTIL y > 0
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 100 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 3, 11]);
+}
+
+#[test]
+fn for_statement_counts_up_and_down() {
+    let content = String::from(
+        "
+  MODULE Test;
+      CONST Step = -3;
+      VAR i, up, down, never: INTEGER;
+    BEGIN
+      FOR i := 1 TO 10 DO
+        up := up + i
+      END;
+      FOR i := 10 TO 0 BY Step DO
+        down := down + i
+      END;
+      FOR i := 5 TO 4 DO
+        never := 1
+      END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 100, max_cycles: 300 };
+    s.execute(execution).unwrap();
+    // 10 + 7 + 4 + 1
+    assert_eq!(s.memory(s.static_base(), 5), [0, 5, 55, 22, 0]);
 }
@@ -0,0 +1,146 @@
+#![feature(assert_matches)]
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn procedures_can_be_called() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+          y: INTEGER;
+    PROCEDURE SetX;
+    BEGIN
+      x := 42
+    END SetX;
+    BEGIN
+     SetX;
+     y := x + 1
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution { stack_base: 1000, max_cycles: 50 };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 42, 43]);
+}
+
+#[test]
+fn procedures_can_take_value_and_var_parameters() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+          y: INTEGER;
+          a: ARRAY 2 OF INTEGER;
+    PROCEDURE Add(i: INTEGER; j: INTEGER; VAR r: INTEGER);
+      VAR k: INTEGER;
+    BEGIN
+      k := i + j;
+      i := 0;
+      r := k
+    END Add;
+    BEGIN
+     x := 1;
+     Add(x, 2, y);
+     Add(x, y, a[1])
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 5), [0, 1, 3, 0, 4]);
+}
+
+#[test]
+fn procedures_can_be_recursive() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+    PROCEDURE Fact(n: INTEGER; VAR r: INTEGER);
+      VAR p: INTEGER;
+    BEGIN
+      IF n <= 1 THEN
+        r := 1
+      ELSE
+        Fact(n - 1, p);
+        r := n * p
+      END
+    END Fact;
+    BEGIN
+     Fact(5, x)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 2), [0, 120]);
+}
+
+#[test]
+fn procedures_can_call_other_procedures() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR x: INTEGER;
+    PROCEDURE Double(VAR v: INTEGER);
+    BEGIN
+      v := v * 2
+    END Double;
+    PROCEDURE Quadruple(VAR v: INTEGER);
+    BEGIN
+      Double(v);
+      Double(v)
+    END Quadruple;
+    BEGIN
+     x := 3;
+     Quadruple(x)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 2), [0, 12]);
+}
+
+#[test]
+fn module_variables_are_not_on_the_stack() {
+    let content = String::from(
+        "
+  MODULE Test;
+      VAR result: INTEGER;
+    PROCEDURE Sum(n: INTEGER; VAR total: INTEGER);
+      VAR rest: INTEGER;
+    BEGIN
+      IF n > 0 THEN
+        Sum(n - 1, rest);
+        total := rest + n
+      ELSE
+        total := 0
+      END
+    END Sum;
+    BEGIN
+     Sum(4, result)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    // The stack grows down from the end of the memory, and the frames of the recursive calls
+    // do not overwrite the static area
+    let execution = Execution {
+        stack_base: risc::computer::MEMORY_SIZE,
+        max_cycles: 500,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 10, 0]);
+    assert_eq!(s.registers()[13] as usize, s.static_base());
+    assert_eq!(s.registers()[14] as usize, risc::computer::MEMORY_SIZE);
+}
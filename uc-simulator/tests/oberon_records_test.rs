@@ -0,0 +1,83 @@
+#![feature(assert_matches)]
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn record_fields_can_be_set_and_read() {
+    let content = String::from(
+        "
+  MODULE Test;
+    TYPE Point = RECORD x, y: INTEGER END;
+    VAR p: Point;
+        s: INTEGER;
+    BEGIN
+     p.x := 3;
+     p.y := 4;
+     s := p.x * p.y
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 50,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 4), [0, 3, 4, 12]);
+}
+
+#[test]
+fn records_can_be_nested_in_arrays() {
+    let content = String::from(
+        "
+  MODULE Test;
+    TYPE Point = RECORD x, y: INTEGER END;
+         Segment = RECORD ends: ARRAY 2 OF Point END;
+    VAR i: INTEGER;
+        p: ARRAY 3 OF Point;
+        s: Segment;
+    BEGIN
+     i := 0;
+     WHILE i < 3 DO
+       p[i].x := i;
+       p[i].y := i * 10;
+       i := i + 1
+     END;
+     i := 1;
+     s.ends[i].y := p[2].y + p[i].x
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 100,
+        max_cycles: 200,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 12), [0, 1, 0, 0, 1, 10, 2, 20, 0, 0, 0, 21]);
+}
+
+#[test]
+fn records_can_be_passed_by_reference() {
+    let content = String::from(
+        "
+  MODULE Test;
+    TYPE Point = RECORD x, y: INTEGER END;
+    VAR p: Point;
+    PROCEDURE Move(VAR q: Point; dx: INTEGER);
+    BEGIN
+      q.x := q.x + dx;
+      q.y := q.y + 1
+    END Move;
+    BEGIN
+     p.x := 1;
+     Move(p, 2);
+     Move(p, 3)
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 3), [0, 6, 2]);
+}
@@ -0,0 +1,61 @@
+use simulator::Simulator;
+use simulator::*;
+
+#[test]
+fn sets_can_be_built_and_combined() {
+    let content = String::from(
+        "
+  MODULE Test;
+    VAR a, b, c, d, e: SET;
+        i, n, m: INTEGER;
+    BEGIN
+     i := 2;
+     a := {i, 5..7};
+     b := {i..i + 2} + {31};
+     c := a * b;
+     d := a / b;
+     e := -a - {0};
+     n := 0;
+     IF 5 IN a THEN n := n + 1 END;
+     IF ~(i + 1 IN a) THEN n := n + 2 END;
+     IF 31 IN b THEN n := n + 4 END;
+     m := 0;
+     FOR i := 0 TO 31 DO
+       IF i IN e THEN m := m + 1 END
+     END
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 2000,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(
+        s.memory(s.static_base(), 9),
+        [0, 0b1110_0100, 0b1_1100 | i32::MIN, 0b100, 0b1111_1000 | i32::MIN, !0b1110_0101, 32, 7, 27]
+    );
+}
+
+#[test]
+fn membership_can_be_stored_in_booleans() {
+    let content = String::from(
+        "
+  MODULE Test;
+    CONST Vowels = {0, 4, 8, 14, 20};
+    VAR has, lacks: BOOLEAN;
+        s: SET;
+    BEGIN
+     s := Vowels;
+     has := 4 IN s;
+     lacks := 5 IN s
+  END Test.",
+    );
+    let mut s = Simulator::from_oberon(&content).unwrap();
+    let execution = Execution {
+        stack_base: 1000,
+        max_cycles: 100,
+    };
+    s.execute(execution).unwrap();
+    assert_eq!(s.memory(s.static_base(), 4), [0, 1, 0, 0b1_0000_0100_0001_0001_0001]);
+}
@@ -1,6 +1,7 @@
 #![feature(assert_matches)]
 use assembler::*;
 use ast::parser::*;
+use compiler::{CompileError, ErrorKind};
 use simulator::Simulator;
 use simulator::*;
 use std::assert_matches::assert_matches;
@@ -87,7 +88,13 @@ fn primes() {
 fn invalid_oberon() {
     let content = String::from("INVALID OBERON");
     let s = Simulator::from_oberon(&content);
-    assert_matches!(s, Err(ParseError::UnexpectedToken(_)));
+    assert_matches!(
+        s,
+        Err(CompileError {
+            kind: ErrorKind::Parse(ParseError::UnexpectedToken { .. }),
+            ..
+        })
+    );
 }
 
 #[test]
@@ -96,10 +103,10 @@ fn oberon_assignments() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution {
         stack_base: 100,
-        max_cycles: 5,
+        max_cycles: 6,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 42, 42]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 42, 42]);
 }
 
 #[test]
@@ -111,5 +118,5 @@ fn oberon_arithmetic() {
         max_cycles: 20,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 3), [0, 42, 18]);
+    assert_eq!(s.memory(s.static_base(), 3), [0, 42, 18]);
 }
@@ -20,8 +20,8 @@ fn full_program_generate_list_of_5_squares() {
     let mut s = Simulator::from_oberon(&content).unwrap();
     let execution = Execution {
         stack_base: 100,
-        max_cycles: 100,
+        max_cycles: 150,
     };
     s.execute(execution).unwrap();
-    assert_eq!(s.memory(execution.stack_base, 7), [0, 5, 0, 1, 4, 9, 16]);
+    assert_eq!(s.memory(s.static_base(), 7), [0, 5, 0, 1, 4, 9, 16]);
 }